@@ -1,13 +1,17 @@
 use mcp_core::protocol::{
-    CallToolResult, Implementation, InitializeResult, JsonRpcError, JsonRpcMessage,
-    JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, ListResourcesResult, ListToolsResult,
-    ReadResourceResult, ServerCapabilities, METHOD_NOT_FOUND,
+    CallToolResult, CancelledNotificationParams, CreateMessageParams, CreateMessageResult,
+    ErrorData, GetPromptResult, Implementation, InitializeResult, JsonRpcError, JsonRpcMessage,
+    JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, ListPromptsResult, ListResourcesResult,
+    ListToolsResult, ProgressToken, ReadResourceResult, ServerCapabilities, INTERNAL_ERROR,
+    INVALID_PARAMS, METHOD_NOT_FOUND,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tower::{Service, ServiceExt}; // for Service::ready()
 
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
@@ -63,7 +67,22 @@ pub struct ClientInfo {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
-    // Add fields as needed. For now, empty capabilities are fine.
+    /// Set when the client answers `sampling/createMessage` requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SamplingCapability {}
+
+/// Answers the `sampling/createMessage` requests in which a server asks for a completion
+/// from the client's model
+#[async_trait::async_trait]
+pub trait SamplingHandler: Send + Sync {
+    async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData>;
 }
 
 #[derive(Serialize, Deserialize)]
@@ -93,6 +112,32 @@ pub trait McpClientTrait: Send + Sync {
     async fn list_tools(&self, next_cursor: Option<String>) -> Result<ListToolsResult, Error>;
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error>;
+
+    /// Call a tool, asking the server to send `notifications/progress` with the given token
+    /// while it runs. Clients that can't ask for progress just call the tool
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        _progress_token: ProgressToken,
+    ) -> Result<CallToolResult, Error> {
+        self.call_tool(name, arguments).await
+    }
+
+    async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error>;
+
+    async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error>;
+
+    /// Ask the server to send `notifications/resources/updated` when the resource changes
+    async fn subscribe_resource(&self, uri: &str) -> Result<(), Error>;
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), Error>;
+
+    /// Subscribe to the notifications the server sends
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification>;
+
+    /// Send an arbitrary JSON-RPC request and return its raw result
+    async fn request(&self, method: &str, params: Value) -> Result<Value, Error>;
 }
 
 /// The MCP client is the interface for MCP operations.
@@ -106,6 +151,8 @@ where
     next_id: AtomicU64,
     server_capabilities: Option<ServerCapabilities>,
     server_info: Option<Implementation>,
+    notifications: broadcast::Receiver<JsonRpcNotification>,
+    request_timeout: Option<Duration>,
 }
 
 impl<S> McpClient<S>
@@ -115,50 +162,123 @@ where
     S::Future: Send,
 {
     pub fn new(service: S) -> Self {
+        // Without a transport to listen to, subscribers only ever see a closed channel
+        let (_, notifications) = broadcast::channel(1);
         Self {
             service: Mutex::new(service),
             next_id: AtomicU64::new(1),
             server_capabilities: None,
             server_info: None,
+            notifications,
+            request_timeout: None,
         }
     }
 
+    /// Give up on requests other than tool calls after a timeout
+    ///
+    /// Tool calls are left to the caller, which knows how long each tool may take.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Answer the requests the server sends, usually taken with `TransportHandle::take_requests`
+    ///
+    /// `sampling/createMessage` goes to the sampling handler, `ping` is answered directly and
+    /// any other method is rejected. Must be called within a Tokio runtime.
+    pub fn with_requests(
+        mut self,
+        mut requests: mpsc::Receiver<JsonRpcRequest>,
+        sampling: Option<Arc<dyn SamplingHandler>>,
+    ) -> Self {
+        let service = self.service.get_mut().clone();
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                // Answer concurrently, as completions can take a while
+                let mut service = service.clone();
+                let sampling = sampling.clone();
+                tokio::spawn(async move {
+                    let id = request.id;
+                    let result = handle_request(sampling.as_deref(), request).await;
+                    let response = JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id,
+                        error: result.as_ref().err().cloned(),
+                        result: result.ok(),
+                    });
+
+                    if service.ready().await.is_err() {
+                        tracing::warn!("Could not answer request {:?} from server", id);
+                        return;
+                    }
+                    if let Err(e) = service.call(response).await {
+                        let e: Error = e.into();
+                        tracing::warn!(error = %e, "Could not answer request from server");
+                    }
+                });
+            }
+        });
+        self
+    }
+
+    /// Receive the server's notifications from the transport, usually `TransportHandle::subscribe`
+    pub fn with_notifications(
+        mut self,
+        notifications: broadcast::Receiver<JsonRpcNotification>,
+    ) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
     /// Send a JSON-RPC request and check we don't get an error response.
     async fn send_request<R>(&self, method: &str, params: Value) -> Result<R, Error>
     where
         R: for<'de> Deserialize<'de>,
     {
-        let mut service = self.service.lock().await;
+        // Each request gets its own handle on the service, so requests can run concurrently
+        let mut service = self.service.lock().await.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request_id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let request = JsonRpcMessage::Request(JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
-            id: Some(id),
+            id: Some(request_id),
             method: method.to_string(),
             params: Some(params.clone()),
         });
 
-        let response_msg = service
-            .call(request)
-            .await
-            .map_err(|e| Error::McpServerError {
-                server: self
-                    .server_info
-                    .as_ref()
-                    .map(|s| s.name.clone())
-                    .unwrap_or("".to_string()),
-                method: method.to_string(),
-                // we don't need include params because it can be really large
-                source: Box::new(e.into()),
-            })?;
+        // Tell the server to stop working on the request if we stop waiting for its response,
+        // which the protocol doesn't allow for initialize
+        let mut cancel = CancelOnDrop {
+            service: (method != "initialize").then(|| service.clone()),
+            request_id,
+        };
+
+        let response = service.call(request);
+        let response = match self.request_timeout {
+            Some(timeout) if method != "tools/call" => tokio::time::timeout(timeout, response)
+                .await
+                .map_err(|_| Error::Timeout(tower::timeout::error::Elapsed::new()))?,
+            _ => response.await,
+        };
+        let response_msg = response.map_err(|e| Error::McpServerError {
+            server: self
+                .server_info
+                .as_ref()
+                .map(|s| s.name.clone())
+                .unwrap_or("".to_string()),
+            method: method.to_string(),
+            // we don't need include params because it can be really large
+            source: Box::new(e.into()),
+        })?;
+        cancel.service = None;
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse {
                 id, result, error, ..
             }) => {
                 // Verify id matches
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcResponse".to_string(),
                     ));
@@ -175,7 +295,7 @@ where
                 }
             }
             JsonRpcMessage::Error(JsonRpcError { id, error, .. }) => {
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcError".to_string(),
                     ));
@@ -222,10 +342,105 @@ where
         Ok(())
     }
 
+    /// Send a `tools/call` request with the given params
+    async fn send_tool_call(&self, params: Value) -> Result<CallToolResult, Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        // If tools is not supported, return an error
+        if self.server_capabilities.as_ref().unwrap().tools.is_none() {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support 'tools' capability".to_string(),
+            });
+        }
+
+        // TODO ERROR: check that if there is an error, we send back is_error: true with msg
+        // https://modelcontextprotocol.io/docs/concepts/tools#error-handling-2
+        self.send_request("tools/call", params).await
+    }
+
     // Check if the client has completed initialization
     fn completed_initialization(&self) -> bool {
         self.server_capabilities.is_some()
     }
+
+    // Check if the server supports subscribing to resources
+    fn supports_resource_subscriptions(&self) -> bool {
+        self.server_capabilities
+            .as_ref()
+            .and_then(|c| c.resources.as_ref())
+            .and_then(|r| r.subscribe)
+            .unwrap_or(false)
+    }
+}
+
+/// Sends `notifications/cancelled` for a request when dropped, unless its service was taken
+/// because the response arrived
+struct CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage, Response = JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    service: Option<S>,
+    request_id: u64,
+}
+
+impl<S> Drop for CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage, Response = JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    fn drop(&mut self) {
+        let Some(mut service) = self.service.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/cancelled".to_string(),
+            params: serde_json::to_value(CancelledNotificationParams {
+                request_id: self.request_id,
+                reason: Some("The client stopped waiting for the response".to_string()),
+            })
+            .ok(),
+        });
+        runtime.spawn(async move {
+            if service.ready().await.is_ok() {
+                let _ = service.call(notification).await;
+            }
+        });
+    }
+}
+
+/// Work out the result of a request the server sent to the client
+async fn handle_request(
+    sampling: Option<&dyn SamplingHandler>,
+    request: JsonRpcRequest,
+) -> Result<Value, ErrorData> {
+    let error = |code, message: String| ErrorData {
+        code,
+        message,
+        data: None,
+    };
+
+    match (request.method.as_str(), sampling) {
+        ("ping", _) => Ok(serde_json::json!({})),
+        ("sampling/createMessage", Some(sampling)) => {
+            let params: CreateMessageParams =
+                serde_json::from_value(request.params.unwrap_or_default())
+                    .map_err(|e| error(INVALID_PARAMS, e.to_string()))?;
+            let result = sampling.create_message(params).await?;
+            serde_json::to_value(result).map_err(|e| error(INTERNAL_ERROR, e.to_string()))
+        }
+        (method, _) => Err(error(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    }
 }
 
 #[async_trait::async_trait]
@@ -329,21 +544,103 @@ where
     }
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error> {
+        let params = serde_json::json!({ "name": name, "arguments": arguments });
+        self.send_tool_call(params).await
+    }
+
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: ProgressToken,
+    ) -> Result<CallToolResult, Error> {
+        let params = serde_json::json!({
+            "name": name,
+            "arguments": arguments,
+            "_meta": { "progressToken": progress_token },
+        });
+        self.send_tool_call(params).await
+    }
+
+    async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error> {
         if !self.completed_initialization() {
             return Err(Error::NotInitialized);
         }
-        // If tools is not supported, return an error
-        if self.server_capabilities.as_ref().unwrap().tools.is_none() {
+        // If prompts is not supported, return an empty list
+        if self.server_capabilities.as_ref().unwrap().prompts.is_none() {
+            return Ok(ListPromptsResult {
+                prompts: vec![],
+                next_cursor: None,
+            });
+        }
+
+        let payload = next_cursor
+            .map(|cursor| serde_json::json!({"cursor": cursor}))
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        self.send_request("prompts/list", payload).await
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        // If prompts is not supported, return an error
+        if self.server_capabilities.as_ref().unwrap().prompts.is_none() {
             return Err(Error::RpcError {
                 code: METHOD_NOT_FOUND,
-                message: "Server does not support 'tools' capability".to_string(),
+                message: "Server does not support 'prompts' capability".to_string(),
             });
         }
 
         let params = serde_json::json!({ "name": name, "arguments": arguments });
+        self.send_request("prompts/get", params).await
+    }
 
-        // TODO ERROR: check that if there is an error, we send back is_error: true with msg
-        // https://modelcontextprotocol.io/docs/concepts/tools#error-handling-2
-        self.send_request("tools/call", params).await
+    async fn subscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        // If resource subscriptions are not supported, return an error
+        if !self.supports_resource_subscriptions() {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support subscribing to resources".to_string(),
+            });
+        }
+
+        let params = serde_json::json!({ "uri": uri });
+        self.send_request::<Value>("resources/subscribe", params)
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        if !self.supports_resource_subscriptions() {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support subscribing to resources".to_string(),
+            });
+        }
+
+        let params = serde_json::json!({ "uri": uri });
+        self.send_request::<Value>("resources/unsubscribe", params)
+            .await?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.resubscribe()
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+
+        self.send_request(method, params).await
     }
 }
@@ -1,10 +1,15 @@
 use async_trait::async_trait;
-use mcp_core::protocol::JsonRpcMessage;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest};
 use std::collections::HashMap;
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
 
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
+
+/// How many server notifications are buffered for each subscriber before the oldest are dropped
+pub const NOTIFICATION_CHANNEL_CAPACITY: usize = 64;
+/// How many requests from the server are queued before further ones are dropped
+pub const REQUEST_CHANNEL_CAPACITY: usize = 32;
 /// A generic error type for transport operations.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -20,7 +25,9 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
-    #[error("Unsupported message type. JsonRpcMessage can only be Request or Notification.")]
+    #[error(
+        "Unsupported message type. JsonRpcMessage can only be Request, Notification or Response."
+    )]
     UnsupportedMessage,
 
     #[error("Stdio process error: {0}")]
@@ -29,6 +36,9 @@ pub enum Error {
     #[error("SSE connection error: {0}")]
     SseConnection(String),
 
+    #[error("Streamable HTTP error: {0}")]
+    StreamableHttpError(String),
+
     #[error("HTTP error: {status} - {message}")]
     HttpError { status: u16, message: String },
 }
@@ -58,6 +68,13 @@ pub trait Transport {
 #[async_trait]
 pub trait TransportHandle: Send + Sync + Clone + 'static {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error>;
+
+    /// Subscribe to the notifications the server sends, such as `notifications/tools/list_changed`
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification>;
+
+    /// Take the requests the server sends to the client, such as `sampling/createMessage`.
+    /// Only the first call gets the receiver; answers are sent back with `send`.
+    async fn take_requests(&self) -> Option<mpsc::Receiver<JsonRpcRequest>>;
 }
 
 // Helper function that contains the common send implementation
@@ -75,9 +92,12 @@ pub async fn send_message(
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
             Ok(response.await.map_err(|_| Error::ChannelClosed)??)
         }
-        JsonRpcMessage::Notification(notification) => {
+        // Notifications and our answers to the server's requests get no response
+        message @ (JsonRpcMessage::Notification(_)
+        | JsonRpcMessage::Response(_)
+        | JsonRpcMessage::Error(_)) => {
             let msg = TransportMessage {
-                message: JsonRpcMessage::Notification(notification),
+                message,
                 response_tx: None,
             };
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
@@ -121,7 +141,10 @@ impl PendingRequests {
 }
 
 pub mod stdio;
-pub use stdio::StdioTransport;
+pub use stdio::{StdioTransport, StdioTransportHandle};
 
 pub mod sse;
 pub use sse::SseTransport;
+
+pub mod streamable_http;
+pub use streamable_http::StreamableHttpTransport;
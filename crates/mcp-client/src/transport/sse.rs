@@ -1,12 +1,15 @@
-use crate::transport::{Error, PendingRequests, TransportMessage};
+use crate::transport::{
+    Error, PendingRequests, TransportMessage, NOTIFICATION_CHANNEL_CAPACITY,
+    REQUEST_CHANNEL_CAPACITY,
+};
 use async_trait::async_trait;
 use eventsource_client::{Client, SSE};
 use futures::TryStreamExt;
-use mcp_core::protocol::{JsonRpcMessage, JsonRpcRequest};
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest};
 use reqwest::Client as HttpClient;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
 use tokio::time::{timeout, Duration};
 use tracing::warn;
 use url::Url;
@@ -24,8 +27,14 @@ pub struct SseActor {
     receiver: mpsc::Receiver<TransportMessage>,
     /// Map of request-id -> oneshot sender
     pending_requests: Arc<PendingRequests>,
+    /// Where notifications from the server are published
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    /// Where requests from the server are queued for the client to answer
+    requests: mpsc::Sender<JsonRpcRequest>,
     /// Base SSE URL
     sse_url: String,
+    /// Sent with the SSE request and every POST, such as an `Authorization` header
+    headers: HashMap<String, String>,
     /// For sending HTTP POST requests
     http_client: HttpClient,
     /// The discovered endpoint for POST requests (once "endpoint" SSE event arrives)
@@ -36,13 +45,19 @@ impl SseActor {
     pub fn new(
         receiver: mpsc::Receiver<TransportMessage>,
         pending_requests: Arc<PendingRequests>,
+        notifications: broadcast::Sender<JsonRpcNotification>,
+        requests: mpsc::Sender<JsonRpcRequest>,
         sse_url: String,
+        headers: HashMap<String, String>,
         post_endpoint: Arc<RwLock<Option<String>>>,
     ) -> Self {
         Self {
             receiver,
             pending_requests,
+            notifications,
+            requests,
             sse_url,
+            headers,
             post_endpoint,
             http_client: HttpClient::new(),
         }
@@ -55,12 +70,16 @@ impl SseActor {
         tokio::join!(
             Self::handle_incoming_messages(
                 self.sse_url.clone(),
+                self.headers.clone(),
                 Arc::clone(&self.pending_requests),
+                self.notifications.clone(),
+                self.requests.clone(),
                 Arc::clone(&self.post_endpoint)
             ),
             Self::handle_outgoing_messages(
                 self.receiver,
                 self.http_client.clone(),
+                self.headers.clone(),
                 Arc::clone(&self.post_endpoint),
                 Arc::clone(&self.pending_requests),
             )
@@ -70,20 +89,29 @@ impl SseActor {
     /// Continuously reads SSE events from `sse_url`.
     /// - If an `endpoint` event is received, store it in `post_endpoint`.
     /// - If a `message` event is received, parse it as `JsonRpcMessage`
-    ///   and respond to pending requests if it's a `Response`.
+    ///   and respond to pending requests if it's a `Response`, publish it if it's
+    ///   a `Notification`, or queue it for the client if it's a `Request`.
     async fn handle_incoming_messages(
         sse_url: String,
+        headers: HashMap<String, String>,
         pending_requests: Arc<PendingRequests>,
+        notifications: broadcast::Sender<JsonRpcNotification>,
+        requests: mpsc::Sender<JsonRpcRequest>,
         post_endpoint: Arc<RwLock<Option<String>>>,
     ) {
-        let client = match eventsource_client::ClientBuilder::for_url(&sse_url) {
-            Ok(builder) => builder.build(),
-            Err(e) => {
-                pending_requests.clear().await;
-                warn!("Failed to connect SSE client: {}", e);
-                return;
-            }
-        };
+        let client =
+            match eventsource_client::ClientBuilder::for_url(&sse_url).and_then(|builder| {
+                headers.iter().try_fold(builder, |builder, (name, value)| {
+                    builder.header(name, value)
+                })
+            }) {
+                Ok(builder) => builder.build(),
+                Err(e) => {
+                    pending_requests.clear().await;
+                    warn!("Failed to connect SSE client: {}", e);
+                    return;
+                }
+            };
         let mut stream = client.stream();
 
         // First, wait for the "endpoint" event
@@ -110,15 +138,24 @@ impl SseActor {
                 SSE::Event(e) if e.event_type == "message" => {
                     // Attempt to parse the SSE data as a JsonRpcMessage
                     match serde_json::from_str::<JsonRpcMessage>(&e.data) {
-                        Ok(message) => {
+                        Ok(message) => match &message {
                             // If it's a response, complete the pending request
-                            if let JsonRpcMessage::Response(resp) = &message {
+                            JsonRpcMessage::Response(resp) => {
                                 if let Some(id) = &resp.id {
                                     pending_requests.respond(&id.to_string(), Ok(message)).await;
                                 }
                             }
-                            // If it's something else (notification, etc.), handle as needed
-                        }
+                            // Sending only fails when nobody is subscribed
+                            JsonRpcMessage::Notification(notification) => {
+                                let _ = notifications.send(notification.clone());
+                            }
+                            JsonRpcMessage::Request(request) => {
+                                if let Err(e) = requests.try_send(request.clone()) {
+                                    warn!("Dropped request from server: {e}");
+                                }
+                            }
+                            _ => {}
+                        },
                         Err(err) => {
                             warn!("Failed to parse SSE message: {err}");
                         }
@@ -139,6 +176,7 @@ impl SseActor {
     async fn handle_outgoing_messages(
         mut receiver: mpsc::Receiver<TransportMessage>,
         http_client: HttpClient,
+        headers: HashMap<String, String>,
         post_endpoint: Arc<RwLock<Option<String>>>,
         pending_requests: Arc<PendingRequests>,
     ) {
@@ -174,8 +212,12 @@ impl SseActor {
             }
 
             // Perform the HTTP POST
-            match http_client
-                .post(&post_url)
+            let request = headers
+                .iter()
+                .fold(http_client.post(&post_url), |request, (name, value)| {
+                    request.header(name, value)
+                });
+            match request
                 .header("Content-Type", "application/json")
                 .body(message_str)
                 .send()
@@ -208,6 +250,8 @@ impl SseActor {
 #[derive(Clone)]
 pub struct SseTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    requests: Arc<Mutex<Option<mpsc::Receiver<JsonRpcRequest>>>>,
 }
 
 #[async_trait::async_trait]
@@ -215,12 +259,21 @@ impl TransportHandle for SseTransportHandle {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
         send_message(&self.sender, message).await
     }
+
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.subscribe()
+    }
+
+    async fn take_requests(&self) -> Option<mpsc::Receiver<JsonRpcRequest>> {
+        self.requests.lock().await.take()
+    }
 }
 
 #[derive(Clone)]
 pub struct SseTransport {
     sse_url: String,
     env: HashMap<String, String>,
+    headers: HashMap<String, String>,
 }
 
 /// The SSE transport spawns an `SseActor` on `start()`.
@@ -229,9 +282,16 @@ impl SseTransport {
         Self {
             sse_url: sse_url.into(),
             env,
+            headers: HashMap::new(),
         }
     }
 
+    /// Send headers with the SSE request and every message, such as an `Authorization` header
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
     /// Waits for the endpoint to be set, up to 10 attempts.
     async fn wait_for_endpoint(
         post_endpoint: Arc<RwLock<Option<String>>>,
@@ -264,6 +324,8 @@ impl Transport for SseTransport {
 
         // Create a channel for outgoing TransportMessages
         let (tx, rx) = mpsc::channel(32);
+        let (notification_tx, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+        let (request_tx, request_rx) = mpsc::channel(REQUEST_CHANNEL_CAPACITY);
 
         let post_endpoint: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
         let post_endpoint_clone = Arc::clone(&post_endpoint);
@@ -272,7 +334,10 @@ impl Transport for SseTransport {
         let actor = SseActor::new(
             rx,
             Arc::new(PendingRequests::new()),
+            notification_tx.clone(),
+            request_tx,
             self.sse_url.clone(),
+            self.headers.clone(),
             post_endpoint,
         );
 
@@ -286,7 +351,11 @@ impl Transport for SseTransport {
         )
         .await
         {
-            Ok(_) => Ok(SseTransportHandle { sender: tx }),
+            Ok(_) => Ok(SseTransportHandle {
+                sender: tx,
+                notifications: notification_tx,
+                requests: Arc::new(Mutex::new(Some(request_rx))),
+            }),
             Err(e) => Err(Error::SseConnection(e.to_string())),
         }
     }
@@ -3,11 +3,14 @@ use std::sync::Arc;
 use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 
 use async_trait::async_trait;
-use mcp_core::protocol::JsonRpcMessage;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
 
-use super::{send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage};
+use super::{
+    send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage,
+    NOTIFICATION_CHANNEL_CAPACITY, REQUEST_CHANNEL_CAPACITY,
+};
 
 /// A `StdioTransport` uses a child process's stdin/stdout as a communication channel.
 ///
@@ -15,6 +18,8 @@ use super::{send_message, Error, PendingRequests, Transport, TransportHandle, Tr
 pub struct StdioActor {
     receiver: mpsc::Receiver<TransportMessage>,
     pending_requests: Arc<PendingRequests>,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    requests: mpsc::Sender<JsonRpcRequest>,
     _process: Child, // we store the process to keep it alive
     error_sender: mpsc::Sender<Error>,
     stdin: ChildStdin,
@@ -26,7 +31,12 @@ impl StdioActor {
     pub async fn run(mut self) {
         use tokio::pin;
 
-        let incoming = Self::handle_incoming_messages(self.stdout, self.pending_requests.clone());
+        let incoming = Self::handle_incoming_messages(
+            self.stdout,
+            self.pending_requests.clone(),
+            self.notifications.clone(),
+            self.requests.clone(),
+        );
         let outgoing = Self::handle_outgoing_messages(
             self.receiver,
             self.stdin,
@@ -71,7 +81,12 @@ impl StdioActor {
         self.pending_requests.clear().await;
     }
 
-    async fn handle_incoming_messages(stdout: ChildStdout, pending_requests: Arc<PendingRequests>) {
+    async fn handle_incoming_messages(
+        stdout: ChildStdout,
+        pending_requests: Arc<PendingRequests>,
+        notifications: broadcast::Sender<JsonRpcNotification>,
+        requests: mpsc::Sender<JsonRpcRequest>,
+    ) {
         let mut reader = BufReader::new(stdout);
         let mut line = String::new();
         loop {
@@ -87,10 +102,22 @@ impl StdioActor {
                             "Received incoming message"
                         );
 
-                        if let JsonRpcMessage::Response(response) = &message {
-                            if let Some(id) = &response.id {
-                                pending_requests.respond(&id.to_string(), Ok(message)).await;
+                        match &message {
+                            JsonRpcMessage::Response(response) => {
+                                if let Some(id) = &response.id {
+                                    pending_requests.respond(&id.to_string(), Ok(message)).await;
+                                }
+                            }
+                            JsonRpcMessage::Notification(notification) => {
+                                // Sending only fails when nobody is subscribed
+                                let _ = notifications.send(notification.clone());
+                            }
+                            JsonRpcMessage::Request(request) => {
+                                if let Err(e) = requests.try_send(request.clone()) {
+                                    tracing::warn!(error = ?e, "Dropped request from server");
+                                }
                             }
+                            _ => {}
                         }
                     }
                     line.clear();
@@ -151,6 +178,8 @@ impl StdioActor {
 pub struct StdioTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
     error_receiver: Arc<Mutex<mpsc::Receiver<Error>>>,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    requests: Arc<Mutex<Option<mpsc::Receiver<JsonRpcRequest>>>>,
 }
 
 #[async_trait::async_trait]
@@ -161,6 +190,14 @@ impl TransportHandle for StdioTransportHandle {
         self.check_for_errors().await?;
         result
     }
+
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.subscribe()
+    }
+
+    async fn take_requests(&self) -> Option<mpsc::Receiver<JsonRpcRequest>> {
+        self.requests.lock().await.take()
+    }
 }
 
 impl StdioTransportHandle {
@@ -174,6 +211,11 @@ impl StdioTransportHandle {
             Err(_) => Ok(()),
         }
     }
+
+    /// Whether the process has exited and the transport can no longer send messages
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
 }
 
 pub struct StdioTransport {
@@ -235,10 +277,14 @@ impl Transport for StdioTransport {
         let (process, stdin, stdout, stderr) = self.spawn_process().await?;
         let (message_tx, message_rx) = mpsc::channel(32);
         let (error_tx, error_rx) = mpsc::channel(1);
+        let (notification_tx, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+        let (request_tx, request_rx) = mpsc::channel(REQUEST_CHANNEL_CAPACITY);
 
         let actor = StdioActor {
             receiver: message_rx,
             pending_requests: Arc::new(PendingRequests::new()),
+            notifications: notification_tx.clone(),
+            requests: request_tx,
             _process: process,
             error_sender: error_tx,
             stdin,
@@ -251,6 +297,8 @@ impl Transport for StdioTransport {
         let handle = StdioTransportHandle {
             sender: message_tx,
             error_receiver: Arc::new(Mutex::new(error_rx)),
+            notifications: notification_tx,
+            requests: Arc::new(Mutex::new(Some(request_rx))),
         };
         Ok(handle)
     }
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Client as HttpClient, Response, StatusCode};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use tracing::warn;
+
+use super::{
+    send_message, Error, Transport, TransportHandle, TransportMessage,
+    NOTIFICATION_CHANNEL_CAPACITY, REQUEST_CHANNEL_CAPACITY,
+};
+
+/// The header the server assigns a session with, which is sent back on every later request
+pub const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+
+/// Posts messages to the server's endpoint and reads what comes back on each response
+#[derive(Clone)]
+struct Connection {
+    http_client: HttpClient,
+    url: String,
+    headers: HeaderMap,
+    session_id: Arc<RwLock<Option<String>>>,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    requests: mpsc::Sender<JsonRpcRequest>,
+}
+
+impl Connection {
+    /// POST a message, returning the response to it if the server answered with one
+    ///
+    /// Notifications and requests the server sends on the same response are delivered
+    /// to the handle's subscribers.
+    async fn post(&self, message: &JsonRpcMessage) -> Result<Option<JsonRpcMessage>, Error> {
+        let body = serde_json::to_string(message)?;
+        let mut request = self
+            .http_client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json, text/event-stream")
+            .body(body);
+        let session_id = self.session_id.read().await.clone();
+        if let Some(session_id) = &session_id {
+            request = request.header(SESSION_ID_HEADER, session_id);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+
+        if let Some(assigned) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self.session_id.write().await = Some(assigned.to_string());
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            // The server forgot our session, so the next initialize starts a new one
+            if status == StatusCode::NOT_FOUND && session_id.is_some() {
+                *self.session_id.write().await = None;
+            }
+            let message = response
+                .text()
+                .await
+                .ok()
+                .filter(|text| !text.is_empty())
+                .unwrap_or_else(|| status.to_string());
+            return Err(Error::HttpError {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        if status == StatusCode::ACCEPTED {
+            return Ok(None);
+        }
+
+        let is_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if is_stream {
+            self.read_stream(response).await
+        } else {
+            self.read_json(response).await
+        }
+    }
+
+    /// Read a JSON body holding a single message or a batch of them
+    async fn read_json(&self, response: Response) -> Result<Option<JsonRpcMessage>, Error> {
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+        if body.is_empty() {
+            return Ok(None);
+        }
+
+        let messages = match serde_json::from_slice::<serde_json::Value>(&body)? {
+            serde_json::Value::Array(batch) => batch,
+            message => vec![message],
+        };
+        let mut answer = None;
+        for message in messages {
+            let message: JsonRpcMessage = serde_json::from_value(message)?;
+            answer = answer.or(self.deliver(message));
+        }
+        Ok(answer)
+    }
+
+    /// Read SSE events until the response arrives or the server ends the stream
+    async fn read_stream(&self, response: Response) -> Result<Option<JsonRpcMessage>, Error> {
+        let mut stream = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+            buffer.extend(chunk.iter().filter(|&&b| b != b'\r'));
+
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                let Some(data) = event_data(&String::from_utf8_lossy(&event)) else {
+                    continue;
+                };
+                match serde_json::from_str::<JsonRpcMessage>(&data) {
+                    Ok(message) => {
+                        if let Some(answer) = self.deliver(message) {
+                            return Ok(Some(answer));
+                        }
+                    }
+                    Err(e) => warn!("Failed to parse streamed message: {e}"),
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Pass on a message the server sent, returning it if it answers our request
+    fn deliver(&self, message: JsonRpcMessage) -> Option<JsonRpcMessage> {
+        match message {
+            JsonRpcMessage::Notification(notification) => {
+                // Sending only fails when nobody is subscribed
+                let _ = self.notifications.send(notification);
+                None
+            }
+            JsonRpcMessage::Request(request) => {
+                if let Err(e) = self.requests.try_send(request) {
+                    warn!("Dropped request from server: {e}");
+                }
+                None
+            }
+            JsonRpcMessage::Nil => None,
+            answer => Some(answer),
+        }
+    }
+}
+
+/// The data of an SSE `message` event, joining multiple `data` lines
+fn event_data(event: &str) -> Option<String> {
+    let mut data: Vec<&str> = Vec::new();
+    for line in event.lines() {
+        if let Some(event_type) = line.strip_prefix("event:") {
+            if event_type.trim() != "message" {
+                return None;
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    (!data.is_empty()).then(|| data.join("\n"))
+}
+
+/// The actor that sends every message as a POST to the server's single endpoint
+pub struct StreamableHttpActor {
+    /// Receives messages (requests/notifications/responses) from the handle
+    receiver: mpsc::Receiver<TransportMessage>,
+    connection: Connection,
+}
+
+impl StreamableHttpActor {
+    pub async fn run(mut self) {
+        while let Some(transport_msg) = self.receiver.recv().await {
+            match transport_msg.response_tx {
+                // Requests are posted concurrently, as each is answered on its own response
+                Some(response_tx) => {
+                    let connection = self.connection.clone();
+                    tokio::spawn(async move {
+                        let result = match connection.post(&transport_msg.message).await {
+                            Ok(Some(response)) => Ok(response),
+                            Ok(None) => Err(Error::StreamableHttpError(
+                                "The server did not answer the request".to_string(),
+                            )),
+                            Err(e) => Err(e),
+                        };
+                        let _ = response_tx.send(result);
+                    });
+                }
+                // Notifications are posted in order, so they arrive before any later request
+                None => {
+                    if let Err(e) = self.connection.post(&transport_msg.message).await {
+                        warn!("Failed to post message: {e}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StreamableHttpTransportHandle {
+    sender: mpsc::Sender<TransportMessage>,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    requests: Arc<Mutex<Option<mpsc::Receiver<JsonRpcRequest>>>>,
+}
+
+#[async_trait]
+impl TransportHandle for StreamableHttpTransportHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        send_message(&self.sender, message).await
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.subscribe()
+    }
+
+    async fn take_requests(&self) -> Option<mpsc::Receiver<JsonRpcRequest>> {
+        self.requests.lock().await.take()
+    }
+}
+
+/// A transport for servers exposing the streamable HTTP transport on a single endpoint
+///
+/// Every message is POSTed to the endpoint, and the server answers with JSON or with an SSE
+/// stream carrying the response along with any notifications. The session the server assigns
+/// in the `Mcp-Session-Id` header is kept for later requests and ended on `close`.
+pub struct StreamableHttpTransport {
+    url: String,
+    /// Sent with every request, such as an `Authorization` header
+    headers: HashMap<String, String>,
+    session_id: Arc<RwLock<Option<String>>>,
+}
+
+impl StreamableHttpTransport {
+    pub fn new<S: Into<String>>(url: S, headers: HashMap<String, String>) -> Self {
+        Self {
+            url: url.into(),
+            headers,
+            session_id: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::StreamableHttpError(format!("Invalid header {name}: {e}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::StreamableHttpError(format!("Invalid header {name}: {e}")))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    type Handle = StreamableHttpTransportHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        let (message_tx, message_rx) = mpsc::channel(32);
+        let (notification_tx, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+        let (request_tx, request_rx) = mpsc::channel(REQUEST_CHANNEL_CAPACITY);
+        *self.session_id.write().await = None;
+
+        let actor = StreamableHttpActor {
+            receiver: message_rx,
+            connection: Connection {
+                http_client: HttpClient::new(),
+                url: self.url.clone(),
+                headers: self.header_map()?,
+                session_id: Arc::clone(&self.session_id),
+                notifications: notification_tx.clone(),
+                requests: request_tx,
+            },
+        };
+        tokio::spawn(actor.run());
+
+        Ok(StreamableHttpTransportHandle {
+            sender: message_tx,
+            notifications: notification_tx,
+            requests: Arc::new(Mutex::new(Some(request_rx))),
+        })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        // Let the server free the session rather than wait for it to expire
+        let Some(session_id) = self.session_id.write().await.take() else {
+            return Ok(());
+        };
+        HttpClient::new()
+            .delete(&self.url)
+            .headers(self.header_map()?)
+            .header(SESSION_ID_HEADER, session_id)
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+    use crate::service::McpService;
+    use axum::http::{HeaderMap as AxumHeaderMap, StatusCode as AxumStatusCode};
+    use axum::response::{IntoResponse, Response as AxumResponse};
+    use axum::{routing::post, Json, Router};
+    use mcp_core::Tool;
+    use serde_json::{json, Value};
+
+    /// A stand-in for a streamable HTTP server that wants a bearer token and a session
+    async fn mcp_endpoint(headers: AxumHeaderMap, Json(message): Json<Value>) -> AxumResponse {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        if header("authorization") != Some("Bearer secret") {
+            return AxumStatusCode::UNAUTHORIZED.into_response();
+        }
+
+        let id = message["id"].clone();
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "initialize" {
+            let result = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {"tools": {"listChanged": true}},
+                    "serverInfo": {"name": "stand-in", "version": "1.0.0"}
+                }
+            });
+            return ([(SESSION_ID_HEADER, "session-1")], Json(result)).into_response();
+        }
+        if header(SESSION_ID_HEADER) != Some("session-1") {
+            return AxumStatusCode::NOT_FOUND.into_response();
+        }
+
+        match method {
+            "notifications/initialized" => AxumStatusCode::ACCEPTED.into_response(),
+            "tools/list" => {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/tools/list_changed"
+                });
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": {"tools": [Tool::new("echo", "Echo the input", json!({}))]}
+                });
+                let body = format!(
+                    "event: message\ndata: {}\n\ndata: {}\n\n",
+                    notification, response
+                );
+                ([(CONTENT_TYPE.as_str(), "text/event-stream")], body).into_response()
+            }
+            _ => Json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32601, "message": "Method not found"}
+            }))
+            .into_response(),
+        }
+    }
+
+    async fn serve() -> String {
+        let app = Router::new().route("/mcp", post(mcp_endpoint));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/mcp", addr)
+    }
+
+    fn info() -> ClientInfo {
+        ClientInfo {
+            name: "test".to_string(),
+            version: "1.0.0".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_transport() {
+        let url = serve().await;
+        let transport = StreamableHttpTransport::new(
+            &url,
+            HashMap::from([("Authorization".to_string(), "Bearer secret".to_string())]),
+        );
+        let handle = transport.start().await.unwrap();
+        let mut notifications = handle.subscribe();
+        let mut client = McpClient::new(McpService::new(handle));
+
+        let result = client
+            .initialize(info(), ClientCapabilities::default())
+            .await
+            .unwrap();
+        assert_eq!(result.server_info.name, "stand-in");
+        assert_eq!(
+            transport.session_id.read().await.as_deref(),
+            Some("session-1")
+        );
+
+        // The response is streamed after a notification
+        let tools = client.list_tools(None).await.unwrap();
+        assert_eq!(tools.tools[0].name, "echo");
+        let notification = notifications.try_recv().unwrap();
+        assert_eq!(notification.method, "notifications/tools/list_changed");
+
+        let result = client.request("unknown/method", json!({})).await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::RpcError { code: -32601, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_transport_rejected() {
+        let url = serve().await;
+        let transport = StreamableHttpTransport::new(&url, HashMap::new());
+        let handle = transport.start().await.unwrap();
+        let mut client = McpClient::new(McpService::new(handle));
+
+        let result = client
+            .initialize(info(), ClientCapabilities::default())
+            .await;
+        assert!(result.is_err());
+    }
+}
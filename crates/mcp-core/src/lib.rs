@@ -4,7 +4,7 @@ pub mod handler;
 pub mod role;
 pub use role::Role;
 pub mod tool;
-pub use tool::{Tool, ToolCall};
+pub use tool::{Tool, ToolAnnotations, ToolCall};
 pub mod resource;
 pub use resource::{Resource, ResourceContents};
 pub mod protocol;
@@ -11,8 +11,10 @@ pub struct Prompt {
     /// The name of the prompt
     pub name: String,
     /// A description of what the prompt does
+    #[serde(default)]
     pub description: String,
     /// The arguments that can be passed to customize the prompt
+    #[serde(default)]
     pub arguments: Vec<PromptArgument>,
 }
 
@@ -37,8 +39,10 @@ pub struct PromptArgument {
     /// The name of the argument
     pub name: String,
     /// A description of what the argument is used for
+    #[serde(default)]
     pub description: String,
     /// Whether this argument is required
+    #[serde(default)]
     pub required: bool,
 }
 
@@ -4,6 +4,7 @@ use crate::{
     prompt::{Prompt, PromptMessage},
     resource::Resource,
     resource::ResourceContents,
+    role::Role,
     tool::Tool,
 };
 use serde::{Deserialize, Serialize};
@@ -223,8 +224,11 @@ pub struct CallToolResult {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct ListPromptsResult {
     pub prompts: Vec<Prompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -234,5 +238,71 @@ pub struct GetPromptResult {
     pub messages: Vec<PromptMessage>,
 }
 
+/// A message in a sampling request, from the user or the assistant
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+/// The params of a `sampling/createMessage` request, in which a server asks the client
+/// for a completion from the client's model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    pub max_tokens: u32,
+    /// Hints about which model to prefer, which the client is free to ignore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+    /// The name of the model that generated the message
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Identifies the request a `notifications/progress` is about, chosen by the client in the
+/// request's `_meta.progressToken`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ProgressToken {
+    String(String),
+    Number(i64),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressNotificationParams {
+    pub progress_token: ProgressToken,
+    /// The progress so far, which increases with every notification
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledNotificationParams {
+    /// The id of the request to cancel
+    pub request_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmptyResult {}
@@ -13,6 +13,19 @@ pub struct Tool {
     pub description: String,
     /// A JSON Schema object defining the expected parameters for the tool
     pub input_schema: Value,
+    /// Hints about how the tool behaves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Hints a server gives about a tool, which clients use to decide how to run its calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// Whether calls can run at the same time as other calls to the server's parallel tools.
+    /// Clients run calls to tools without this hint one at a time, in the order requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_hint: Option<bool>,
 }
 
 impl Tool {
@@ -26,8 +39,22 @@ impl Tool {
             name: name.into(),
             description: description.into(),
             input_schema,
+            annotations: None,
         }
     }
+
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Whether calls can run alongside other calls to the server's parallel tools
+    pub fn is_parallel(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|a| a.parallel_hint)
+            .unwrap_or(false)
+    }
 }
 
 /// A tool call request that an extension can execute
@@ -18,6 +18,9 @@ pub enum TransportError {
 
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
+
+    #[error("Connection error: {0}")]
+    Connection(String),
 }
 
 #[derive(Error, Debug)]
@@ -33,6 +36,9 @@ pub enum ServerError {
 
     #[error("Request timed out")]
     Timeout(#[from] tower::timeout::error::Elapsed),
+
+    #[error("Set the token remote clients must send with --token or {0}")]
+    MissingToken(&'static str),
 }
 
 #[derive(Error, Debug)]
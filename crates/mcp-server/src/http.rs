@@ -0,0 +1,345 @@
+//! Serve a router to remote clients over HTTP
+//!
+//! Clients connect in one of two ways, and each connection gets its own session:
+//! - SSE: `GET /sse` opens an event stream whose first `endpoint` event names the URL to
+//!   `POST` messages to, and responses arrive as `message` events on the stream.
+//! - WebSocket: `GET /ws` upgrades to a socket carrying one message per text frame.
+//!
+//! All sessions share the one service, so a stateful router like memory sees every client.
+//!
+//! Every request must carry the token the routes were built with in an
+//! `Authorization: Bearer` header, and requests a browser sends from a page on another
+//! origin are refused, so a website the user visits can't drive the server.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
+use axum::routing::{get, post};
+use futures::{stream, Future, Stream, StreamExt};
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower_service::Service;
+
+use crate::{
+    parse_message, BoundedService, BoxError, Server, ServerError, Transport, TransportError,
+};
+
+/// The environment variable holding the token remote clients must send, when none is given
+pub const TOKEN_ENV: &str = "GOOSE_MCP_TOKEN";
+
+/// Messages buffered for a session before the sender has to wait
+const SESSION_CHANNEL_CAPACITY: usize = 32;
+
+/// The service shared by every session
+struct SharedService<S>(Arc<Mutex<S>>);
+
+impl<S> Clone for SharedService<S> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<S: BoundedService> Service<JsonRpcRequest> for SharedService<S> {
+    type Response = JsonRpcResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<JsonRpcResponse, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: JsonRpcRequest) -> Self::Future {
+        // The lock is only held to start the request, not while it runs
+        self.0.lock().unwrap().call(req)
+    }
+}
+
+/// The senders for the messages POSTed to each open SSE session
+type Sessions = Arc<Mutex<HashMap<String, mpsc::Sender<JsonRpcMessage>>>>;
+
+struct HttpState<S> {
+    service: SharedService<S>,
+    sessions: Sessions,
+}
+
+impl<S> Clone for HttpState<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            sessions: Arc::clone(&self.sessions),
+        }
+    }
+}
+
+/// Build the routes serving a service, for use on its own or nested in a larger app
+///
+/// Requests are refused unless they carry `token` as a bearer token.
+pub fn router<S: BoundedService>(service: S, token: impl Into<String>) -> axum::Router {
+    let state = HttpState {
+        service: SharedService(Arc::new(Mutex::new(service))),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+    };
+
+    axum::Router::new()
+        .route("/sse", get(sse_handler::<S>))
+        .route("/message", post(message_handler::<S>))
+        .route("/ws", get(ws_handler::<S>))
+        .with_state(state)
+        .layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token.into()),
+            authorize,
+        ))
+}
+
+/// Serve a service on a listener until the listener fails, to clients with the token
+pub async fn serve<S: BoundedService>(
+    listener: TcpListener,
+    service: S,
+    token: impl Into<String>,
+) -> Result<(), ServerError> {
+    axum::serve(listener, router(service, token))
+        .await
+        .map_err(|e| ServerError::Transport(TransportError::Io(e)))
+}
+
+/// The token remote clients must send: the one given, or else the one in [`TOKEN_ENV`]
+pub fn resolve_token(token: Option<String>) -> Result<String, ServerError> {
+    token
+        .or_else(|| std::env::var(TOKEN_ENV).ok())
+        .filter(|token| !token.is_empty())
+        .ok_or(ServerError::MissingToken(TOKEN_ENV))
+}
+
+/// Bind to `addr` and serve a service there to clients with the token
+///
+/// A bare port only listens on this machine. Returns the address bound to, along with the
+/// future that serves until the listener fails.
+pub async fn serve_on<S: BoundedService>(
+    addr: &str,
+    service: S,
+    token: impl Into<String>,
+) -> Result<(SocketAddr, impl Future<Output = Result<(), ServerError>>), ServerError> {
+    let addr = match addr.parse::<u16>() {
+        Ok(port) => format!("127.0.0.1:{port}"),
+        Err(_) => addr.to_string(),
+    };
+    let listener = TcpListener::bind(&addr).await.map_err(TransportError::Io)?;
+    let addr = listener.local_addr().map_err(TransportError::Io)?;
+    if !addr.ip().is_loopback() {
+        tracing::warn!(%addr, "Serving to other machines");
+    }
+    tracing::info!(%addr, "Serving over SSE and WebSocket");
+    Ok((addr, serve(listener, service, token)))
+}
+
+/// Refuse requests from other origins and requests without the bearer token
+async fn authorize(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !same_origin(request.headers()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer {
+        Some(bearer) if constant_time_eq(bearer.as_bytes(), token.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Whether a request comes from the server's own origin, or from a client that isn't a
+/// browser and so sends no `Origin`
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    matches!((origin_host, host), (Some(origin), Some(host)) if origin.eq_ignore_ascii_case(host))
+}
+
+/// Compare secrets without leaking how much of them matched through the time taken
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Moves messages between an SSE session's handlers and its server
+struct ChannelTransport {
+    incoming: mpsc::Receiver<JsonRpcMessage>,
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+}
+
+#[async_trait]
+impl Transport for ChannelTransport {
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage, TransportError>> {
+        self.incoming.recv().await.map(Ok)
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
+        self.outgoing
+            .send(msg)
+            .await
+            .map_err(|_| TransportError::Connection("The client disconnected".to_string()))
+    }
+}
+
+/// Closes an SSE session once its event stream is dropped
+struct SessionGuard {
+    id: String,
+    sessions: Sessions,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        // Dropping the sender ends the session's server
+        self.sessions.lock().unwrap().remove(&self.id);
+    }
+}
+
+async fn sse_handler<S: BoundedService>(
+    State(state): State<HttpState<S>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let (incoming_tx, incoming_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    let (outgoing_tx, outgoing_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(id.clone(), incoming_tx);
+
+    let transport = ChannelTransport {
+        incoming: incoming_rx,
+        outgoing: outgoing_tx,
+    };
+    let server = Server::new(state.service.clone());
+    tokio::spawn(async move {
+        if let Err(e) = server.run(transport).await {
+            tracing::info!(error = %e, "SSE session ended");
+        }
+    });
+    tracing::info!(session_id = %id, "SSE session started");
+
+    // The endpoint is relative so it resolves correctly when these routes are nested
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("message?sessionId={}", id));
+    let guard = SessionGuard {
+        id,
+        sessions: state.sessions,
+    };
+    let messages = stream::unfold(outgoing_rx, |mut rx| async move {
+        rx.recv().await.map(|msg| (msg, rx))
+    })
+    .map(move |msg| {
+        let _guard = &guard;
+        Event::default().event("message").json_data(msg)
+    });
+
+    Sse::new(stream::once(async { Ok(endpoint) }).chain(messages)).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionQuery {
+    session_id: String,
+}
+
+async fn message_handler<S: BoundedService>(
+    State(state): State<HttpState<S>>,
+    Query(query): Query<SessionQuery>,
+    body: String,
+) -> StatusCode {
+    let sender = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get(&query.session_id)
+        .cloned();
+    let Some(sender) = sender else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    let message = match parse_message(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::warn!(error = %e, "Invalid message posted to an SSE session");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    match sender.send(message).await {
+        Ok(()) => StatusCode::ACCEPTED,
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+async fn ws_handler<S: BoundedService>(
+    State(state): State<HttpState<S>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        tracing::info!("WebSocket session started");
+        let server = Server::new(state.service.clone());
+        if let Err(e) = server.run(WebSocketTransport(socket)).await {
+            tracing::info!(error = %e, "WebSocket session ended");
+        }
+    })
+}
+
+/// Carries one message per text frame of a WebSocket
+struct WebSocketTransport(WebSocket);
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage, TransportError>> {
+        loop {
+            let text = match self.0.recv().await? {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                    Ok(text) => text,
+                    Err(e) => return Some(Err(TransportError::Utf8(e))),
+                },
+                Ok(Message::Close(_)) => return None,
+                // Pings are answered by axum
+                Ok(Message::Ping(_) | Message::Pong(_)) => continue,
+                Err(e) => return Some(Err(TransportError::Connection(e.to_string()))),
+            };
+            return Some(parse_message(&text));
+        }
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
+        let text = serde_json::to_string(&msg)?;
+        self.0
+            .send(Message::Text(text))
+            .await
+            .map_err(|e| TransportError::Connection(e.to_string()))
+    }
+}
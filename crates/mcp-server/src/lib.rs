@@ -1,27 +1,74 @@
 use std::{
+    collections::HashMap,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use futures::{Future, Stream};
-use mcp_core::protocol::{JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
+use async_trait::async_trait;
+use futures::{Future, Stream, StreamExt};
+use mcp_core::protocol::{
+    CancelledNotificationParams, JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse,
+};
 use pin_project::pin_project;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
 use tower_service::Service;
+use tracing::Instrument;
 
 mod errors;
 pub use errors::{BoxError, RouterError, ServerError, TransportError};
 
+pub mod http;
+pub mod progress;
+pub use progress::Progress;
 pub mod router;
 pub use router::Router;
 
+/// A connection to one client that the server reads requests from and writes responses to
+#[async_trait]
+pub trait Transport: Send {
+    /// Read the next message, or None once the client has gone away
+    ///
+    /// This must be cancel safe, as the server stops waiting for a message whenever it has a
+    /// response to write.
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage, TransportError>>;
+
+    /// Write a message to the client
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError>;
+}
+
+/// Parse and validate one JSON-RPC message received by a transport
+pub(crate) fn parse_message(text: &str) -> Result<JsonRpcMessage, TransportError> {
+    let value = serde_json::from_str::<serde_json::Value>(text)?;
+
+    // Validate basic JSON-RPC structure
+    let Some(obj) = value.as_object() else {
+        return Err(TransportError::InvalidMessage(
+            "Message must be a JSON object".into(),
+        ));
+    };
+
+    // Check jsonrpc version field
+    if !obj.contains_key("jsonrpc") || obj["jsonrpc"] != "2.0" {
+        return Err(TransportError::InvalidMessage(
+            "Missing or invalid jsonrpc version".into(),
+        ));
+    }
+
+    // Now try to parse as proper message
+    Ok(serde_json::from_value::<JsonRpcMessage>(value)?)
+}
+
 /// A transport layer that handles JSON-RPC messages over byte
 #[pin_project]
 pub struct ByteTransport<R, W> {
     #[pin]
-    reader: R,
+    reader: BufReader<R>,
     #[pin]
     writer: W,
+    /// The start of a line whose end hasn't been read yet
+    line: Vec<u8>,
 }
 
 impl<R, W> ByteTransport<R, W>
@@ -30,7 +77,13 @@ where
     W: AsyncWrite,
 {
     pub fn new(reader: R, writer: W) -> Self {
-        Self { reader, writer }
+        Self {
+            // Default BufReader capacity is 8 * 1024, increase this to 2MB to the file size limit
+            // allows the buffer to have the capacity to read very large calls
+            reader: BufReader::with_capacity(2 * 1024 * 1024, reader),
+            writer,
+            line: Vec::new(),
+        }
     }
 }
 
@@ -43,55 +96,47 @@ where
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
-        let mut buf = Vec::new();
-        // Default BufReader capacity is 8 * 1024, increase this to 2MB to the file size limit
-        // allows the buffer to have the capacity to read very large calls
-        let mut reader = BufReader::with_capacity(2 * 1024 * 1024, &mut this.reader);
-
-        let mut read_future = Box::pin(reader.read_until(b'\n', &mut buf));
-        match read_future.as_mut().poll(cx) {
-            Poll::Ready(Ok(0)) => Poll::Ready(None), // EOF
-            Poll::Ready(Ok(_)) => {
-                // Convert to UTF-8 string
-                let line = match String::from_utf8(buf) {
-                    Ok(s) => s,
-                    Err(e) => return Poll::Ready(Some(Err(TransportError::Utf8(e)))),
-                };
-                // Log incoming message here before serde conversion to
-                // track incomplete chunks which are not valid JSON
-                tracing::info!(json = %line, "incoming message");
-
-                // Parse JSON and validate message format
-                match serde_json::from_str::<serde_json::Value>(&line) {
-                    Ok(value) => {
-                        // Validate basic JSON-RPC structure
-                        if !value.is_object() {
-                            return Poll::Ready(Some(Err(TransportError::InvalidMessage(
-                                "Message must be a JSON object".into(),
-                            ))));
-                        }
 
-                        let obj = value.as_object().unwrap(); // Safe due to check above
-
-                        // Check jsonrpc version field
-                        if !obj.contains_key("jsonrpc") || obj["jsonrpc"] != "2.0" {
-                            return Poll::Ready(Some(Err(TransportError::InvalidMessage(
-                                "Missing or invalid jsonrpc version".into(),
-                            ))));
-                        }
+        // Keep what has been read of the line between polls, so no input is lost when the
+        // server stops waiting for a message
+        loop {
+            let available = match this.reader.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(Ok(available)) => available,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(TransportError::Io(e)))),
+                Poll::Pending => return Poll::Pending,
+            };
+            if available.is_empty() {
+                // EOF, after which a final line without a newline is still a message
+                if this.line.is_empty() {
+                    return Poll::Ready(None);
+                }
+                break;
+            }
 
-                        // Now try to parse as proper message
-                        match serde_json::from_value::<JsonRpcMessage>(value) {
-                            Ok(msg) => Poll::Ready(Some(Ok(msg))),
-                            Err(e) => Poll::Ready(Some(Err(TransportError::Json(e)))),
-                        }
-                    }
-                    Err(e) => Poll::Ready(Some(Err(TransportError::Json(e)))),
+            match available.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    this.line.extend_from_slice(&available[..=end]);
+                    this.reader.as_mut().consume(end + 1);
+                    break;
+                }
+                None => {
+                    let read = available.len();
+                    this.line.extend_from_slice(available);
+                    this.reader.as_mut().consume(read);
                 }
             }
-            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(TransportError::Io(e)))),
-            Poll::Pending => Poll::Pending,
         }
+
+        // Convert to UTF-8 string
+        let line = match String::from_utf8(std::mem::take(this.line)) {
+            Ok(s) => s,
+            Err(e) => return Poll::Ready(Some(Err(TransportError::Utf8(e)))),
+        };
+        // Log incoming message here before serde conversion to
+        // track incomplete chunks which are not valid JSON
+        tracing::info!(json = %line, "incoming message");
+
+        Poll::Ready(Some(parse_message(&line)))
     }
 }
 
@@ -111,6 +156,21 @@ where
     }
 }
 
+#[async_trait]
+impl<R, W> Transport for ByteTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn read_message(&mut self) -> Option<Result<JsonRpcMessage, TransportError>> {
+        self.next().await
+    }
+
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
+        Ok(ByteTransport::write_message(self, msg).await?)
+    }
+}
+
 /// The main server type that processes incoming requests
 pub struct Server<S> {
     service: S,
@@ -120,42 +180,103 @@ impl<S> Server<S>
 where
     S: Service<JsonRpcRequest, Response = JsonRpcResponse> + Send,
     S::Error: Into<BoxError>,
-    S::Future: Send,
+    S::Future: Send + 'static,
 {
     pub fn new(service: S) -> Self {
         Self { service }
     }
 
-    // TODO transport trait instead of byte transport if we implement others
-    pub async fn run<R, W>(self, mut transport: ByteTransport<R, W>) -> Result<(), ServerError>
-    where
-        R: AsyncRead + Unpin,
-        W: AsyncWrite + Unpin,
-    {
-        use futures::StreamExt;
-        let mut service = self.service;
+    /// Serve one client until its transport closes
+    ///
+    /// Requests are handled concurrently, so the client can cancel a running request with
+    /// `notifications/cancelled`, and the progress they report is sent as it happens.
+    pub async fn run<T: Transport>(self, mut transport: T) -> Result<(), ServerError> {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(OUTGOING_CHANNEL_CAPACITY);
+        let mut session = Session {
+            service: self.service,
+            outgoing,
+            running: HashMap::new(),
+        };
 
         tracing::info!("Server started");
-        while let Some(msg_result) = transport.next().await {
-            let _span = tracing::span!(tracing::Level::INFO, "message_processing").entered();
-            match msg_result {
-                Ok(msg) => {
-                    match msg {
-                        JsonRpcMessage::Request(request) => {
-                            // Serialize request for logging
-                            let id = request.id;
-                            let request_json = serde_json::to_string(&request)
-                                .unwrap_or_else(|_| "Failed to serialize request".to_string());
+        loop {
+            tokio::select! {
+                msg_result = transport.read_message() => {
+                    let Some(msg_result) = msg_result else {
+                        break;
+                    };
+                    let span = tracing::span!(tracing::Level::INFO, "message_processing");
+                    session
+                        .process(&mut transport, msg_result)
+                        .instrument(span)
+                        .await?;
+                }
+                Some(msg) = outgoing_rx.recv() => {
+                    if let JsonRpcMessage::Response(JsonRpcResponse { id: Some(id), .. }) = &msg {
+                        session.running.remove(id);
+                    }
+                    transport.write_message(msg).await?;
+                }
+            }
+        }
 
-                            tracing::info!(
-                                request_id = ?id,
-                                method = ?request.method,
-                                json = %request_json,
-                                "Received request"
-                            );
+        // The client won't send anything else, but still gets the responses to the requests
+        // it already made
+        drop(session);
+        while let Some(msg) = outgoing_rx.recv().await {
+            transport.write_message(msg).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Capacity of the channel carrying responses and notifications to the transport
+const OUTGOING_CHANNEL_CAPACITY: usize = 64;
+
+/// The state of the server while it serves one client
+struct Session<S> {
+    service: S,
+    /// Sender for the responses and notifications of the running requests
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+    /// The running requests that the client can still cancel
+    running: HashMap<u64, AbortHandle>,
+}
+
+impl<S> Session<S>
+where
+    S: Service<JsonRpcRequest, Response = JsonRpcResponse> + Send,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    async fn process<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        msg_result: Result<JsonRpcMessage, TransportError>,
+    ) -> Result<(), ServerError> {
+        match msg_result {
+            Ok(msg) => {
+                match msg {
+                    JsonRpcMessage::Request(request) => {
+                        // Serialize request for logging
+                        let id = request.id;
+                        let request_json = serde_json::to_string(&request)
+                            .unwrap_or_else(|_| "Failed to serialize request".to_string());
 
-                            // Process the request using our service
-                            let response = match service.call(request).await {
+                        tracing::info!(
+                            request_id = ?id,
+                            method = ?request.method,
+                            json = %request_json,
+                            "Received request"
+                        );
+
+                        // Process the request using our service, in its own task so it can be
+                        // cancelled and other requests aren't held up behind it
+                        let progress = progress::requested(&request, &self.outgoing);
+                        let future = self.service.call(request);
+                        let outgoing = self.outgoing.clone();
+                        let task = tokio::spawn(async move {
+                            let response = match progress::scope(progress, future).await {
                                 Ok(resp) => resp,
                                 Err(e) => {
                                     let error_msg = e.into().to_string();
@@ -183,54 +304,67 @@ where
                                 "Sending response"
                             );
                             // Send the response back
-                            if let Err(e) = transport
-                                .write_message(JsonRpcMessage::Response(response))
-                                .await
-                            {
-                                return Err(ServerError::Transport(TransportError::Io(e)));
-                            }
-                        }
-                        JsonRpcMessage::Response(_)
-                        | JsonRpcMessage::Notification(_)
-                        | JsonRpcMessage::Nil
-                        | JsonRpcMessage::Error(_) => {
-                            // Ignore responses, notifications and nil messages for now
-                            continue;
+                            let _ = outgoing.send(JsonRpcMessage::Response(response)).await;
+                        });
+                        if let Some(id) = id {
+                            self.running.insert(id, task.abort_handle());
                         }
                     }
-                }
-                Err(e) => {
-                    // Convert transport error to JSON-RPC error response
-                    let error = match e {
-                        TransportError::Json(_) | TransportError::InvalidMessage(_) => {
-                            mcp_core::protocol::ErrorData {
-                                code: mcp_core::protocol::PARSE_ERROR,
-                                message: e.to_string(),
-                                data: None,
+                    JsonRpcMessage::Notification(notification)
+                        if notification.method == "notifications/cancelled" =>
+                    {
+                        let params = notification.params.and_then(|params| {
+                            serde_json::from_value::<CancelledNotificationParams>(params).ok()
+                        });
+                        // The request may have already finished, which the client expects
+                        if let Some(params) = params {
+                            if let Some(task) = self.running.remove(&params.request_id) {
+                                tracing::info!(
+                                    request_id = params.request_id,
+                                    reason = ?params.reason,
+                                    "Cancelled request"
+                                );
+                                task.abort();
                             }
                         }
-                        TransportError::Protocol(_) => mcp_core::protocol::ErrorData {
-                            code: mcp_core::protocol::INVALID_REQUEST,
-                            message: e.to_string(),
-                            data: None,
-                        },
-                        _ => mcp_core::protocol::ErrorData {
-                            code: mcp_core::protocol::INTERNAL_ERROR,
+                    }
+                    JsonRpcMessage::Response(_)
+                    | JsonRpcMessage::Notification(_)
+                    | JsonRpcMessage::Nil
+                    | JsonRpcMessage::Error(_) => {
+                        // Ignore responses, other notifications and nil messages for now
+                    }
+                }
+            }
+            Err(e) => {
+                // Convert transport error to JSON-RPC error response
+                let error = match e {
+                    TransportError::Json(_) | TransportError::InvalidMessage(_) => {
+                        mcp_core::protocol::ErrorData {
+                            code: mcp_core::protocol::PARSE_ERROR,
                             message: e.to_string(),
                             data: None,
-                        },
-                    };
+                        }
+                    }
+                    TransportError::Protocol(_) => mcp_core::protocol::ErrorData {
+                        code: mcp_core::protocol::INVALID_REQUEST,
+                        message: e.to_string(),
+                        data: None,
+                    },
+                    _ => mcp_core::protocol::ErrorData {
+                        code: mcp_core::protocol::INTERNAL_ERROR,
+                        message: e.to_string(),
+                        data: None,
+                    },
+                };
 
-                    let error_response = JsonRpcMessage::Error(JsonRpcError {
-                        jsonrpc: "2.0".to_string(),
-                        id: None,
-                        error,
-                    });
+                let error_response = JsonRpcMessage::Error(JsonRpcError {
+                    jsonrpc: "2.0".to_string(),
+                    id: None,
+                    error,
+                });
 
-                    if let Err(e) = transport.write_message(error_response).await {
-                        return Err(ServerError::Transport(TransportError::Io(e)));
-                    }
-                }
+                transport.write_message(error_response).await?;
             }
         }
 
@@ -0,0 +1,74 @@
+//! Progress reporting for long-running requests
+//!
+//! A client asks to hear about a request's progress by giving it a `_meta.progressToken`.
+//! While the server handles such a request, tools can report progress with
+//! [`Progress::current`], which the server sends on as `notifications/progress`.
+
+use futures::Future;
+use mcp_core::protocol::{
+    JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, ProgressNotificationParams, ProgressToken,
+};
+use tokio::sync::mpsc;
+
+tokio::task_local! {
+    static PROGRESS: Progress;
+}
+
+/// Reports the progress of the request being handled to the client
+#[derive(Clone)]
+pub struct Progress {
+    token: ProgressToken,
+    notifications: mpsc::Sender<JsonRpcMessage>,
+}
+
+impl Progress {
+    /// The progress of the request the current task is handling, if the client asked for it
+    pub fn current() -> Option<Progress> {
+        PROGRESS.try_with(Clone::clone).ok()
+    }
+
+    /// Report the progress so far, which has to increase with every report
+    ///
+    /// Reports are dropped rather than holding up the request when the client is slow to
+    /// read them.
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<String>) {
+        let params = ProgressNotificationParams {
+            progress_token: self.token.clone(),
+            progress,
+            total,
+            message,
+        };
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/progress".to_string(),
+            params: serde_json::to_value(params).ok(),
+        });
+        if self.notifications.try_send(notification).is_err() {
+            tracing::debug!("Dropped a progress notification");
+        }
+    }
+}
+
+/// The progress reporter for a request, if its client asked for progress
+pub(crate) fn requested(
+    request: &JsonRpcRequest,
+    notifications: &mpsc::Sender<JsonRpcMessage>,
+) -> Option<Progress> {
+    let token = request
+        .params
+        .as_ref()?
+        .get("_meta")?
+        .get("progressToken")?;
+    Some(Progress {
+        token: serde_json::from_value(token.clone()).ok()?,
+        notifications: notifications.clone(),
+    })
+}
+
+/// Handle a request with its progress reporter available to [`Progress::current`]
+pub(crate) async fn scope<F: Future>(progress: Option<Progress>, future: F) -> F::Output {
+    match progress {
+        Some(progress) => PROGRESS.scope(progress, future).await,
+        None => future.await,
+    }
+}
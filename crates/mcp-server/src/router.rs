@@ -259,7 +259,10 @@ pub trait Router: Send + Sync + 'static {
         async move {
             let prompts = self.list_prompts().unwrap_or_default();
 
-            let result = ListPromptsResult { prompts };
+            let result = ListPromptsResult {
+                prompts,
+                next_cursor: None,
+            };
 
             let mut response = self.create_response(req.id);
             response.result =
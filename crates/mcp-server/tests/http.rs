@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+use mcp_client::transport::{SseTransport, Transport, TransportHandle};
+use mcp_client::McpService;
+use mcp_core::content::Content;
+use mcp_core::handler::{ResourceError, ToolError};
+use mcp_core::protocol::{ProgressNotificationParams, ProgressToken, ServerCapabilities};
+use mcp_core::resource::Resource;
+use mcp_core::tool::Tool;
+use mcp_server::router::{CapabilitiesBuilder, RouterService};
+use mcp_server::{Progress, Router};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+
+const TOKEN: &str = "test-token";
+
+fn auth_headers() -> HashMap<String, String> {
+    HashMap::from([("Authorization".to_string(), format!("Bearer {}", TOKEN))])
+}
+
+#[derive(Clone, Default)]
+struct CounterRouter {
+    counter: Arc<AtomicI32>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Records that the request running the wait tool was cancelled
+struct CancelledGuard(Arc<AtomicBool>);
+
+impl Drop for CancelledGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Router for CounterRouter {
+    fn name(&self) -> String {
+        "counter".to_string()
+    }
+
+    fn instructions(&self) -> String {
+        "Counts calls to increment".to_string()
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        CapabilitiesBuilder::new().with_tools(false).build()
+    }
+
+    fn list_tools(&self) -> Vec<Tool> {
+        vec![
+            Tool::new(
+                "increment".to_string(),
+                "Increment the counter by 1".to_string(),
+                json!({"type": "object", "properties": {}}),
+            ),
+            Tool::new(
+                "wait".to_string(),
+                "Report some progress, then wait until cancelled".to_string(),
+                json!({"type": "object", "properties": {}}),
+            ),
+        ]
+    }
+
+    fn call_tool(
+        &self,
+        tool_name: &str,
+        _arguments: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        let counter = Arc::clone(&self.counter);
+        let cancelled = Arc::clone(&self.cancelled);
+        let tool_name = tool_name.to_string();
+        Box::pin(async move {
+            match tool_name.as_str() {
+                "increment" => {
+                    let value = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    Ok(vec![Content::text(value.to_string())])
+                }
+                "wait" => {
+                    let _guard = CancelledGuard(cancelled);
+                    if let Some(progress) = Progress::current() {
+                        progress.report(1.0, Some(2.0), Some("Waiting".to_string()));
+                    }
+                    futures::future::pending().await
+                }
+                _ => Err(ToolError::NotFound(tool_name)),
+            }
+        })
+    }
+
+    fn list_resources(&self) -> Vec<Resource> {
+        vec![]
+    }
+
+    fn read_resource(
+        &self,
+        uri: &str,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
+        let uri = uri.to_string();
+        Box::pin(async move { Err(ResourceError::NotFound(uri)) })
+    }
+}
+
+async fn start_server() -> String {
+    start_server_with(CounterRouter::default()).await
+}
+
+async fn start_server_with(router: CounterRouter) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(mcp_server::http::serve(
+        listener,
+        RouterService(router),
+        TOKEN,
+    ));
+    addr.to_string()
+}
+
+#[tokio::test]
+async fn test_sse_and_websocket_sessions() {
+    let addr = start_server().await;
+
+    // The client's SSE transport talks to the server's SSE sessions
+    let transport = SseTransport::new(format!("http://{}/sse", addr), HashMap::new())
+        .with_headers(auth_headers());
+    let handle = transport.start().await.unwrap();
+    let mut client = McpClient::new(McpService::with_timeout(handle, Duration::from_secs(5)));
+    let info = client
+        .initialize(
+            ClientInfo {
+                name: "test-client".into(),
+                version: "1.0.0".into(),
+            },
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(info.server_info.name, "counter");
+
+    let tools = client.list_tools(None).await.unwrap();
+    assert_eq!(tools.tools[0].name, "increment");
+    let result = client.call_tool("increment", json!({})).await.unwrap();
+    assert_eq!(result.content, vec![Content::text("1")]);
+
+    // A WebSocket session shares the router with the SSE session
+    let mut request = format!("ws://{}/ws", addr).into_client_request().unwrap();
+    request.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", TOKEN).parse().unwrap(),
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {"name": "increment", "arguments": {}}
+    });
+    socket
+        .send(Message::Text(request.to_string()))
+        .await
+        .unwrap();
+    let response = match socket.next().await.unwrap().unwrap() {
+        Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+        other => panic!("Unexpected frame {:?}", other),
+    };
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["content"][0]["text"], "2");
+}
+
+#[tokio::test]
+async fn test_message_to_unknown_session() {
+    let addr = start_server().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/message?sessionId=missing", addr))
+        .bearer_auth(TOKEN)
+        .body(json!({"jsonrpc": "2.0", "method": "ping", "id": 1}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_serve_on_bare_port() {
+    // A bare port binds to this machine only
+    let (addr, server) =
+        mcp_server::http::serve_on("0", RouterService(CounterRouter::default()), TOKEN)
+            .await
+            .unwrap();
+    assert!(addr.ip().is_loopback());
+    tokio::spawn(server);
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/message?sessionId=missing", addr))
+        .bearer_auth(TOKEN)
+        .body(json!({"jsonrpc": "2.0", "method": "ping", "id": 1}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_unauthorized_requests() {
+    let addr = start_server().await;
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/message?sessionId=missing", addr);
+
+    // Requests need the token
+    let response = client.post(&url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = client.post(&url).bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Pages on other origins are refused even with the token
+    let response = client
+        .post(&url)
+        .bearer_auth(TOKEN)
+        .header("Origin", "https://example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    let response = client
+        .post(&url)
+        .bearer_auth(TOKEN)
+        .header("Origin", format!("http://{}", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    // The WebSocket upgrade is refused without the token
+    assert!(
+        tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_progress_and_cancellation() {
+    let router = CounterRouter::default();
+    let addr = start_server_with(router.clone()).await;
+
+    let transport = SseTransport::new(format!("http://{}/sse", addr), HashMap::new())
+        .with_headers(auth_headers());
+    let handle = transport.start().await.unwrap();
+    let mut notifications = handle.subscribe();
+    let mut client = McpClient::new(McpService::with_timeout(handle, Duration::from_secs(5)));
+    client
+        .initialize(
+            ClientInfo {
+                name: "test-client".into(),
+                version: "1.0.0".into(),
+            },
+            ClientCapabilities::default(),
+        )
+        .await
+        .unwrap();
+
+    // The tool's progress arrives while it runs
+    let token = ProgressToken::String("call-1".to_string());
+    let call = client.call_tool_with_progress("wait", json!({}), token.clone());
+    let notification = tokio::select! {
+        _ = call => panic!("The wait tool should not finish"),
+        notification = notifications.recv() => notification.unwrap(),
+    };
+    assert_eq!(notification.method, "notifications/progress");
+    let params: ProgressNotificationParams =
+        serde_json::from_value(notification.params.unwrap()).unwrap();
+    assert_eq!(params.progress_token, token);
+    assert_eq!(params.message.as_deref(), Some("Waiting"));
+
+    // Dropping the call cancels the request, which stops the tool
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !router.cancelled.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The server should cancel the request");
+}
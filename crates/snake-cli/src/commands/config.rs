@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use goose::config::{Config, ConfigEntry};
+use serde_json::Value;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show the effective configuration, after merging every layer
+    Show {
+        /// Show which layers each value came from
+        #[arg(long)]
+        origin: bool,
+    },
+
+    /// Read a project's .goose/config.yaml from now on
+    Trust {
+        /// The project directory, the nearest project by default
+        dir: Option<PathBuf>,
+    },
+
+    /// Stop reading a project's .goose/config.yaml
+    Untrust {
+        /// The project directory, the nearest project by default
+        dir: Option<PathBuf>,
+    },
+}
+
+impl ConfigCommand {
+    pub fn run(&self) -> Result<()> {
+        match self {
+            ConfigCommand::Show { origin } => {
+                let config = Config::global();
+                let entries = config.entries()?;
+                print!("{}", render_entries(&entries, *origin)?);
+                if let (Some(path), Some(root)) = (config.project_path(), config.project_root()) {
+                    if !config.is_trusted_project(&root) {
+                        eprintln!(
+                            "Ignored {} as the project isn't trusted, run 'goose config trust' to use it",
+                            path.display()
+                        );
+                    }
+                }
+            }
+            ConfigCommand::Trust { dir } => {
+                let dir = project_dir(dir.as_ref())?;
+                let dir = Config::global().trust_project(&dir)?;
+                println!("Trusted the project config of {}", dir.display());
+            }
+            ConfigCommand::Untrust { dir } => {
+                let dir = project_dir(dir.as_ref())?;
+                if Config::global().untrust_project(&dir)? {
+                    println!("No longer trusting the project config of {}", dir.display());
+                } else {
+                    println!("{} wasn't trusted", dir.display());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The directory a trust command is about, the nearest project unless one is given
+fn project_dir(dir: Option<&PathBuf>) -> Result<PathBuf> {
+    dir.cloned()
+        .or_else(|| Config::global().project_root())
+        .ok_or_else(|| anyhow!("No project directory found"))
+}
+
+/// Parse a `KEY=VALUE` flag into the key and its value, which is JSON or else a string
+pub fn parse_flag(flag: &str) -> Result<(String, Value)> {
+    let (key, value) = flag
+        .split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| anyhow!("Expected KEY=VALUE, got '{}'", flag))?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+/// Render the entries as YAML, noting the origins of each key after it when asked
+fn render_entries(entries: &BTreeMap<String, ConfigEntry>, origin: bool) -> Result<String> {
+    let mut output = String::new();
+    if entries.is_empty() {
+        writeln!(
+            output,
+            "No configuration found. Run 'goose configure' first"
+        )?;
+        return Ok(output);
+    }
+
+    for (key, entry) in entries {
+        let yaml = serde_yaml::to_string(&BTreeMap::from([(key, &entry.value)]))?;
+        let mut lines = yaml.lines();
+        let first = lines.next().unwrap_or_default();
+        if origin {
+            let origins: Vec<String> = entry.origins.iter().map(ToString::to_string).collect();
+            writeln!(output, "{}  # {}", first, origins.join(", "))?;
+        } else {
+            writeln!(output, "{}", first)?;
+        }
+        for line in lines {
+            writeln!(output, "{}", line)?;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::config::ConfigOrigin;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_entries() {
+        let user = PathBuf::from("/home/me/.config/goose/config.yaml");
+        let project = PathBuf::from("/src/app/.goose/config.yaml");
+        let entries = BTreeMap::from([
+            (
+                "GOOSE_MODEL".to_string(),
+                ConfigEntry {
+                    value: json!("gpt-4o"),
+                    origins: vec![ConfigOrigin::Env("GOOSE_MODEL".to_string())],
+                },
+            ),
+            (
+                "extensions".to_string(),
+                ConfigEntry {
+                    value: json!({"jetbrains": {"enabled": false}}),
+                    origins: vec![ConfigOrigin::User(user), ConfigOrigin::Project(project)],
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            render_entries(&entries, false).unwrap(),
+            "GOOSE_MODEL: gpt-4o\nextensions:\n  jetbrains:\n    enabled: false\n"
+        );
+        assert_eq!(
+            render_entries(&entries, true).unwrap(),
+            "GOOSE_MODEL: gpt-4o  # env GOOSE_MODEL\n\
+             extensions:  # user /home/me/.config/goose/config.yaml, project /src/app/.goose/config.yaml\n  \
+             jetbrains:\n    enabled: false\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert_eq!(
+            parse_flag("GOOSE_MODEL=gpt-4o").unwrap(),
+            ("GOOSE_MODEL".to_string(), json!("gpt-4o"))
+        );
+        assert_eq!(
+            parse_flag("GOOSE_MAX_TURNS=10").unwrap(),
+            ("GOOSE_MAX_TURNS".to_string(), json!(10))
+        );
+        assert!(parse_flag("GOOSE_MODEL").is_err());
+        assert!(parse_flag("=gpt-4o").is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use cliclack::spinner;
 use console::style;
-use goose::agents::{extension::Envs, ExtensionConfig};
-use goose::config::{Config, ConfigError, ExtensionEntry, ExtensionManager};
+use goose::agents::{extension::Envs, AgentFactory, ExtensionConfig, ToolFilter};
+use goose::config::{
+    Config, ConfigError, ConfigResolver, ConfigScope, ExtensionEntry, ExtensionManager, Profile,
+    ProfileManager, PROFILE_CONFIG_KEY, PROJECT_CONFIG_DIR,
+};
 use goose::message::Message;
+use goose::providers::base::Provider;
 use goose::providers::{create, providers};
 use mcp_core::Tool;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 
 pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
@@ -38,6 +42,7 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
                     enabled: true,
                     config: ExtensionConfig::Builtin {
                         name: "developer".to_string(),
+                        tools: ToolFilter::default(),
                     },
                 })?;
             }
@@ -63,7 +68,16 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
                     }
                     Some(ConfigError::KeyringError(msg)) => {
                         println!(
-                            "\n  {} Failed to access secure storage (keyring): {} \n  Please check your system keychain and run '{}' again. \n  If your system is unable to use the keyring, please try setting secret key(s) via environment variables.",
+                            "\n  {} Failed to access secure storage (keyring): {} \n  Please check your system keychain and run '{}' again. \n  If your system is unable to use the keyring, store secrets in an encrypted file with '{}' or set them via environment variables.",
+                            style("Error").red().italic(),
+                            msg,
+                            style("goose configure").cyan(),
+                            style("goose secrets migrate --to file").cyan()
+                        );
+                    }
+                    Some(ConfigError::SecretsError(msg)) => {
+                        println!(
+                            "\n  {} {} \n  Please fix your secrets storage and run '{}' again",
                             style("Error").red().italic(),
                             msg,
                             style("goose configure").cyan()
@@ -143,11 +157,42 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
     }
 }
 
-/// Dialog for configuring the AI provider and model
+/// Dialog for configuring the AI provider and model, or the profiles to switch to
 pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     // Get global config instance
     let config = Config::global();
 
+    // Profiles are offered once there is a default provider to fall back on
+    if config.exists() {
+        let profiles = ProfileManager::get_all()?;
+        let mut action = cliclack::select("What would you like to configure?")
+            .item(
+                "default",
+                "Default Provider",
+                "The provider and model used without a profile",
+            )
+            .item(
+                "profile",
+                "Add or Edit Profile",
+                "A provider, model and extensions to choose with --profile",
+            );
+        if !profiles.is_empty() {
+            action = action
+                .item(
+                    "default_profile",
+                    "Choose Default Profile",
+                    "The profile used when none is given",
+                )
+                .item("remove_profile", "Remove Profile", "");
+        }
+        match action.interact()? {
+            "profile" => return configure_profile_dialog(profiles).await,
+            "default_profile" => return default_profile_dialog(profiles),
+            "remove_profile" => return remove_profile_dialog(profiles),
+            _ => {}
+        }
+    }
+
     // Get all available providers and their metadata
     let available_providers = providers();
 
@@ -263,13 +308,15 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     config.set("GOOSE_PROVIDER", Value::String(provider_name.to_string()))?;
     config.set("GOOSE_MODEL", Value::String(model.clone()))?;
 
-    // Test the configuration
-    let spin = spinner();
-    spin.start("Checking your configuration...");
-
     // Use max tokens to speed up the provider test.
     let model_config = goose::model::ModelConfig::new(model.clone()).with_max_tokens(Some(10));
-    let provider = create(provider_name, model_config)?;
+    check_provider(create(provider_name, model_config, &config.resolver())?).await
+}
+
+/// Check that a provider can complete a request with a tool, reporting how it went
+async fn check_provider(provider: Box<dyn Provider + Send + Sync>) -> Result<bool, Box<dyn Error>> {
+    let spin = spinner();
+    spin.start("Checking your configuration...");
 
     let messages =
         vec![Message::user().with_text("What is the weather like in San Francisco today?")];
@@ -306,6 +353,247 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     }
 }
 
+/// Dialog for adding a profile or editing one
+async fn configure_profile_dialog(
+    profiles: BTreeMap<String, Profile>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = Config::global();
+
+    // None stands for a new profile
+    let chosen: Option<String> = if profiles.is_empty() {
+        None
+    } else {
+        let mut select = cliclack::select("Which profile?").item(None, "New profile", "");
+        for (name, profile) in &profiles {
+            select = select.item(
+                Some(name.clone()),
+                name,
+                format!("{} {}", profile.provider, profile.model),
+            );
+        }
+        select.interact()?
+    };
+    let (name, existing) = match chosen {
+        Some(name) => {
+            let existing = profiles.get(&name).cloned();
+            (name, existing)
+        }
+        None => {
+            let name: String = cliclack::input("Name the profile:")
+                .placeholder("work")
+                .validate(|input: &String| {
+                    if input.trim().is_empty() || input.contains(char::is_whitespace) {
+                        Err("Profile names can't be empty or contain spaces")
+                    } else {
+                        Ok(())
+                    }
+                })
+                .interact()?;
+            (name, None)
+        }
+    };
+    let existing = existing.unwrap_or_default();
+
+    let available_providers = providers();
+    let provider_items: Vec<(&String, &str, &str)> = available_providers
+        .iter()
+        .map(|p| (&p.name, p.display_name.as_str(), p.description.as_str()))
+        .collect();
+    let provider_name = cliclack::select(format!("Which model provider should {} use?", name))
+        .initial_value(&existing.provider)
+        .items(&provider_items)
+        .interact()?;
+    let provider_meta = available_providers
+        .iter()
+        .find(|p| &p.name == provider_name)
+        .expect("Selected provider must exist in metadata");
+
+    // Secrets can be shared with the default provider or kept for this profile alone
+    let mut secrets = HashMap::new();
+    for key in provider_meta.config_keys.iter().filter(|key| key.required) {
+        let profile_key = format!("{}_{}", key.name, name.to_uppercase().replace('-', "_"));
+        if !key.secret {
+            if config.get::<String>(&key.name).is_err() {
+                let value: String = cliclack::input(format!(
+                    "Provider {} requires {}, please enter a value",
+                    provider_meta.display_name, key.name
+                ))
+                .interact()?;
+                config.set(&key.name, Value::String(value))?;
+            }
+            continue;
+        }
+
+        let own = cliclack::select(format!("Which {} should {} use?", key.name, name))
+            .initial_value(existing.secrets.contains_key(&key.name))
+            .item(false, "Shared", "The one used without a profile")
+            .item(true, "Its own", format!("Stored as {}", profile_key))
+            .interact()?;
+        let stored_key = if own {
+            secrets.insert(key.name.clone(), profile_key.clone());
+            profile_key
+        } else {
+            key.name.clone()
+        };
+        let configured = config.get_secret::<String>(&stored_key).is_ok();
+        let update = !configured
+            || cliclack::confirm(format!(
+                "{} is already configured, would you like to update it?",
+                stored_key
+            ))
+            .initial_value(false)
+            .interact()?;
+        if update {
+            let value: String = cliclack::password(format!("Enter a value for {}", stored_key))
+                .mask('▪')
+                .interact()?;
+            config.set_secret(&stored_key, Value::String(value))?;
+        }
+    }
+
+    let default_model = if existing.provider == *provider_name {
+        existing.model.clone()
+    } else {
+        provider_meta.default_model.clone()
+    };
+    let model: String = cliclack::input("Enter a model from that provider:")
+        .default_input(&default_model)
+        .interact()?;
+
+    let temperature: String = cliclack::input("Temperature (leave empty for the provider default)")
+        .default_input(
+            &existing
+                .temperature
+                .map(|t| t.to_string())
+                .unwrap_or_default(),
+        )
+        .required(false)
+        .validate(|input: &String| {
+            if input.is_empty() || input.parse::<f32>().is_ok() {
+                Ok(())
+            } else {
+                Err("Enter a number, e.g. 0.2")
+            }
+        })
+        .interact()?;
+
+    let versions = AgentFactory::available_versions();
+    let current_version = existing
+        .agent_version
+        .clone()
+        .unwrap_or_else(|| AgentFactory::default_version().to_string());
+    let agent_version: String = cliclack::select("Which agent version should it use?")
+        .initial_value(current_version)
+        .items(
+            &versions
+                .iter()
+                .map(|v| (v.to_string(), *v, ""))
+                .collect::<Vec<_>>(),
+        )
+        .interact()?;
+
+    let extensions = ExtensionManager::get_all()?;
+    let extension_names: Vec<String> = extensions
+        .iter()
+        .map(|entry| entry.config.name().to_string())
+        .collect();
+    let selected_extensions = if extension_names.is_empty() {
+        None
+    } else {
+        let initial = existing.extensions.clone().unwrap_or_else(|| {
+            extensions
+                .iter()
+                .filter(|entry| entry.enabled)
+                .map(|entry| entry.config.name().to_string())
+                .collect()
+        });
+        let selected: Vec<String> = cliclack::multiselect(
+            "enable extensions: (use \"space\" to toggle and \"enter\" to submit)",
+        )
+        .required(false)
+        .items(
+            &extension_names
+                .iter()
+                .map(|name| (name.clone(), name.as_str(), ""))
+                .collect::<Vec<_>>(),
+        )
+        .initial_values(initial)
+        .interact()?;
+        Some(selected)
+    };
+
+    let profile = Profile {
+        provider: provider_name.to_string(),
+        model,
+        temperature: temperature.parse().ok(),
+        agent_version: Some(agent_version),
+        extensions: selected_extensions,
+        secrets,
+    };
+
+    // Use max tokens to speed up the provider test.
+    let model_config = profile.model_config().with_max_tokens(Some(10));
+    let provider = create(
+        &profile.provider,
+        model_config,
+        &ConfigResolver::new(config, &profile.secrets),
+    )?;
+    let ok = check_provider(provider).await?;
+    if ok {
+        ProfileManager::set(&name, profile)?;
+        let _ = cliclack::log::info(format!("Use it with: goose session --profile {}", name));
+    }
+    Ok(ok)
+}
+
+/// Dialog for choosing the profile used when none is given on the command line
+fn default_profile_dialog(profiles: BTreeMap<String, Profile>) -> Result<bool, Box<dyn Error>> {
+    let config = Config::global();
+    let current: String = config.get(PROFILE_CONFIG_KEY).unwrap_or_default();
+    let mut select = cliclack::select("Which profile should goose use by default?")
+        .initial_value(current)
+        .item(String::new(), "None", "Use the default provider");
+    for (name, profile) in &profiles {
+        select = select.item(
+            name.clone(),
+            name,
+            format!("{} {}", profile.provider, profile.model),
+        );
+    }
+    let name = select.interact()?;
+    config.set(
+        PROFILE_CONFIG_KEY,
+        if name.is_empty() {
+            Value::Null
+        } else {
+            Value::String(name)
+        },
+    )?;
+    cliclack::outro("Default profile updated")?;
+    Ok(true)
+}
+
+fn remove_profile_dialog(profiles: BTreeMap<String, Profile>) -> Result<bool, Box<dyn Error>> {
+    let mut select = cliclack::select("Which profile should be removed?");
+    for (name, profile) in &profiles {
+        select = select.item(
+            name.clone(),
+            name,
+            format!("{} {}", profile.provider, profile.model),
+        );
+    }
+    let name = select.interact()?;
+    ProfileManager::remove(&name)?;
+
+    // Don't leave the default pointing at a profile that's gone
+    let config = Config::global();
+    if config.get::<String>(PROFILE_CONFIG_KEY).ok().as_deref() == Some(name.as_str()) {
+        config.set(PROFILE_CONFIG_KEY, Value::Null)?;
+    }
+    cliclack::outro(format!("Removed profile {}", name))?;
+    Ok(true)
+}
+
 /// Configure extensions that can be used with goose
 /// Dialog for toggling which extensions are enabled/disabled
 pub fn toggle_extensions_dialog() -> Result<(), Box<dyn Error>> {
@@ -345,9 +633,31 @@ pub fn toggle_extensions_dialog() -> Result<(), Box<dyn Error>> {
     .initial_values(enabled_extensions)
     .interact()?;
 
+    // Projects can turn extensions on and off in their own config
+    let project_path = Config::global()
+        .project_path()
+        .or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|dir| dir.join(PROJECT_CONFIG_DIR).join("config.yaml"))
+        })
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let scope = cliclack::select("Where should these settings apply?")
+        .item(ConfigScope::User, "Everywhere", "")
+        .item(ConfigScope::Project, "This project only", &project_path)
+        .interact()?;
+
+    // The user chose the project, so its config can be read from now on
+    if scope == ConfigScope::Project {
+        if let Some(dir) = Config::global().project_root() {
+            Config::global().trust_project(&dir)?;
+        }
+    }
+
     // Update enabled status for each extension
     for name in extension_status.iter().map(|(name, _)| name) {
-        ExtensionManager::set_enabled(name, selected.iter().any(|s| s.as_str() == name))?;
+        ExtensionManager::set_enabled_in(scope, name, selected.iter().any(|s| s.as_str() == name))?;
     }
 
     cliclack::outro("Extension settings updated successfully")?;
@@ -371,6 +681,11 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
             "Remote Extension",
             "Connect to a remote extension via SSE",
         )
+        .item(
+            "streamable_http",
+            "Remote Extension (Streamable HTTP)",
+            "Connect to a remote extension via a single HTTP endpoint",
+        )
         .interact()?;
 
     match extension_type {
@@ -405,6 +720,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 enabled: true,
                 config: ExtensionConfig::Builtin {
                     name: extension.clone(),
+                    tools: ToolFilter::default(),
                 },
             })?;
 
@@ -470,6 +786,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     cmd,
                     args,
                     envs: Envs::new(envs),
+                    tools: ToolFilter::default(),
                 },
             })?;
 
@@ -531,6 +848,69 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     name: name.clone(),
                     uri,
                     envs: Envs::new(envs),
+                    headers: HashMap::new(),
+                    tools: ToolFilter::default(),
+                },
+            })?;
+
+            cliclack::outro(format!("Added {} extension", style(name).green()))?;
+        }
+        "streamable_http" => {
+            let extensions = ExtensionManager::get_all_names()?;
+            let name: String = cliclack::input("What would you like to call this extension?")
+                .placeholder("my-remote-extension")
+                .validate(move |input: &String| {
+                    if input.is_empty() {
+                        Err("Please enter a name")
+                    } else if extensions.contains(input) {
+                        Err("An extension with this name already exists")
+                    } else {
+                        Ok(())
+                    }
+                })
+                .interact()?;
+
+            let uri: String = cliclack::input("What is the endpoint URI?")
+                .placeholder("http://localhost:8000/mcp")
+                .validate(|input: &String| {
+                    if input.is_empty() {
+                        Err("Please enter a URI")
+                    } else if !input.starts_with("http") {
+                        Err("URI should start with http:// or https://")
+                    } else {
+                        Ok(())
+                    }
+                })
+                .interact()?;
+
+            let add_headers =
+                cliclack::confirm("Would you like to add headers, e.g. for authorization?")
+                    .interact()?;
+
+            let mut headers = HashMap::new();
+            if add_headers {
+                loop {
+                    let key: String = cliclack::input("Header name:")
+                        .placeholder("Authorization")
+                        .interact()?;
+
+                    let value: String = cliclack::password("Header value:").mask('▪').interact()?;
+
+                    headers.insert(key, value);
+
+                    if !cliclack::confirm("Add another header?").interact()? {
+                        break;
+                    }
+                }
+            }
+
+            ExtensionManager::set(ExtensionEntry {
+                enabled: true,
+                config: ExtensionConfig::StreamableHttp {
+                    name: name.clone(),
+                    uri,
+                    headers,
+                    tools: ToolFilter::default(),
                 },
             })?;
 
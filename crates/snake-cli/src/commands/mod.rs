@@ -1,5 +1,8 @@
 pub mod agent_version;
+pub mod config;
 pub mod configure;
 pub mod mcp;
+pub mod secrets;
 pub mod session;
+pub mod usage;
 pub mod version;
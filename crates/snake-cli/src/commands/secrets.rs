@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use goose::config::{Config, SecretsBackend, SECRETS_BACKEND_CONFIG_KEY, SECRETS_PASSPHRASE_ENV};
+use serde_json::Value;
+
+#[derive(Subcommand)]
+pub enum SecretsCommand {
+    /// Copy the stored secrets to another backend and switch to it
+    Migrate {
+        /// The backend to copy from, the current one by default
+        #[arg(long, value_name = "BACKEND")]
+        from: Option<SecretsBackend>,
+
+        /// The backend to copy to: keyring, file or env
+        #[arg(
+            long,
+            value_name = "BACKEND",
+            long_help = "The backend to copy to. keyring stores secrets in the system keyring. file stores them in an encrypted file beside the config file, unlocked with the passphrase in GOOSE_SECRETS_PASSPHRASE. env stores nothing, so the secrets are printed as KEY=VALUE lines to set as environment variables instead."
+        )]
+        to: SecretsBackend,
+    },
+}
+
+impl SecretsCommand {
+    pub fn run(&self) -> Result<()> {
+        match self {
+            SecretsCommand::Migrate { from, to } => {
+                let config = Config::global();
+                let from = from.unwrap_or_else(|| config.secrets_backend());
+                if from == *to {
+                    bail!("Secrets are already stored in the {} backend", to);
+                }
+                if from == SecretsBackend::File || *to == SecretsBackend::File {
+                    let confirm = *to == SecretsBackend::File && !config.secrets_path().exists();
+                    prompt_passphrase(config, confirm)?;
+                }
+
+                let values = config.secret_store_for(from).load()?;
+                if *to == SecretsBackend::Env {
+                    eprintln!(
+                        "Set these environment variables wherever goose runs, they won't be stored:"
+                    );
+                    for (key, value) in values.iter().collect::<BTreeMap<_, _>>() {
+                        // Secrets read from the environment are parsed as JSON unless they
+                        // are plain strings
+                        match value {
+                            Value::String(value) => println!("{}={}", key, value),
+                            value => println!("{}={}", key, value),
+                        }
+                    }
+                } else {
+                    let store = config.secret_store_for(*to);
+                    let mut merged = store.load()?;
+                    merged.extend(values.clone());
+                    store.save(&merged)?;
+                }
+
+                config.set(SECRETS_BACKEND_CONFIG_KEY, Value::String(to.to_string()))?;
+                eprintln!(
+                    "Copied {} secrets from the {} backend to the {} backend. The {} backend was left as it was",
+                    values.len(),
+                    from,
+                    to,
+                    from
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ask for the passphrase of the encrypted secrets file, when secrets are stored in it
+/// and it isn't set in `GOOSE_SECRETS_PASSPHRASE`
+pub fn unlock_secrets() -> Result<()> {
+    let config = Config::global();
+    if config.secrets_backend() == SecretsBackend::File {
+        prompt_passphrase(config, !config.secrets_path().exists())?;
+    }
+    Ok(())
+}
+
+fn prompt_passphrase(config: &Config, confirm: bool) -> Result<()> {
+    if config.has_secrets_passphrase() || !std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+
+    let passphrase: String = cliclack::password("Enter the passphrase for your secrets")
+        .mask('▪')
+        .interact()?;
+    if confirm {
+        let again: String = cliclack::password("Enter the passphrase again")
+            .mask('▪')
+            .interact()?;
+        if again != passphrase {
+            bail!(
+                "The passphrases don't match. You can also set {}",
+                SECRETS_PASSPHRASE_ENV
+            );
+        }
+    }
+    config.set_secrets_passphrase(passphrase);
+    Ok(())
+}
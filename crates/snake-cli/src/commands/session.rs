@@ -1,14 +1,23 @@
+use anyhow::Result;
+use clap::Subcommand;
 use rand::{distributions::Alphanumeric, Rng};
+use std::fmt::Write;
+use std::path::PathBuf;
 use std::process;
 
+use crate::prompt::json::{JsonPrompt, OutputFormat};
 use crate::prompt::rustyline::RustylinePrompt;
-use crate::session::{ensure_session_dir, get_most_recent_session, Session};
+use crate::prompt::Prompt;
+use crate::session::{Session, SETUP_FAILED_EXIT_CODE};
+use crate::transcript::{export, ExportFormat};
+use chrono::Local;
 use console::style;
 use goose::agents::extension::{Envs, ExtensionError};
-use goose::agents::AgentFactory;
-use goose::config::{Config, ExtensionConfig, ExtensionManager};
-use goose::providers::create;
-use std::path::Path;
+use goose::agents::ToolFilter;
+use goose::config::{Config, ExtensionConfig, Profile};
+use goose::message::MessageContent;
+use goose::session::{self, SessionMetadata, SessionStore};
+use mcp_core::role::Role;
 
 use mcp_client::transport::Error as McpClientError;
 
@@ -17,51 +26,50 @@ pub async fn build_session(
     resume: bool,
     extension: Option<String>,
     builtin: Option<String>,
+    profile: Option<String>,
+    output_format: OutputFormat,
 ) -> Session<'static> {
     // Load config and get provider/model
     let config = Config::global();
 
-    let provider_name: String = config
-        .get("GOOSE_PROVIDER")
-        .expect("No provider configured. Run 'goose configure' first");
-    let session_dir = ensure_session_dir().expect("Failed to create session directory");
-
-    let model: String = config
-        .get("GOOSE_MODEL")
-        .expect("No model configured. Run 'goose configure' first");
-    let model_config = goose::model::ModelConfig::new(model.clone());
-    let provider = create(&provider_name, model_config).expect("Failed to create provider");
+    let profile = Profile::from_config(config, profile.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(SETUP_FAILED_EXIT_CODE);
+    });
+    let provider_name = profile.provider.clone();
+    let model = profile.model.clone();
+    let store = session::from_config(config).unwrap_or_else(|e| {
+        eprintln!("Failed to open the session store: {}", e);
+        process::exit(SETUP_FAILED_EXIT_CODE);
+    });
 
     // Create the agent
-    let agent_version: Option<String> = config.get("GOOSE_AGENT").ok();
-    let mut agent = match agent_version {
-        Some(version) => AgentFactory::create(&version, provider),
-        None => AgentFactory::create(AgentFactory::default_version(), provider),
-    }
-    .expect("Failed to create agent");
+    let mut agent = profile.create_agent().unwrap_or_else(|e| {
+        eprintln!("Failed to create agent: {}", e);
+        process::exit(SETUP_FAILED_EXIT_CODE);
+    });
 
     // Setup extensions for the agent
-    for extension in ExtensionManager::get_all().expect("should load extensions") {
-        if extension.enabled {
-            let config = extension.config.clone();
-            agent
-                .add_extension(config.clone())
-                .await
-                .unwrap_or_else(|e| {
-                    let err = match e {
-                        ExtensionError::Transport(McpClientError::StdioProcessError(inner)) => {
-                            inner
-                        }
-                        _ => e.to_string(),
-                    };
-                    println!("Failed to start extension: {}, {:?}", config.name(), err);
-                    println!(
-                        "Please check extension configuration for {}.",
-                        config.name()
-                    );
-                    process::exit(1);
-                });
-        }
+    let extensions = profile.extensions().unwrap_or_else(|e| {
+        eprintln!("Failed to load extensions: {}", e);
+        process::exit(SETUP_FAILED_EXIT_CODE);
+    });
+    for config in extensions {
+        agent
+            .add_extension(config.clone())
+            .await
+            .unwrap_or_else(|e| {
+                let err = match e {
+                    ExtensionError::Transport(McpClientError::StdioProcessError(inner)) => inner,
+                    _ => e.to_string(),
+                };
+                eprintln!("Failed to start extension: {}, {:?}", config.name(), err);
+                eprintln!(
+                    "Please check extension configuration for {}.",
+                    config.name()
+                );
+                process::exit(SETUP_FAILED_EXIT_CODE);
+            });
     }
 
     // Add extension if provided
@@ -81,7 +89,7 @@ pub async fn build_session(
 
         if parts.is_empty() {
             eprintln!("No command provided in extension string");
-            process::exit(1);
+            process::exit(SETUP_FAILED_EXIT_CODE);
         }
 
         let cmd = parts.remove(0).to_string();
@@ -96,20 +104,24 @@ pub async fn build_session(
             cmd,
             args: parts.iter().map(|s| s.to_string()).collect(),
             envs: Envs::new(envs),
+            tools: ToolFilter::default(),
         };
 
         agent.add_extension(config).await.unwrap_or_else(|e| {
             eprintln!("Failed to start extension: {}", e);
-            process::exit(1);
+            process::exit(SETUP_FAILED_EXIT_CODE);
         });
     }
 
     // Add builtin extension if provided
     if let Some(name) = builtin {
-        let config = ExtensionConfig::Builtin { name };
+        let config = ExtensionConfig::Builtin {
+            name,
+            tools: ToolFilter::default(),
+        };
         agent.add_extension(config).await.unwrap_or_else(|e| {
             eprintln!("Failed to start builtin extension: {}", e);
-            process::exit(1);
+            process::exit(SETUP_FAILED_EXIT_CODE);
         });
     }
 
@@ -117,20 +129,28 @@ pub async fn build_session(
     if resume {
         if let Some(ref session_name) = name {
             // Try to resume specific session
-            let session_file = session_dir.join(format!("{}.jsonl", session_name));
-            if session_file.exists() {
-                let prompt = Box::new(RustylinePrompt::new());
-                return Session::new(agent, prompt, session_file);
+            if store.exists(session_name).unwrap_or(false) {
+                display_session_info(output_format, resume, &provider_name, &model, session_name);
+                let prompt = new_prompt(output_format);
+                return Session::new(agent, prompt, store, session_name.clone());
             } else {
                 eprintln!("Session '{}' not found, starting new session", session_name);
             }
         } else {
             // Try to resume most recent session
-            if let Ok(session_file) = get_most_recent_session() {
-                let prompt = Box::new(RustylinePrompt::new());
-                return Session::new(agent, prompt, session_file);
-            } else {
-                eprintln!("No previous sessions found, starting new session");
+            match store.list().map(|sessions| sessions.into_iter().next()) {
+                Ok(Some(metadata)) => {
+                    display_session_info(
+                        output_format,
+                        resume,
+                        &provider_name,
+                        &model,
+                        &metadata.name,
+                    );
+                    let prompt = new_prompt(output_format);
+                    return Session::new(agent, prompt, store, metadata.name);
+                }
+                _ => eprintln!("No previous sessions found, starting new session"),
             }
         }
     }
@@ -144,19 +164,38 @@ pub async fn build_session(
             .collect()
     });
 
-    let session_file = session_dir.join(format!("{}.jsonl", name));
-    if session_file.exists() {
-        eprintln!("Session '{}' already exists", name);
-        process::exit(1);
+    let metadata = SessionMetadata::new(name.clone()).with_provider(&provider_name, &model);
+    if let Err(e) = store.create(&metadata) {
+        eprintln!("Failed to start session: {}", e);
+        process::exit(SETUP_FAILED_EXIT_CODE);
     }
 
-    let prompt = Box::new(RustylinePrompt::new());
+    let prompt = new_prompt(output_format);
 
-    display_session_info(resume, &provider_name, &model, &session_file);
-    Session::new(agent, prompt, session_file)
+    display_session_info(output_format, false, &provider_name, &model, &name);
+    Session::new(agent, prompt, store, name)
 }
 
-fn display_session_info(resume: bool, provider: &str, model: &str, session_file: &Path) {
+fn new_prompt(output_format: OutputFormat) -> Box<dyn Prompt> {
+    match output_format {
+        OutputFormat::Text => Box::new(RustylinePrompt::new()),
+        OutputFormat::Json => Box::new(JsonPrompt::new(false)),
+        OutputFormat::StreamJson => Box::new(JsonPrompt::new(true)),
+    }
+}
+
+fn display_session_info(
+    output_format: OutputFormat,
+    resume: bool,
+    provider: &str,
+    model: &str,
+    session_name: &str,
+) {
+    // Anything else on stdout would break the JSON a script is reading
+    if output_format != OutputFormat::Text {
+        return;
+    }
+
     let start_session_msg = if resume {
         "resuming session |"
     } else {
@@ -172,7 +211,239 @@ fn display_session_info(resume: bool, provider: &str, model: &str, session_file:
     );
     println!(
         "    {} {}",
-        style("logging to").dim(),
-        style(session_file.display()).dim().cyan(),
+        style("session:").dim(),
+        style(session_name).dim().cyan(),
     );
 }
+
+#[derive(Subcommand)]
+pub enum SessionCommand {
+    /// List saved sessions, most recently used first
+    List {
+        /// Only list sessions whose name or messages contain this text
+        #[arg(short, long, value_name = "TEXT")]
+        search: Option<String>,
+    },
+
+    /// Show the transcript of a session
+    Show {
+        /// Name of the session
+        name: String,
+    },
+
+    /// Delete a session
+    Delete {
+        /// Name of the session
+        name: String,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Give a session a new name
+    Rename {
+        /// Name of the session
+        name: String,
+
+        /// The new name
+        new_name: String,
+    },
+
+    /// Export the transcript of a session to share it
+    Export {
+        /// Name of the session
+        name: String,
+
+        /// Format of the transcript
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+
+        /// Write the transcript to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+impl SessionCommand {
+    pub fn run(&self) -> Result<()> {
+        let store = session::from_config(Config::global())?;
+        match self {
+            SessionCommand::List { search } => {
+                let sessions = match search {
+                    Some(query) => store.search(query)?,
+                    None => store.list()?,
+                };
+                print!("{}", render_sessions(&sessions)?);
+            }
+            SessionCommand::Show { name } => show_session(store.as_ref(), name)?,
+            SessionCommand::Delete { name, yes } => {
+                // Fail on a missing session before asking about it
+                store.metadata(name)?;
+                let confirmed = *yes
+                    || cliclack::confirm(format!("Delete session {}?", name))
+                        .initial_value(false)
+                        .interact()?;
+                if confirmed {
+                    store.delete(name)?;
+                    println!("Deleted session {}", name);
+                }
+            }
+            SessionCommand::Rename { name, new_name } => {
+                store.rename(name, new_name)?;
+                println!("Renamed session {} to {}", name, new_name);
+            }
+            SessionCommand::Export {
+                name,
+                format,
+                output,
+            } => {
+                let metadata = store.metadata(name)?;
+                let messages = store.messages(name)?;
+                let transcript = export(*format, &metadata, &messages)?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(path, transcript)?;
+                        eprintln!("Exported session {} to {}", name, path.display());
+                    }
+                    None => print!("{}", transcript),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render_sessions(sessions: &[SessionMetadata]) -> Result<String> {
+    let mut output = String::new();
+    if sessions.is_empty() {
+        writeln!(output, "No sessions found.")?;
+        return Ok(output);
+    }
+
+    let rows: Vec<[String; 5]> = sessions
+        .iter()
+        .map(|session| {
+            [
+                session.name.clone(),
+                session
+                    .updated
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                session.message_count.to_string(),
+                session.model.clone().unwrap_or_else(|| "-".to_string()),
+                if session.usage.is_empty() {
+                    "-".to_string()
+                } else {
+                    session.total_tokens().to_string()
+                },
+            ]
+        })
+        .collect();
+    let name_width = rows
+        .iter()
+        .map(|row| row[0].len())
+        .chain(std::iter::once("NAME".len()))
+        .max()
+        .unwrap_or_default();
+    let model_width = rows
+        .iter()
+        .map(|row| row[3].len())
+        .chain(std::iter::once("MODEL".len()))
+        .max()
+        .unwrap_or_default();
+
+    writeln!(
+        output,
+        "{:<name_width$}  {:<16}  {:>8}  {:<model_width$}  {:>10}",
+        "NAME", "UPDATED", "MESSAGES", "MODEL", "TOKENS"
+    )?;
+    for [name, updated, messages, model, tokens] in &rows {
+        writeln!(
+            output,
+            "{:<name_width$}  {:<16}  {:>8}  {:<model_width$}  {:>10}",
+            name, updated, messages, model, tokens
+        )?;
+    }
+    Ok(output)
+}
+
+/// Render a session the way it looked while it ran
+fn show_session(store: &dyn SessionStore, name: &str) -> Result<()> {
+    let metadata = store.metadata(name)?;
+    let messages = store.messages(name)?;
+
+    println!(
+        "{} {} {} {}",
+        style("session:").dim(),
+        style(&metadata.name).cyan().dim(),
+        style("model:").dim(),
+        style(metadata.model.as_deref().unwrap_or("unknown"))
+            .cyan()
+            .dim(),
+    );
+    if let Some(dir) = &metadata.working_dir {
+        println!(
+            "    {} {}",
+            style("directory:").dim(),
+            style(dir.display()).cyan().dim()
+        );
+    }
+    println!();
+
+    let mut prompt = RustylinePrompt::new();
+    for message in messages {
+        if message.role != Role::User {
+            prompt.render(Box::new(message));
+            continue;
+        }
+
+        // What the user typed is shown after the input prompt, and the rest as goose shows it
+        let text = message.as_concat_text();
+        if !text.is_empty() {
+            println!("{} {}\n", style("( O)>").cyan().bold(), text);
+        }
+        let mut rest = message;
+        rest.content
+            .retain(|content| !matches!(content, MessageContent::Text(_)));
+        if !rest.content.is_empty() {
+            prompt.render(Box::new(rest));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::providers::base::{ProviderUsage, Usage};
+
+    #[test]
+    fn test_render_sessions() {
+        let mut used = SessionMetadata::new("refactor-parser").with_provider("openai", "gpt-4o");
+        used.message_count = 12;
+        used.usage = vec![
+            ProviderUsage::new(
+                "gpt-4o".to_string(),
+                Usage::new(Some(900), Some(100), Some(1000)),
+            ),
+            ProviderUsage::new(
+                "gpt-4o".to_string(),
+                Usage::new(Some(400), Some(100), Some(500)),
+            ),
+        ];
+        let unused = SessionMetadata::new("a");
+
+        let output = render_sessions(&[used, unused]).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("NAME             UPDATED"));
+        assert!(lines[1].starts_with("refactor-parser  "));
+        assert!(lines[1].contains("  12  gpt-4o"));
+        assert!(lines[1].ends_with("1500"));
+        assert!(lines[2].ends_with("-"));
+
+        assert_eq!(render_sessions(&[]).unwrap(), "No sessions found.\n");
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::Args;
+use goose::config::Config;
+use goose::providers::pricing::Pricing;
+
+use crate::log_usage::{read_usage_log, usage_log_path, SessionLog};
+
+#[derive(Args)]
+pub struct UsageCommand {
+    /// Only include sessions closed on or after this day
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    since: Option<NaiveDate>,
+}
+
+/// Token and cost totals for one model in one session on one day
+#[derive(Debug, Default, Clone, PartialEq)]
+struct UsageRow {
+    input_tokens: i64,
+    output_tokens: i64,
+    /// None when none of the requests had a known price
+    cost: Option<f64>,
+}
+
+impl UsageRow {
+    fn add(&mut self, other: &UsageRow) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost = match (self.cost, other.cost) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// Day, session and model a row is grouped by
+type UsageKey = (String, String, String);
+
+impl UsageCommand {
+    pub fn run(&self) -> Result<()> {
+        let path = usage_log_path().context("Failed to determine home directory")?;
+        let logs = if path.exists() {
+            read_usage_log(&path)
+                .with_context(|| format!("Failed to read usage log {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+
+        let pricing = Pricing::from_config(Config::global());
+        let rows = aggregate(&logs, &pricing, self.since);
+
+        print!("{}", render(&rows)?);
+        Ok(())
+    }
+}
+
+fn aggregate(
+    logs: &[SessionLog],
+    pricing: &Pricing,
+    since: Option<NaiveDate>,
+) -> BTreeMap<UsageKey, UsageRow> {
+    let mut rows: BTreeMap<UsageKey, UsageRow> = BTreeMap::new();
+    for log in logs {
+        let date = log.timestamp.map(|t| t.with_timezone(&Local).date_naive());
+        if let Some(since) = since {
+            // Sessions logged before timestamps were recorded can't be placed in time
+            if date.is_none_or(|date| date < since) {
+                continue;
+            }
+        }
+
+        let day = date.map_or_else(|| "unknown".to_string(), |d| d.to_string());
+        // Older logs name the session by the path of its file
+        let path = Path::new(&log.session_file);
+        let session = match path.extension() {
+            Some(ext) if ext == "jsonl" => path.file_stem().and_then(|s| s.to_str()),
+            _ => None,
+        }
+        .unwrap_or(&log.session_file)
+        .to_string();
+
+        for usage in &log.usage {
+            // Price usage logged before costs were recorded with the current price table
+            let cost = usage
+                .cost
+                .or_else(|| pricing.cost(&usage.model, &usage.usage));
+            let row = UsageRow {
+                input_tokens: usage.usage.input_tokens.unwrap_or(0) as i64,
+                output_tokens: usage.usage.output_tokens.unwrap_or(0) as i64,
+                cost,
+            };
+            rows.entry((day.clone(), session.clone(), usage.model.clone()))
+                .or_default()
+                .add(&row);
+        }
+    }
+    rows
+}
+
+fn render(rows: &BTreeMap<UsageKey, UsageRow>) -> Result<String> {
+    let mut output = String::new();
+    if rows.is_empty() {
+        writeln!(output, "No usage recorded yet.")?;
+        return Ok(output);
+    }
+
+    let format_cost =
+        |cost: Option<f64>| cost.map_or_else(|| "-".to_string(), |c| format!("${:.4}", c));
+    let session_width = rows
+        .keys()
+        .map(|(_, session, _)| session.len())
+        .chain(std::iter::once("SESSION".len()))
+        .max()
+        .unwrap_or_default();
+    let model_width = rows
+        .keys()
+        .map(|(_, _, model)| model.len())
+        .chain(std::iter::once("MODEL".len()))
+        .max()
+        .unwrap_or_default();
+
+    writeln!(
+        output,
+        "{:<10}  {:<session_width$}  {:<model_width$}  {:>12}  {:>12}  {:>10}",
+        "DAY", "SESSION", "MODEL", "INPUT", "OUTPUT", "COST"
+    )?;
+
+    let mut total = UsageRow::default();
+    for ((day, session, model), row) in rows {
+        writeln!(
+            output,
+            "{:<10}  {:<session_width$}  {:<model_width$}  {:>12}  {:>12}  {:>10}",
+            day,
+            session,
+            model,
+            row.input_tokens,
+            row.output_tokens,
+            format_cost(row.cost)
+        )?;
+        total.add(row);
+    }
+
+    writeln!(
+        output,
+        "{:<10}  {:<session_width$}  {:<model_width$}  {:>12}  {:>12}  {:>10}",
+        "TOTAL",
+        "",
+        "",
+        total.input_tokens,
+        total.output_tokens,
+        format_cost(total.cost)
+    )?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use goose::providers::base::{ProviderUsage, Usage};
+
+    fn usage(model: &str, input: i32, output: i32, cost: Option<f64>) -> ProviderUsage {
+        let mut usage = ProviderUsage::new(
+            model.to_string(),
+            Usage::new(Some(input), Some(output), Some(input + output)),
+        );
+        usage.cost = cost;
+        usage
+    }
+
+    #[test]
+    fn test_aggregate_groups_by_day_session_and_model() {
+        let day = Utc.with_ymd_and_hms(2024, 12, 1, 12, 0, 0).unwrap();
+        let logs = vec![
+            SessionLog {
+                session_file: "/sessions/alpha.jsonl".to_string(),
+                usage: vec![
+                    usage("gpt-4o", 100, 10, Some(0.5)),
+                    usage("gpt-4o", 200, 20, Some(0.25)),
+                    usage("llama3.2", 50, 5, None),
+                ],
+                timestamp: Some(day),
+            },
+            // Logged before costs were recorded, so priced from the table
+            SessionLog {
+                session_file: "/sessions/beta.jsonl".to_string(),
+                usage: vec![usage("gpt-4o", 1_000_000, 0, None)],
+                timestamp: None,
+            },
+        ];
+
+        let rows = aggregate(&logs, &Pricing::default(), None);
+        let day = day.with_timezone(&Local).date_naive().to_string();
+        let key = |day: &str, session: &str, model: &str| {
+            (day.to_string(), session.to_string(), model.to_string())
+        };
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[&key(&day, "alpha", "gpt-4o")],
+            UsageRow {
+                input_tokens: 300,
+                output_tokens: 30,
+                cost: Some(0.75)
+            }
+        );
+        assert_eq!(rows[&key(&day, "alpha", "llama3.2")].cost, None);
+        assert_eq!(rows[&key("unknown", "beta", "gpt-4o")].cost, Some(2.5));
+
+        // Undated sessions are left out once a start day is given
+        let since = NaiveDate::from_ymd_opt(2024, 11, 1);
+        let rows = aggregate(&logs, &Pricing::default(), since);
+        assert_eq!(rows.len(), 2);
+        assert!(render(&rows).unwrap().contains("$0.7500"));
+    }
+}
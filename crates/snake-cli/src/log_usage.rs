@@ -1,26 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
 use goose::providers::base::ProviderUsage;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct SessionLog {
-    session_file: String,
-    usage: Vec<ProviderUsage>,
+pub struct SessionLog {
+    /// The session's name, or the path of its file in logs written before sessions had a store
+    pub session_file: String,
+    pub usage: Vec<ProviderUsage>,
+    /// When the session was closed, missing from logs written before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// The usage log, ~/.config/goose/logs/goose.log
+pub fn usage_log_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_dir| {
+        home_dir
+            .join(".config")
+            .join("goose")
+            .join("logs")
+            .join("goose.log")
+    })
+}
+
+/// Read every session from the usage log, skipping lines that can't be parsed
+pub fn read_usage_log(path: &Path) -> std::io::Result<Vec<SessionLog>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
 }
 
-pub fn log_usage(session_file: String, usage: Vec<ProviderUsage>) {
+pub fn log_usage(session_name: String, usage: Vec<ProviderUsage>) {
     let log = SessionLog {
-        session_file,
+        session_file: session_name,
         usage,
+        timestamp: Some(Utc::now()),
     };
 
     // Ensure log directory exists
-    if let Some(home_dir) = dirs::home_dir() {
-        let log_dir = home_dir.join(".config").join("goose").join("logs");
-        if let Err(e) = std::fs::create_dir_all(&log_dir) {
+    if let Some(log_file) = usage_log_path() {
+        let log_dir = log_file.parent().expect("the log file is in a directory");
+        if let Err(e) = std::fs::create_dir_all(log_dir) {
             eprintln!("Failed to create log directory: {}", e);
             return;
         }
 
-        let log_file = log_dir.join("goose.log");
         let serialized = match serde_json::to_string(&log) {
             Ok(s) => s,
             Err(e) => {
@@ -85,6 +113,7 @@ mod tests {
             assert_eq!(log.usage[0].usage.output_tokens, Some(20));
             assert_eq!(log.usage[0].usage.total_tokens, Some(30));
             assert_eq!(log.usage[0].model, "model");
+            assert!(log.timestamp.is_some());
 
             // Remove the log file after test
             std::fs::remove_file(&log_file).ok();
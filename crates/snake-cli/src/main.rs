@@ -6,15 +6,20 @@ mod log_usage;
 mod logging;
 mod prompt;
 mod session;
+mod transcript;
 
 use commands::agent_version::AgentCommand;
+use commands::config::{parse_flag, ConfigCommand};
 use commands::configure::handle_configure;
 use commands::mcp::run_server;
-use commands::session::build_session;
+use commands::secrets::{unlock_secrets, SecretsCommand};
+use commands::session::{build_session, SessionCommand};
+use commands::usage::UsageCommand;
 use commands::version::print_version;
 use console::style;
 use goose::config::Config;
 use logging::setup_logging;
+use prompt::json::OutputFormat;
 use std::io::{self, Read};
 
 #[cfg(test)]
@@ -26,6 +31,17 @@ struct Cli {
     #[arg(short = 'v', long = "version")]
     version: bool,
 
+    /// Override a config value for this command
+    #[arg(
+        long = "set",
+        value_name = "KEY=VALUE",
+        value_parser = parse_flag,
+        global = true,
+        help = "Override a config value for this command (e.g., 'GOOSE_MODEL=gpt-4o')",
+        long_help = "Override a config value for this command only, above the config files and environment variables. The value is read as JSON when it parses, otherwise as a string. Can be given more than once."
+    )]
+    set: Vec<(String, serde_json::Value)>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -36,13 +52,45 @@ enum Command {
     #[command(about = "Configure Goose settings")]
     Configure {},
 
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Manage where secrets are stored
+    #[command(subcommand)]
+    Secrets(SecretsCommand),
+
     /// Manage system prompts and behaviors
     #[command(about = "Run one of the mcp servers bundled with goose")]
-    Mcp { name: String },
+    Mcp {
+        /// Name of the MCP server to run
+        name: String,
+
+        /// Serve remote clients over SSE and WebSocket on this address instead of stdio
+        #[arg(
+            long,
+            value_name = "ADDR",
+            num_args = 0..=1,
+            default_missing_value = "127.0.0.1:3000",
+            long_help = "Serve remote clients over SSE and WebSocket on this address instead of stdio. A bare port listens on 127.0.0.1 only, as does --listen without an address, which uses port 3000."
+        )]
+        listen: Option<String>,
+
+        /// The bearer token remote clients must send, GOOSE_MCP_TOKEN by default
+        #[arg(long, value_name = "TOKEN", requires = "listen")]
+        token: Option<String>,
+    },
 
     /// Start or resume interactive chat sessions
-    #[command(about = "Start or resume interactive chat sessions", alias = "s")]
+    #[command(
+        about = "Start or resume interactive chat sessions",
+        alias = "s",
+        args_conflicts_with_subcommands = true
+    )]
     Session {
+        #[command(subcommand)]
+        command: Option<SessionCommand>,
+
         /// Name for the chat session
         #[arg(
             short,
@@ -79,6 +127,15 @@ enum Command {
             long_help = "Add a builtin extension that is bundled with goose by specifying its name"
         )]
         builtin: Option<String>,
+
+        /// Use a named profile
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Use a profile from the config (e.g., 'work')",
+            long_help = "Use the provider, model, temperature, agent version, extensions and secrets of a profile defined under 'profiles' in the config, instead of those set with goose configure."
+        )]
+        profile: Option<String>,
     },
 
     /// Execute commands from an instruction file
@@ -142,10 +199,32 @@ enum Command {
             long_help = "Add a builtin extension that is compiled into goose by specifying its name"
         )]
         builtin: Option<String>,
+
+        /// Use a named profile
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Use a profile from the config (e.g., 'work')",
+            long_help = "Use the provider, model, temperature, agent version, extensions and secrets of a profile defined under 'profiles' in the config, instead of those set with goose configure."
+        )]
+        profile: Option<String>,
+
+        /// How to report the run
+        #[arg(
+            long = "output-format",
+            value_enum,
+            default_value_t = OutputFormat::Text,
+            help = "How to report the run: text, json or stream-json",
+            long_help = "Report the run as text for people to read, or as JSON for scripts. json writes one object with the messages, usage and status once the run ends. stream-json writes a line for each message as it arrives, then the usage and the status. The exit code is 0 on success, 1 when the session could not be set up, 3 for provider errors, 4 when a tool call failed and 5 when a limit was reached."
+        )]
+        output_format: OutputFormat,
     },
 
     /// List available agent versions
     Agents(AgentCommand),
+
+    /// Report token usage and cost by day, session and model
+    Usage(UsageCommand),
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -164,22 +243,57 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    for (key, value) in cli.set {
+        Config::global().set_flag(&key, value);
+    }
+
     match cli.command {
         Some(Command::Configure {}) => {
+            unlock_secrets()?;
             let _ = handle_configure().await;
             return Ok(());
         }
-        Some(Command::Mcp { name }) => {
-            let _ = run_server(&name).await;
+        Some(Command::Config(cmd)) => {
+            cmd.run()?;
+            return Ok(());
+        }
+        Some(Command::Secrets(cmd)) => {
+            cmd.run()?;
+            return Ok(());
+        }
+        Some(Command::Mcp {
+            name,
+            listen,
+            token,
+        }) => {
+            run_server(&name, listen.as_deref(), token).await?;
         }
         Some(Command::Session {
+            command: Some(command),
+            ..
+        }) => {
+            command.run()?;
+            return Ok(());
+        }
+        Some(Command::Session {
+            command: None,
             name,
             resume,
             extension,
             builtin,
+            profile,
         }) => {
-            let mut session = build_session(name, resume, extension, builtin).await;
-            setup_logging(session.session_file().file_stem().and_then(|s| s.to_str()))?;
+            unlock_secrets()?;
+            let mut session = build_session(
+                name,
+                resume,
+                extension,
+                builtin,
+                profile,
+                OutputFormat::Text,
+            )
+            .await;
+            setup_logging(Some(session.name()))?;
 
             let _ = session.start().await;
             return Ok(());
@@ -191,6 +305,8 @@ async fn main() -> Result<()> {
             resume,
             extension,
             builtin,
+            profile,
+            output_format,
         }) => {
             // Validate that we have some input source
             if instructions.is_none() && input_text.is_none() {
@@ -210,14 +326,26 @@ async fn main() -> Result<()> {
                     .expect("Failed to read from stdin");
                 stdin
             };
-            let mut session = build_session(name, resume, extension, builtin).await;
-            let _ = session.headless_start(contents.clone()).await;
+            unlock_secrets()?;
+            let mut session =
+                build_session(name, resume, extension, builtin, profile, output_format).await;
+            let status = session
+                .headless_start(contents.clone())
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            if status.exit_code() != 0 {
+                std::process::exit(status.exit_code());
+            }
             return Ok(());
         }
         Some(Command::Agents(cmd)) => {
             cmd.run()?;
             return Ok(());
         }
+        Some(Command::Usage(cmd)) => {
+            cmd.run()?;
+            return Ok(());
+        }
         None => {
             Cli::command().print_help()?;
             println!();
@@ -1,16 +1,34 @@
 use anyhow::Result;
+use goose::agents::{ToolConfirmationRequest, ToolProgress};
 use goose::message::Message;
+use goose::providers::base::ProviderUsage;
 
+use crate::session::RunStatus;
+
+pub mod json;
 pub mod renderer;
 pub mod rustyline;
 pub mod thinking;
 
 pub trait Prompt {
     fn render(&mut self, message: Box<Message>);
+    /// Render a note from goose itself that isn't part of the conversation
+    fn render_notice(&mut self, text: &str) {
+        self.render(Box::new(Message::assistant().with_text(text)));
+    }
+    /// Render a fragment of assistant text as it is generated. The complete message is still
+    /// passed to `render` afterwards, so prompts which don't stream can ignore this.
+    fn render_delta(&mut self, _text: &str) {}
     fn get_input(&mut self) -> Result<Input>;
+    /// Ask the user whether a tool call the approval policy holds back may run
+    fn confirm_tool_call(&mut self, request: &ToolConfirmationRequest) -> Result<bool>;
+    /// Show the progress an extension reported for a running tool call
+    fn render_progress(&mut self, _progress: &ToolProgress) {}
     fn show_busy(&mut self);
     fn hide_busy(&self);
     fn close(&self);
+    /// Report how a headless run ended and the usage of the session
+    fn finish(&mut self, _session: &str, _usage: &[ProviderUsage], _status: &RunStatus) {}
     /// Load the user's message history into the prompt for command history navigation. First message is the oldest message.
     /// When history is supported by the prompt.
     fn load_user_message_history(&mut self, _messages: Vec<Message>) {}
@@ -27,9 +45,12 @@ pub struct Input {
 }
 
 pub enum InputType {
-    AskAgain, // Ask the user for input again. Control flow command.
-    Message,  // User sent a message
-    Exit,     // User wants to exit the session
+    AskAgain,       // Ask the user for input again. Control flow command.
+    Message,        // User sent a message
+    Exit,           // User wants to exit the session
+    ListExtensions, // User wants to see the extensions and their status
+    ListPrompts,    // User wants to see the prompts the extensions offer
+    Prompt,         // User wants to run an extension's prompt, the content is the command
 }
 
 pub enum Theme {
@@ -0,0 +1,158 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use goose::agents::ToolConfirmationRequest;
+use goose::message::Message;
+use goose::providers::base::ProviderUsage;
+use serde_json::{json, Value};
+
+use super::{Input, InputType, Prompt};
+use crate::session::RunStatus;
+
+/// How `goose run` reports what happened
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Rendered for people to read
+    #[default]
+    Text,
+    /// One JSON object with every message, once the run ends
+    Json,
+    /// A JSON object per line for each message as it arrives, then the usage and result
+    StreamJson,
+}
+
+/// Writes a headless run as JSON for scripts to read
+///
+/// Nothing here asks the user anything, so tool calls that need confirmation are declined.
+pub struct JsonPrompt<W: Write = io::Stdout> {
+    writer: W,
+    /// Whether messages are written as they arrive rather than with the result
+    stream: bool,
+    messages: Vec<Message>,
+}
+
+impl JsonPrompt {
+    pub fn new(stream: bool) -> Self {
+        Self::with_writer(io::stdout(), stream)
+    }
+}
+
+impl<W: Write> JsonPrompt<W> {
+    pub fn with_writer(writer: W, stream: bool) -> Self {
+        Self {
+            writer,
+            stream,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Write a value as one line
+    fn emit(&mut self, value: &Value) {
+        let written = serde_json::to_writer(&mut self.writer, value)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(self.writer))
+            .and_then(|_| self.writer.flush());
+        if let Err(e) = written {
+            eprintln!("Failed to write output: {}", e);
+        }
+    }
+}
+
+impl<W: Write> Prompt for JsonPrompt<W> {
+    fn render(&mut self, message: Box<Message>) {
+        if self.stream {
+            self.emit(&json!({"type": "message", "message": message}));
+        } else {
+            self.messages.push(*message);
+        }
+    }
+
+    // Notices are for people at a terminal, the result already says how the run went
+    fn render_notice(&mut self, _text: &str) {}
+
+    fn get_input(&mut self) -> Result<Input> {
+        Ok(Input {
+            input_type: InputType::Exit,
+            content: None,
+        })
+    }
+
+    fn confirm_tool_call(&mut self, _request: &ToolConfirmationRequest) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn show_busy(&mut self) {}
+
+    fn hide_busy(&self) {}
+
+    fn close(&self) {}
+
+    fn goose_ready(&self) {}
+
+    fn finish(&mut self, session: &str, usage: &[ProviderUsage], status: &RunStatus) {
+        let mut result = serde_json::to_value(status).unwrap_or_else(|_| json!({}));
+        result["type"] = json!("result");
+        result["session"] = json!(session);
+        result["exit_code"] = json!(status.exit_code());
+
+        if self.stream {
+            self.emit(&json!({"type": "usage", "usage": usage}));
+        } else {
+            result["messages"] = json!(std::mem::take(&mut self.messages));
+            result["usage"] = json!(usage);
+        }
+        self.emit(&result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::providers::base::Usage;
+
+    fn lines(output: &[u8]) -> Vec<Value> {
+        std::str::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_json_writes_a_line_per_event() {
+        let mut prompt = JsonPrompt::with_writer(Vec::new(), true);
+        prompt.render(Box::new(Message::assistant().with_text("Done")));
+        prompt.render_notice("Closing session");
+        let usage = vec![ProviderUsage::new(
+            "gpt-4o".to_string(),
+            Usage::new(Some(10), Some(5), Some(15)),
+        )];
+        prompt.finish("nightly", &usage, &RunStatus::Success);
+
+        let lines = lines(&prompt.writer);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "message");
+        assert_eq!(lines[0]["message"]["content"][0]["Text"]["text"], "Done");
+        assert_eq!(lines[1]["type"], "usage");
+        assert_eq!(lines[1]["usage"][0]["usage"]["total_tokens"], 15);
+        assert_eq!(
+            lines[2],
+            json!({"type": "result", "session": "nightly", "status": "success", "exit_code": 0})
+        );
+    }
+
+    #[test]
+    fn test_json_writes_one_result() {
+        let mut prompt = JsonPrompt::with_writer(Vec::new(), false);
+        prompt.render(Box::new(Message::assistant().with_text("Partial")));
+        let status = RunStatus::ProviderError("Rate limited".to_string());
+        prompt.finish("nightly", &[], &status);
+
+        let lines = lines(&prompt.writer);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["status"], "provider_error");
+        assert_eq!(lines[0]["error"], "Rate limited");
+        assert_eq!(lines[0]["exit_code"], 3);
+        assert_eq!(lines[0]["messages"].as_array().unwrap().len(), 1);
+    }
+}
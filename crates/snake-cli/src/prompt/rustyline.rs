@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::io::{self, Write};
 
 use super::{
     renderer::{
@@ -10,7 +11,8 @@ use super::{
 
 use anyhow::Result;
 use cliclack::spinner;
-use goose::message::Message;
+use goose::agents::{ToolConfirmationRequest, ToolProgress};
+use goose::message::{Message, MessageContent};
 use mcp_core::Role;
 use rustyline::{DefaultEditor, EventHandler, KeyCode, KeyEvent, Modifiers};
 
@@ -21,6 +23,8 @@ pub struct RustylinePrompt {
     theme: Theme,
     renderers: HashMap<String, Box<dyn ToolRenderer>>,
     editor: DefaultEditor,
+    // Whether the text of the message being generated has already been printed
+    text_streamed: bool,
 }
 
 impl RustylinePrompt {
@@ -61,15 +65,52 @@ impl RustylinePrompt {
                 .unwrap_or(Theme::Dark),
             renderers,
             editor,
+            text_streamed: false,
         }
     }
 }
 
 impl Prompt for RustylinePrompt {
     fn render(&mut self, message: Box<Message>) {
+        let mut message = *message;
+        if self.text_streamed {
+            // Only the tool calls are left to show, the text was printed as it arrived
+            message
+                .content
+                .retain(|content| !matches!(content, MessageContent::Text(_)));
+            println!();
+            self.text_streamed = false;
+        }
         render(&message, &self.theme, self.renderers.clone());
     }
 
+    fn render_delta(&mut self, text: &str) {
+        self.text_streamed = true;
+        print!("{}", text);
+        io::stdout().flush().expect("Failed to flush stdout");
+    }
+
+    fn confirm_tool_call(&mut self, request: &ToolConfirmationRequest) -> Result<bool> {
+        // The tool request itself was already rendered with the assistant message
+        let confirmed =
+            cliclack::confirm(format!("Allow goose to run {}?", request.tool_call.name))
+                .initial_value(true)
+                .interact()?;
+        Ok(confirmed)
+    }
+
+    fn render_progress(&mut self, progress: &ToolProgress) {
+        let amount = match progress.total {
+            Some(total) => format!("{}/{}", progress.progress, total),
+            None => progress.progress.to_string(),
+        };
+        let text = match &progress.message {
+            Some(message) => format!("{} ({})", message, amount),
+            None => format!("Working... ({})", amount),
+        };
+        self.spinner.set_message(text);
+    }
+
     fn show_busy(&mut self) {
         self.spinner = spinner();
         self.spinner
@@ -127,12 +168,33 @@ impl Prompt for RustylinePrompt {
                 input_type: InputType::AskAgain,
                 content: None,
             });
+        } else if message_text.eq_ignore_ascii_case("/extensions") {
+            Ok(Input {
+                input_type: InputType::ListExtensions,
+                content: None,
+            })
+        } else if message_text.eq_ignore_ascii_case("/prompts") {
+            Ok(Input {
+                input_type: InputType::ListPrompts,
+                content: None,
+            })
+        } else if let Some(command) = message_text
+            .strip_prefix("/prompt")
+            .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        {
+            Ok(Input {
+                input_type: InputType::Prompt,
+                content: Some(command.trim().to_string()),
+            })
         } else if message_text.eq_ignore_ascii_case("/?")
             || message_text.eq_ignore_ascii_case("/help")
         {
             println!("Commands:");
             println!("/exit - Exit the session");
             println!("/t - Toggle Light/Dark theme");
+            println!("/extensions - List the extensions and their status");
+            println!("/prompts - List the prompts the extensions offer");
+            println!("/prompt <extension>:<name> [arg=value ...] - Run an extension's prompt");
             println!("/? | /help - Display this help message");
             println!("Ctrl+C - Interrupt goose (resets the interaction to before the interrupted user request)");
             println!("Ctrl+j - Adds a newline");
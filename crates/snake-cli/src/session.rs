@@ -1,101 +1,59 @@
 use anyhow::Result;
 use core::panic;
 use futures::StreamExt;
-use std::fs::{self, File};
-use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::log_usage::log_usage;
 use crate::prompt::{InputType, Prompt};
-use goose::agents::Agent;
+use console::style;
+use goose::agents::{Agent, AgentEvent, ExtensionState, ExtensionStatus, LimitExceeded};
 use goose::message::{Message, MessageContent};
+use goose::providers::base::{ProviderDelta, ProviderUsage};
+use goose::providers::errors::ProviderError;
+use goose::session::SessionStore;
 use mcp_core::handler::ToolError;
+use mcp_core::prompt::Prompt as McpPrompt;
 use mcp_core::role::Role;
-
-// File management functions
-pub fn ensure_session_dir() -> Result<PathBuf> {
-    let home_dir = dirs::home_dir().ok_or(anyhow::anyhow!("Could not determine home directory"))?;
-    let config_dir = home_dir.join(".config").join("goose").join("sessions");
-
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)?;
-    }
-
-    Ok(config_dir)
-}
-
-pub fn get_most_recent_session() -> Result<PathBuf> {
-    let session_dir = ensure_session_dir()?;
-    let mut entries = fs::read_dir(&session_dir)?
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "jsonl"))
-        .collect::<Vec<_>>();
-
-    if entries.is_empty() {
-        return Err(anyhow::anyhow!("No session files found"));
-    }
-
-    // Sort by modification time, most recent first
-    entries.sort_by(|a, b| {
-        b.metadata()
-            .and_then(|m| m.modified())
-            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-            .cmp(
-                &a.metadata()
-                    .and_then(|m| m.modified())
-                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
-            )
-    });
-
-    Ok(entries[0].path())
-}
-
-pub fn readable_session_file(session_file: &PathBuf) -> Result<File> {
-    match fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(false)
-        .open(session_file)
-    {
-        Ok(file) => Ok(file),
-        Err(e) => Err(anyhow::anyhow!("Failed to open session file: {}", e)),
-    }
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The exit code when the session can't be set up, e.g. because an extension failed to start
+pub const SETUP_FAILED_EXIT_CODE: i32 = 1;
+
+/// How a reply from the agent ended, which a headless run exits with
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "snake_case")]
+pub enum RunStatus {
+    Success,
+    /// The provider failed, or the agent couldn't continue because of it
+    ProviderError(String),
+    /// The reply finished, but at least one tool call failed along the way
+    ToolError,
+    /// The agent stopped at one of its configured limits
+    LimitExceeded(String),
+    /// The user stopped the reply
+    Interrupted,
 }
 
-pub fn persist_messages(session_file: &PathBuf, messages: &[Message]) -> Result<()> {
-    let file = fs::File::create(session_file)?; // Create or truncate the file
-    persist_messages_internal(file, messages)
-}
-
-fn persist_messages_internal(session_file: File, messages: &[Message]) -> Result<()> {
-    let mut writer = std::io::BufWriter::new(session_file);
-
-    for message in messages {
-        serde_json::to_writer(&mut writer, &message)?;
-        writeln!(writer)?;
-    }
-
-    writer.flush()?;
-    Ok(())
-}
-
-pub fn deserialize_messages(file: File) -> Result<Vec<Message>> {
-    let reader = io::BufReader::new(file);
-    let mut messages = Vec::new();
-
-    for line in reader.lines() {
-        messages.push(serde_json::from_str::<Message>(&line?)?);
+impl RunStatus {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunStatus::Success => 0,
+            RunStatus::ProviderError(_) => 3,
+            RunStatus::ToolError => 4,
+            RunStatus::LimitExceeded(_) => 5,
+            RunStatus::Interrupted => 130,
+        }
     }
-
-    Ok(messages)
 }
 
 // Session management
 pub struct Session<'a> {
     agent: Box<dyn Agent>,
     prompt: Box<dyn Prompt + 'a>,
-    session_file: PathBuf,
+    store: Arc<dyn SessionStore>,
+    name: String,
     messages: Vec<Message>,
 }
 
@@ -104,28 +62,24 @@ impl<'a> Session<'a> {
     pub fn new(
         agent: Box<dyn Agent>,
         mut prompt: Box<dyn Prompt + 'a>,
-        session_file: PathBuf,
+        store: Arc<dyn SessionStore>,
+        name: String,
     ) -> Self {
-        let messages = match readable_session_file(&session_file) {
-            Ok(file) => deserialize_messages(file).unwrap_or_else(|e| {
-                eprintln!(
-                    "Failed to read messages from session file. Starting fresh.\n{}",
-                    e
-                );
-                Vec::<Message>::new()
-            }),
-            Err(e) => {
-                eprintln!("Failed to load session file. Starting fresh.\n{}", e);
-                Vec::<Message>::new()
-            }
-        };
+        let messages = store.messages(&name).unwrap_or_else(|e| {
+            eprintln!(
+                "Failed to load the session's messages. Starting fresh.\n{}",
+                e
+            );
+            Vec::<Message>::new()
+        });
 
         prompt.load_user_message_history(messages.clone());
 
         Session {
             agent,
             prompt,
-            session_file,
+            store,
+            name,
             messages,
         }
     }
@@ -139,11 +93,39 @@ impl<'a> Session<'a> {
                 InputType::Message => {
                     if let Some(content) = &input.content {
                         self.messages.push(Message::user().with_text(content));
-                        persist_messages(&self.session_file, &self.messages)?;
+                        self.save_messages()?;
                     }
                 }
                 InputType::Exit => break,
                 InputType::AskAgain => continue,
+                InputType::ListExtensions => {
+                    render_extensions(&self.agent.list_extensions().await);
+                    continue;
+                }
+                InputType::ListPrompts => {
+                    render_prompts(&self.agent.list_prompts().await);
+                    continue;
+                }
+                InputType::Prompt => {
+                    let command = input.content.unwrap_or_default();
+                    match self.expand_prompt(&command).await {
+                        Ok(messages) => {
+                            for message in messages {
+                                self.prompt.render(Box::new(message.clone()));
+                                self.messages.push(message);
+                            }
+                            self.save_messages()?;
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to run prompt: {}", e);
+                            continue;
+                        }
+                    }
+                    // The agent only replies once the prompt leaves the user with the last word
+                    if self.messages.last().map(|m| &m.role) != Some(&Role::User) {
+                        continue;
+                    }
+                }
             }
 
             self.prompt.show_busy();
@@ -157,46 +139,87 @@ impl<'a> Session<'a> {
     pub async fn headless_start(
         &mut self,
         initial_message: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<RunStatus, Box<dyn std::error::Error>> {
         self.messages
             .push(Message::user().with_text(initial_message.as_str()));
-        persist_messages(&self.session_file, &self.messages)?;
+        self.save_messages()?;
 
-        self.agent_process_messages().await;
+        let status = self.agent_process_messages().await;
 
-        self.close_session().await;
-        Ok(())
+        let usage = self.close_session().await;
+        self.prompt.finish(&self.name, &usage, &status);
+        Ok(status)
     }
 
-    async fn agent_process_messages(&mut self) {
+    async fn agent_process_messages(&mut self) -> RunStatus {
         let mut stream = match self.agent.reply(&self.messages).await {
             Ok(stream) => stream,
             Err(e) => {
                 eprintln!("Error starting reply stream: {}", e);
-                return;
+                return RunStatus::ProviderError(e.to_string());
             }
         };
+        // Whether deltas of the message being generated are being rendered
+        let mut streaming = false;
+        let mut tool_failed = false;
+        let mut provider_error = None;
         loop {
             tokio::select! {
                 response = stream.next() => {
                     match response {
-                        Some(Ok(message)) => {
+                        Some(Ok(AgentEvent::Message(message))) => {
+                            tool_failed |= message.content.iter().any(|content| {
+                                matches!(content, MessageContent::ToolResponse(response) if response.tool_result.is_err())
+                            });
+                            provider_error = provider_error_reply(&message);
                             self.messages.push(message.clone());
-                            persist_messages(&self.session_file, &self.messages).unwrap_or_else(|e| eprintln!("Failed to persist messages: {}", e));
-                            self.prompt.hide_busy();
+                            self.save_messages().unwrap_or_else(|e| eprintln!("Failed to persist messages: {}", e));
+                            if !streaming {
+                                self.prompt.hide_busy();
+                            }
+                            streaming = false;
                             self.prompt.render(Box::new(message.clone()));
                             self.prompt.show_busy();
                         }
+                        Some(Ok(AgentEvent::Delta(ProviderDelta::Text(text)))) => {
+                            if !streaming {
+                                self.prompt.hide_busy();
+                                streaming = true;
+                            }
+                            self.prompt.render_delta(&text);
+                        }
+                        Some(Ok(AgentEvent::Delta(_))) => {}
+                        Some(Ok(AgentEvent::Progress(progress))) => {
+                            self.prompt.render_progress(&progress);
+                        }
+                        Some(Ok(AgentEvent::ConfirmationRequired(request))) => {
+                            self.prompt.hide_busy();
+                            // Failing to ask, e.g. when the input is not a terminal, declines the call
+                            let confirmed = self.prompt.confirm_tool_call(&request).unwrap_or_else(|e| {
+                                eprintln!("Failed to confirm tool call: {}", e);
+                                false
+                            });
+                            self.agent.handle_confirmation(&request.id, confirmed).await;
+                            self.prompt.show_busy();
+                        }
+                        // The agent already explained which limit it reached
+                        Some(Err(e)) if e.is::<LimitExceeded>() => {
+                            return RunStatus::LimitExceeded(e.to_string());
+                        }
+                        // As is the provider error that ended the reply, and the conversation can go on
+                        Some(Err(e)) if e.is::<ProviderError>() => {
+                            return RunStatus::ProviderError(e.to_string());
+                        }
                         Some(Err(e)) => {
                             eprintln!("Error: {}", e);
                             drop(stream);
                             self.rewind_messages();
-                            self.prompt.render(raw_message(r#"
+                            self.prompt.render_notice(r#"
 The error above was an exception we were not able to handle.\n\n
 These errors are often related to connection or authentication\n
 We've removed the conversation up to the most recent user message
- - depending on the error you may be able to continue"#));
-                            break;
+ - depending on the error you may be able to continue"#);
+                            return RunStatus::ProviderError(e.to_string());
                         }
                         None => break,
                     }
@@ -207,10 +230,28 @@ We've removed the conversation up to the most recent user message
                     // goose::process_store::kill_processes();
                     drop(stream);
                     self.handle_interrupted_messages();
-                    break;
+                    return RunStatus::Interrupted;
                 }
             }
         }
+
+        if let Some(error) = provider_error {
+            RunStatus::ProviderError(error)
+        } else if tool_failed {
+            RunStatus::ToolError
+        } else {
+            RunStatus::Success
+        }
+    }
+
+    /// Render an extension's prompt from a `/prompt extension:name arg=value` command
+    async fn expand_prompt(&self, command: &str) -> Result<Vec<Message>> {
+        let (extension, name, arguments) = parse_prompt_command(command)?;
+        let result = self
+            .agent
+            .get_prompt(&extension, &name, Value::Object(arguments))
+            .await?;
+        Ok(result.messages.into_iter().map(Message::from).collect())
     }
 
     /// Rewind the messages to before the last user message (they have cancelled it).
@@ -299,7 +340,7 @@ We've removed the conversation up to the most recent user message
                             // A real users message
                             self.messages.pop();
                             let prompt_response = "We interrupted before the model replied and removed the last message.";
-                            self.prompt.render(raw_message(prompt_response));
+                            self.prompt.render_notice(prompt_response);
                         }
                         None => panic!("No content in last message"),
                     }
@@ -308,24 +349,209 @@ We've removed the conversation up to the most recent user message
         }
     }
 
-    async fn close_session(&mut self) {
-        self.prompt.render(raw_message(
-            format!(
-                "Closing session. Recorded to {}\n",
-                self.session_file.display()
-            )
-            .as_str(),
-        ));
+    fn save_messages(&self) -> Result<(), goose::session::SessionError> {
+        self.store.save_messages(&self.name, &self.messages)
+    }
+
+    /// Close the prompt and record the session's usage, returning it
+    async fn close_session(&mut self) -> Vec<ProviderUsage> {
+        self.prompt
+            .render_notice(format!("Closing session. Recorded as {}\n", self.name).as_str());
         self.prompt.close();
         let usage = self.agent.usage().await;
-        log_usage(self.session_file.to_string_lossy().to_string(), usage);
+        let recorded = self.store.metadata(&self.name).and_then(|mut metadata| {
+            metadata.usage.extend(usage.iter().cloned());
+            self.store.update(&metadata)
+        });
+        if let Err(e) = recorded {
+            eprintln!("Failed to record the session's usage: {}", e);
+        }
+        log_usage(self.name.clone(), usage.clone());
+        usage
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
     }
+}
 
-    pub fn session_file(&self) -> PathBuf {
-        self.session_file.clone()
+/// How the agents start the message explaining a provider error
+const PROVIDER_ERROR_PREFIX: &str = "Ran into this error: ";
+/// And the message they end with when the context stays too long after shortening it
+const CONTEXT_LENGTH_ERROR: &str = "Error: Context length exceeds limits";
+
+/// The provider error an agent ended its reply with, from the message it explained it in
+///
+/// Agents end the reply when the provider fails, rather than failing the stream, so the
+/// conversation can go on.
+fn provider_error_reply(message: &Message) -> Option<String> {
+    if message.role != Role::Assistant {
+        return None;
+    }
+    let text = message.as_concat_text();
+    if let Some(error) = text.strip_prefix(PROVIDER_ERROR_PREFIX) {
+        let error = error.split_once(".\n\n").map_or(error, |(error, _)| error);
+        return Some(error.to_string());
     }
+    text.starts_with(CONTEXT_LENGTH_ERROR)
+        .then(|| text.trim_start_matches("Error: ").to_string())
 }
 
 fn raw_message(content: &str) -> Box<Message> {
     Box::new(Message::assistant().with_text(content))
 }
+
+fn render_extensions(extensions: &[ExtensionStatus]) {
+    if extensions.is_empty() {
+        println!("No extensions are enabled");
+        return;
+    }
+
+    for extension in extensions {
+        let state = match extension.state {
+            ExtensionState::Ready => style(extension.state.to_string()).green(),
+            ExtensionState::Failed => style(extension.state.to_string()).red(),
+            ExtensionState::Starting | ExtensionState::Restarting => {
+                style(extension.state.to_string()).yellow()
+            }
+        };
+        if extension.restarts > 0 {
+            println!(
+                "{} {} (restarted {} times)",
+                extension.name, state, extension.restarts
+            );
+        } else {
+            println!("{} {}", extension.name, state);
+        }
+        if let Some(error) = &extension.last_error {
+            println!("  {}", style(error).dim());
+        }
+        for line in &extension.stderr_tail {
+            println!("  {} {}", style("|").dim(), style(line).dim());
+        }
+    }
+}
+
+fn render_prompts(prompts: &HashMap<String, Vec<McpPrompt>>) {
+    if prompts.is_empty() {
+        println!("No extension offers prompts");
+        return;
+    }
+
+    let mut extensions: Vec<_> = prompts.iter().collect();
+    extensions.sort_by_key(|(name, _)| name.as_str());
+    for (extension, prompts) in extensions {
+        for prompt in prompts {
+            let arguments: Vec<String> = prompt
+                .arguments
+                .iter()
+                .map(|arg| {
+                    if arg.required {
+                        format!("{}=<value>", arg.name)
+                    } else {
+                        format!("[{}=<value>]", arg.name)
+                    }
+                })
+                .collect();
+            println!(
+                "{} {}",
+                style(format!("{}:{}", extension, prompt.name)).cyan(),
+                arguments.join(" ")
+            );
+            if !prompt.description.is_empty() {
+                println!("  {}", style(&prompt.description).dim());
+            }
+        }
+    }
+}
+
+/// Split a `extension:name arg=value other="quoted value"` prompt command into the
+/// extension, the prompt name and its arguments
+fn parse_prompt_command(command: &str) -> Result<(String, String, Map<String, Value>)> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unterminated quote in prompt arguments");
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    let mut tokens = tokens.into_iter();
+    let prompt = tokens.next().unwrap_or_default();
+    let (extension, name) = prompt
+        .split_once(':')
+        .filter(|(extension, name)| !extension.is_empty() && !name.is_empty())
+        .ok_or_else(|| {
+            anyhow::anyhow!("Expected a prompt like extension:name, got '{}'", prompt)
+        })?;
+
+    let mut arguments = Map::new();
+    for token in tokens {
+        let (key, value) = token.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Expected arguments like name=value, got '{}'", token)
+        })?;
+        arguments.insert(key.to_string(), Value::String(value.to_string()));
+    }
+
+    Ok((extension.to_string(), name.to_string(), arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompt_command() {
+        let (extension, name, arguments) =
+            parse_prompt_command(r#"developer:review focus=tests note="keep it short""#).unwrap();
+        assert_eq!(extension, "developer");
+        assert_eq!(name, "review");
+        assert_eq!(arguments["focus"], "tests");
+        assert_eq!(arguments["note"], "keep it short");
+
+        let (_, _, arguments) = parse_prompt_command("developer:review").unwrap();
+        assert!(arguments.is_empty());
+
+        assert!(parse_prompt_command("review").is_err());
+        assert!(parse_prompt_command("developer:review focus").is_err());
+        assert!(parse_prompt_command(r#"developer:review note="open"#).is_err());
+    }
+
+    #[test]
+    fn test_provider_error_reply() {
+        let failed = Message::assistant().with_text(
+            "Ran into this error: Server error: 503.\n\nPlease retry if you think this is a transient or recoverable error.",
+        );
+        assert_eq!(
+            provider_error_reply(&failed).as_deref(),
+            Some("Server error: 503")
+        );
+
+        let too_long = Message::assistant().with_text(
+            "Error: Context length exceeds limits even after multiple attempts to truncate.",
+        );
+        assert!(provider_error_reply(&too_long).is_some());
+
+        assert_eq!(
+            provider_error_reply(&Message::assistant().with_text("Done")),
+            None
+        );
+        assert_eq!(
+            provider_error_reply(&Message::user().with_text("Ran into this error: none")),
+            None
+        );
+    }
+}
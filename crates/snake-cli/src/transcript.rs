@@ -0,0 +1,338 @@
+//! Transcripts of sessions to share outside of goose, as markdown, JSON or HTML
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use goose::message::{Message, MessageContent};
+use goose::session::SessionMetadata;
+use mcp_core::content::Content;
+use mcp_core::role::Role;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+    Html,
+}
+
+pub fn export(
+    format: ExportFormat,
+    metadata: &SessionMetadata,
+    messages: &[Message],
+) -> Result<String> {
+    match format {
+        ExportFormat::Markdown => to_markdown(metadata, messages),
+        ExportFormat::Json => to_json(metadata, messages),
+        ExportFormat::Html => to_html(metadata, messages),
+    }
+}
+
+/// One piece of a transcript, shared by the formats that lay it out
+enum Part<'a> {
+    /// The start of a turn by the user or goose
+    Turn(&'a Role),
+    Text(&'a str),
+    /// An image, shown by its type since transcripts don't embed the data
+    Image(&'a str),
+    ToolCall {
+        name: String,
+        arguments: String,
+    },
+    ToolResult {
+        output: String,
+        is_error: bool,
+    },
+}
+
+/// Flatten the messages into the parts of a transcript
+///
+/// Tool results arrive in user messages, but they are shown as part of goose's turn.
+fn parts(messages: &[Message]) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut last_role = None;
+    for message in messages {
+        let only_tool_results = message
+            .content
+            .iter()
+            .all(|content| matches!(content, MessageContent::ToolResponse(_)));
+        if !only_tool_results && last_role != Some(&message.role) {
+            parts.push(Part::Turn(&message.role));
+            last_role = Some(&message.role);
+        }
+
+        for content in &message.content {
+            match content {
+                MessageContent::Text(text) => parts.push(Part::Text(&text.text)),
+                MessageContent::Image(image) => parts.push(Part::Image(&image.mime_type)),
+                MessageContent::ToolRequest(request) => match &request.tool_call {
+                    Ok(call) => parts.push(Part::ToolCall {
+                        name: call.name.clone(),
+                        arguments: serde_json::to_string_pretty(&call.arguments)
+                            .unwrap_or_default(),
+                    }),
+                    Err(e) => parts.push(Part::ToolResult {
+                        output: e.to_string(),
+                        is_error: true,
+                    }),
+                },
+                MessageContent::ToolResponse(response) => match &response.tool_result {
+                    Ok(contents) => parts.push(Part::ToolResult {
+                        output: contents
+                            .iter()
+                            .filter(|content| {
+                                content
+                                    .audience()
+                                    .is_none_or(|audience| audience.contains(&Role::Assistant))
+                            })
+                            .map(content_text)
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        is_error: false,
+                    }),
+                    Err(e) => parts.push(Part::ToolResult {
+                        output: e.to_string(),
+                        is_error: true,
+                    }),
+                },
+            }
+        }
+    }
+    parts
+}
+
+fn content_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.text.clone(),
+        Content::Image(image) => format!("[image: {}]", image.mime_type),
+        Content::Resource(resource) => resource.get_text(),
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "Goose",
+    }
+}
+
+/// The details of a session shown at the top of a transcript
+fn details(metadata: &SessionMetadata) -> Vec<(&'static str, String)> {
+    let mut details = vec![("Started", metadata.created.to_rfc3339())];
+    if let Some(dir) = &metadata.working_dir {
+        details.push(("Directory", dir.display().to_string()));
+    }
+    if let Some(provider) = &metadata.provider {
+        details.push(("Provider", provider.clone()));
+    }
+    if let Some(model) = &metadata.model {
+        details.push(("Model", model.clone()));
+    }
+    details.push(("Messages", metadata.message_count.to_string()));
+    details.push(("Tokens", metadata.total_tokens().to_string()));
+    details
+}
+
+/// A code fence longer than any run of backticks in the text it wraps
+fn fence(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn to_markdown(metadata: &SessionMetadata, messages: &[Message]) -> Result<String> {
+    let mut output = String::new();
+    writeln!(output, "# Session {}\n", metadata.name)?;
+    for (label, value) in details(metadata) {
+        writeln!(output, "- **{}:** {}", label, value)?;
+    }
+
+    for part in parts(messages) {
+        match part {
+            Part::Turn(role) => write!(output, "\n## {}\n", role_name(role))?,
+            Part::Text(text) => write!(output, "\n{}\n", text.trim_end())?,
+            Part::Image(mime_type) => write!(output, "\n*[image: {}]*\n", mime_type)?,
+            Part::ToolCall { name, arguments } => {
+                let fence = fence(&arguments);
+                write!(
+                    output,
+                    "\n**Tool call** `{}`\n\n{}json\n{}\n{}\n",
+                    name, fence, arguments, fence
+                )?;
+            }
+            Part::ToolResult {
+                output: result,
+                is_error,
+            } => {
+                let fence = fence(&result);
+                let label = if is_error {
+                    "Tool error"
+                } else {
+                    "Tool result"
+                };
+                write!(
+                    output,
+                    "\n**{}**\n\n{}\n{}\n{}\n",
+                    label,
+                    fence,
+                    result.trim_end(),
+                    fence
+                )?;
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn to_json(metadata: &SessionMetadata, messages: &[Message]) -> Result<String> {
+    let transcript = serde_json::json!({
+        "metadata": metadata,
+        "messages": messages,
+    });
+    Ok(serde_json::to_string_pretty(&transcript)? + "\n")
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.25rem 1rem; color: #555; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+h2 { border-top: 1px solid #ddd; padding-top: 1rem; }
+.text { white-space: pre-wrap; }
+pre { background: #f6f8fa; padding: 0.75rem; overflow-x: auto; }
+.error pre { background: #fdecea; }";
+
+fn to_html(metadata: &SessionMetadata, messages: &[Message]) -> Result<String> {
+    let title = escape_html(&format!("Session {}", metadata.name));
+    let mut output = String::new();
+    writeln!(output, "<!DOCTYPE html>")?;
+    writeln!(output, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(output, "<title>{}</title>", title)?;
+    writeln!(output, "<style>\n{}\n</style>\n</head>\n<body>", HTML_STYLE)?;
+    writeln!(output, "<h1>{}</h1>\n<dl>", title)?;
+    for (label, value) in details(metadata) {
+        writeln!(output, "<dt>{}</dt><dd>{}</dd>", label, escape_html(&value))?;
+    }
+    writeln!(output, "</dl>")?;
+
+    for part in parts(messages) {
+        match part {
+            Part::Turn(role) => writeln!(output, "<h2>{}</h2>", role_name(role))?,
+            Part::Text(text) => writeln!(
+                output,
+                "<div class=\"text\">{}</div>",
+                escape_html(text.trim_end())
+            )?,
+            Part::Image(mime_type) => writeln!(
+                output,
+                "<p class=\"image\"><em>[image: {}]</em></p>",
+                escape_html(mime_type)
+            )?,
+            Part::ToolCall { name, arguments } => writeln!(
+                output,
+                "<div class=\"tool-call\"><p><strong>Tool call</strong> <code>{}</code></p><pre>{}</pre></div>",
+                escape_html(&name),
+                escape_html(&arguments)
+            )?,
+            Part::ToolResult { output: result, is_error } => {
+                let (class, label) = if is_error {
+                    ("tool-result error", "Tool error")
+                } else {
+                    ("tool-result", "Tool result")
+                };
+                writeln!(
+                    output,
+                    "<div class=\"{}\"><p><strong>{}</strong></p><pre>{}</pre></div>",
+                    class,
+                    label,
+                    escape_html(result.trim_end())
+                )?;
+            }
+        }
+    }
+    writeln!(output, "</body>\n</html>")?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    fn transcript() -> (SessionMetadata, Vec<Message>) {
+        let metadata = SessionMetadata::new("review").with_provider("openai", "gpt-4o");
+        let messages = vec![
+            Message::user()
+                .with_text("List the <src> files")
+                .with_image("aGVsbG8=", "image/png"),
+            Message::assistant()
+                .with_text("Listing them")
+                .with_tool_request(
+                    "1",
+                    Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+                ),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("main.rs\n```")])),
+            Message::assistant().with_text("There is one file"),
+        ];
+        (metadata, messages)
+    }
+
+    #[test]
+    fn test_markdown_transcript() {
+        let (metadata, messages) = transcript();
+        let markdown = export(ExportFormat::Markdown, &metadata, &messages).unwrap();
+
+        assert!(markdown.starts_with("# Session review\n"));
+        assert!(markdown.contains("- **Model:** gpt-4o"));
+        // The tool result belongs to goose's turn, so there is one heading per turn
+        assert_eq!(markdown.matches("## Goose").count(), 1);
+        assert_eq!(markdown.matches("## User").count(), 1);
+        assert!(markdown.contains("**Tool call** `developer__shell`"));
+        assert!(markdown.contains("\n*[image: image/png]*\n"));
+        assert_eq!(markdown.matches("**Tool result**").count(), 1);
+        // Backticks in the output don't close its fence
+        assert!(markdown.contains("````\nmain.rs\n```\n````"));
+    }
+
+    #[test]
+    fn test_html_transcript_is_escaped() {
+        let (metadata, messages) = transcript();
+        let html = export(ExportFormat::Html, &metadata, &messages).unwrap();
+
+        assert!(html.contains("List the &lt;src&gt; files"));
+        assert!(!html.contains("<src>"));
+        assert!(html.contains("<code>developer__shell</code>"));
+        assert!(html.contains("<p class=\"image\"><em>[image: image/png]</em></p>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_json_transcript_round_trips() {
+        let (metadata, messages) = transcript();
+        let json = export(ExportFormat::Json, &metadata, &messages).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["metadata"]["name"], "review");
+        let parsed: Vec<Message> = serde_json::from_value(value["messages"].clone()).unwrap();
+        assert_eq!(parsed, messages);
+    }
+}
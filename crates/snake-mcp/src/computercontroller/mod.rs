@@ -12,11 +12,11 @@ use mcp_core::{
     handler::{ResourceError, ToolError},
     protocol::ServerCapabilities,
     resource::Resource,
-    tool::Tool,
+    tool::{Tool, ToolAnnotations},
     Content,
 };
 use mcp_server::router::CapabilitiesBuilder;
-use mcp_server::Router;
+use mcp_server::{Progress, Router};
 
 /// An extension designed for non-developers to help them with common tasks like
 /// web scraping, data processing, and automation.
@@ -55,7 +55,10 @@ impl ComputerControllerRouter {
                     }
                 }
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let web_scrape_tool = Tool::new(
             "web_scrape",
@@ -84,7 +87,10 @@ impl ComputerControllerRouter {
                     }
                 }
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let computer_control_tool = Tool::new(
             "computer_control",
@@ -357,6 +363,12 @@ impl ComputerControllerRouter {
             .and_then(|v| v.as_str())
             .unwrap_or("text");
 
+        // Report the fetch and the save as two steps, if the client asked
+        let progress = Progress::current();
+        if let Some(progress) = &progress {
+            progress.report(0.0, Some(2.0), Some(format!("Fetching {}", url)));
+        }
+
         // Fetch the content
         let response = self
             .http_client
@@ -401,12 +413,24 @@ impl ComputerControllerRouter {
                 _ => unreachable!(), // Prevented by enum in tool definition
             };
 
+        if let Some(progress) = &progress {
+            progress.report(
+                1.0,
+                Some(2.0),
+                Some(format!("Saving {} bytes", content.len())),
+            );
+        }
+
         // Save to cache
         let cache_path = self.save_to_cache(&content, "web", extension).await?;
 
         // Register as a resource
         self.register_as_resource(&cache_path, save_as)?;
 
+        if let Some(progress) = &progress {
+            progress.report(2.0, Some(2.0), None);
+        }
+
         Ok(vec![Content::text(format!(
             "Content saved to: {}",
             cache_path.display()
@@ -11,6 +11,7 @@ use std::{
     path::{Path, PathBuf},
     pin::Pin,
 };
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use url::Url;
 
@@ -18,10 +19,10 @@ use mcp_core::{
     handler::{ResourceError, ToolError},
     protocol::ServerCapabilities,
     resource::Resource,
-    tool::Tool,
+    tool::{Tool, ToolAnnotations},
 };
 use mcp_server::router::CapabilitiesBuilder;
-use mcp_server::Router;
+use mcp_server::{Progress, Router};
 
 use mcp_core::content::Content;
 use mcp_core::role::Role;
@@ -125,7 +126,10 @@ impl DeveloperRouter {
                 "required": [],
                 "properties": {}
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let screen_capture_tool = Tool::new(
             "screen_capture",
@@ -153,7 +157,10 @@ impl DeveloperRouter {
                     }
                 }
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         // Get base instructions and working directory
         let cwd = std::env::current_dir().expect("should have a current working dir");
@@ -234,9 +241,9 @@ impl DeveloperRouter {
         let cmd_with_redirect = format!("{} 2>&1", command);
 
         // Execute the command
-        let child = Command::new("bash")
-            .stdout(Stdio::piped()) // These two pipes required to capture output later.
-            .stderr(Stdio::piped())
+        let mut child = Command::new("bash")
+            .stdout(Stdio::piped()) // Required to capture output as it is written.
+            .stderr(Stdio::null()) // Already redirected to stdout by the command.
             .stdin(Stdio::null())
             .kill_on_drop(true) // Critical so that the command is killed when the agent.reply stream is interrupted.
             .arg("-c")
@@ -244,29 +251,50 @@ impl DeveloperRouter {
             .spawn()
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
-        // Wait for the command to complete and get output
-        let output = child
-            .wait_with_output()
+        // Read the output line by line, reporting each line as progress if the client asked
+        let progress = Progress::current();
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut output = Vec::new();
+        let mut lines = 0;
+        loop {
+            let start = output.len();
+            let read = stdout
+                .read_until(b'\n', &mut output)
+                .await
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+            if read == 0 {
+                break;
+            }
+            if let Some(progress) = &progress {
+                lines += 1;
+                let line = String::from_utf8_lossy(&output[start..]);
+                progress.report(lines as f64, None, Some(line.trim_end().to_string()));
+            }
+        }
+
+        // Wait for the command to complete
+        child
+            .wait()
             .await
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
+        let output_str = String::from_utf8_lossy(&output);
 
-        // Check the character count of the output
+        // The agent fits the model's copy to its context, but the user's copy is only
+        // capped here, keeping the end of the output where errors usually are
         const MAX_CHAR_COUNT: usize = 400_000; // 409600 chars = 400KB
         let char_count = output_str.chars().count();
-        if char_count > MAX_CHAR_COUNT {
-            return Err(ToolError::ExecutionError(format!(
-                    "Shell output from command '{}' has too many characters ({}). Maximum character count is {}.",
-                    command,
-                    char_count,
-                    MAX_CHAR_COUNT
-                )));
-        }
+        let user_output = if char_count > MAX_CHAR_COUNT {
+            let omitted = char_count - MAX_CHAR_COUNT;
+            let tail: String = output_str.chars().skip(omitted).collect();
+            format!("[... {} earlier characters omitted ...]\n{}", omitted, tail)
+        } else {
+            output_str.to_string()
+        };
 
         Ok(vec![
-            Content::text(output_str.clone()).with_audience(vec![Role::Assistant]),
-            Content::text(output_str)
+            Content::text(output_str).with_audience(vec![Role::Assistant]),
+            Content::text(user_output)
                 .with_audience(vec![Role::User])
                 .with_priority(0.0),
         ])
@@ -8,7 +8,7 @@ use mcp_core::{
     handler::{ResourceError, ToolError},
     protocol::ServerCapabilities,
     resource::Resource,
-    tool::Tool,
+    tool::{Tool, ToolAnnotations},
 };
 use mcp_server::router::CapabilitiesBuilder;
 use mcp_server::Router;
@@ -151,7 +151,10 @@ impl GoogleDriveRouter {
               },
               "required": ["query"],
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let read_tool = Tool::new(
             "read".to_string(),
@@ -174,7 +177,10 @@ impl GoogleDriveRouter {
               },
               "required": ["uri"],
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let instructions = indoc::formatdoc! {r#"
             Google Drive MCP Server Instructions
@@ -14,7 +14,7 @@ use mcp_core::{
     handler::{ResourceError, ToolError},
     protocol::ServerCapabilities,
     resource::Resource,
-    tool::{Tool, ToolCall},
+    tool::{Tool, ToolAnnotations, ToolCall},
     Content,
 };
 use mcp_server::router::CapabilitiesBuilder;
@@ -63,7 +63,10 @@ impl MemoryRouter {
                 },
                 "required": ["category", "is_global"]
             }),
-        );
+        )
+        .with_annotations(ToolAnnotations {
+            parallel_hint: Some(true),
+        });
 
         let remove_memory_category = Tool::new(
             "remove_memory_category",
@@ -1,6 +1,7 @@
 use crate::configuration;
 use crate::state;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
@@ -12,11 +13,28 @@ pub async fn run() -> Result<()> {
     let settings = configuration::Settings::new()?;
 
     // load secret key from GOOSE_SERVER__SECRET_KEY environment variable
-    let secret_key =
-        std::env::var("GOOSE_SERVER__SECRET_KEY").unwrap_or_else(|_| "test".to_string());
+    let secret_key = match std::env::var("GOOSE_SERVER__SECRET_KEY") {
+        Ok(key) if !key.is_empty() => key,
+        _ => bail!("Set GOOSE_SERVER__SECRET_KEY to the key clients send in X-Secret-Key"),
+    };
 
-    // Create app state - agent will start as None
-    let state = state::AppState::new(secret_key.clone()).await?;
+    // Create app state - sessions are started through /agent
+    let idle_timeout = Duration::from_secs(settings.session_idle_timeout);
+    let state = state::AppState::new(secret_key, idle_timeout).await?;
+
+    // Stop the agents of sessions that are no longer used
+    let agents = state.agents.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(
+            idle_timeout.clamp(Duration::from_secs(1), Duration::from_secs(60)),
+        );
+        loop {
+            interval.tick().await;
+            for id in agents.evict_idle() {
+                info!("Evicted idle session {}", id);
+            }
+        }
+    });
 
     // Create router with CORS support
     let cors = CorsLayer::new()
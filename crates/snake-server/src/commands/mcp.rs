@@ -3,10 +3,10 @@ use goose_mcp::{
     ComputerControllerRouter, DeveloperRouter, GoogleDriveRouter, JetBrainsRouter, MemoryRouter,
 };
 use mcp_server::router::RouterService;
-use mcp_server::{BoundedService, ByteTransport, Server};
+use mcp_server::{http, BoundedService, ByteTransport, Server};
 use tokio::io::{stdin, stdout};
 
-pub async fn run(name: &str) -> Result<()> {
+pub async fn run(name: &str, listen: Option<&str>, token: Option<String>) -> Result<()> {
     // Initialize logging
     crate::logging::setup_logging(Some(&format!("mcp-{name}")))?;
 
@@ -23,8 +23,16 @@ pub async fn run(name: &str) -> Result<()> {
         _ => None,
     };
 
+    let router = router.unwrap_or_else(|| panic!("Unknown server requested {}", name));
+
+    if let Some(addr) = listen {
+        let token = http::resolve_token(token)?;
+        let (_, server) = http::serve_on(addr, router, token).await?;
+        return Ok(server.await?);
+    }
+
     // Create and run the server
-    let server = Server::new(router.unwrap_or_else(|| panic!("Unknown server requested {}", name)));
+    let server = Server::new(router);
     let transport = ByteTransport::new(stdin(), stdout());
 
     tracing::info!("Server initialized and ready to handle requests");
@@ -9,6 +9,9 @@ pub struct Settings {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// How long a session can go unused before its agent is stopped, in seconds
+    #[serde(default = "default_session_idle_timeout")]
+    pub session_idle_timeout: u64,
 }
 
 impl Settings {
@@ -28,6 +31,7 @@ impl Settings {
             // Server defaults
             .set_default("host", default_host())?
             .set_default("port", default_port())?
+            .set_default("session_idle_timeout", default_session_idle_timeout())?
             // Layer on the environment variables
             .add_source(
                 Environment::with_prefix("GOOSE")
@@ -74,6 +78,10 @@ fn default_port() -> u16 {
     3000
 }
 
+fn default_session_idle_timeout() -> u64 {
+    30 * 60
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,6 +91,7 @@ mod tests {
         let server_settings = Settings {
             host: "127.0.0.1".to_string(),
             port: 3000,
+            session_idle_timeout: 60,
         };
         let addr = server_settings.socket_addr();
         assert_eq!(addr.to_string(), "127.0.0.1:3000");
@@ -23,6 +23,18 @@ enum Commands {
     Mcp {
         /// Name of the MCP server type
         name: String,
+        /// Serve remote clients over SSE and WebSocket on this address instead of stdio,
+        /// 127.0.0.1 only for a bare port or no address
+        #[arg(
+            long,
+            value_name = "ADDR",
+            num_args = 0..=1,
+            default_missing_value = "127.0.0.1:3000"
+        )]
+        listen: Option<String>,
+        /// The bearer token remote clients must send, GOOSE_MCP_TOKEN by default
+        #[arg(long, value_name = "TOKEN", requires = "listen")]
+        token: Option<String>,
     },
 }
 
@@ -34,8 +46,12 @@ async fn main() -> anyhow::Result<()> {
         Commands::Agent => {
             commands::agent::run().await?;
         }
-        Commands::Mcp { name } => {
-            commands::mcp::run(name).await?;
+        Commands::Mcp {
+            name,
+            listen,
+            token,
+        } => {
+            commands::mcp::run(name, listen.as_deref(), token.clone()).await?;
         }
     }
 
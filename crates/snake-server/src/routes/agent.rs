@@ -1,15 +1,14 @@
 use crate::state::AppState;
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::{HeaderMap, StatusCode},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use goose::config::Config;
-use goose::{agents::AgentFactory, model::ModelConfig, providers};
+use goose::agents::AgentFactory;
+use goose::config::{Config, Profile};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::env;
 
 #[derive(Serialize)]
 struct VersionsResponse {
@@ -20,13 +19,18 @@ struct VersionsResponse {
 #[derive(Deserialize)]
 struct CreateAgentRequest {
     version: Option<String>,
-    provider: String,
+    /// Required unless a profile is given
+    provider: Option<String>,
     model: Option<String>,
+    /// A profile from the config to start from, which the other fields override
+    profile: Option<String>,
 }
 
 #[derive(Serialize)]
 struct CreateAgentResponse {
     version: String,
+    /// The id to send in `X-Session-Id` to use this agent
+    session_id: String,
 }
 
 #[derive(Deserialize)]
@@ -51,14 +55,19 @@ struct ProviderList {
     details: ProviderDetails,
 }
 
-async fn get_versions() -> Json<VersionsResponse> {
+async fn get_versions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<VersionsResponse>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
     let versions = AgentFactory::available_versions();
     let default_version = AgentFactory::default_version().to_string();
 
-    Json(VersionsResponse {
+    Ok(Json(VersionsResponse {
         available_versions: versions.iter().map(|v| v.to_string()).collect(),
         default_version,
-    })
+    }))
 }
 
 async fn create_agent(
@@ -66,46 +75,96 @@ async fn create_agent(
     headers: HeaderMap,
     Json(payload): Json<CreateAgentRequest>,
 ) -> Result<Json<CreateAgentResponse>, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
-    // Set the environment variable for the model if provided
-    if let Some(model) = &payload.model {
-        let env_var_key = format!("{}_MODEL", payload.provider.to_uppercase());
-        env::set_var(env_var_key.clone(), model);
-        println!("Set environment variable: {}={}", env_var_key, model);
+    let config = Config::global();
+    let mut profile = match &payload.profile {
+        Some(name) => Profile::from_config(config, Some(name)).map_err(|e| {
+            tracing::error!("Failed to load profile: {}", e);
+            StatusCode::NOT_FOUND
+        })?,
+        None => {
+            let provider = payload.provider.clone().ok_or(StatusCode::BAD_REQUEST)?;
+            let model = match &payload.model {
+                Some(model) => model.clone(),
+                None => config
+                    .get("GOOSE_MODEL")
+                    .map_err(|_| StatusCode::BAD_REQUEST)?,
+            };
+            Profile {
+                provider,
+                model,
+                ..Default::default()
+            }
+        }
+    };
+    if let Some(provider) = payload.provider {
+        profile.provider = provider;
+    }
+    if let Some(model) = payload.model {
+        profile.model = model;
+    }
+    if let Some(version) = payload.version {
+        profile.agent_version = Some(version);
     }
 
-    let config = Config::global();
-    let model = payload.model.unwrap_or_else(|| {
-        config
-            .get("GOOSE_MODEL")
-            .expect("Did not find a model on payload or in env")
-    });
-    let model_config = ModelConfig::new(model);
-    let provider =
-        providers::create(&payload.provider, model_config).expect("Failed to create provider");
-
-    let version = payload
-        .version
+    let version = profile
+        .agent_version
+        .clone()
         .unwrap_or_else(|| AgentFactory::default_version().to_string());
+    let mut new_agent = profile.create_agent().map_err(|e| {
+        tracing::error!("Failed to create agent: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Profiles that choose their extensions start with them, otherwise the client adds them
+    if profile.extensions.is_some() {
+        let extensions = profile
+            .extensions()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        for extension in extensions {
+            if let Err(e) = new_agent.add_extension(extension.clone()).await {
+                tracing::error!("Failed to start extension {}: {}", extension.name(), e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
 
-    let new_agent = AgentFactory::create(&version, provider).expect("Failed to create agent");
-
-    let mut agent = state.agent.lock().await;
-    *agent = Some(new_agent);
+    let session_id = state.agents.insert(new_agent);
+    tracing::info!(
+        "Started session {} with {} {}",
+        session_id,
+        profile.provider,
+        profile.model
+    );
+
+    Ok(Json(CreateAgentResponse {
+        version,
+        session_id,
+    }))
+}
 
-    Ok(Json(CreateAgentResponse { version }))
+/// Handler ending a session, stopping its agent and extensions
+async fn delete_agent(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    if state.agents.remove(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
 }
 
-async fn list_providers() -> Json<Vec<ProviderList>> {
+async fn list_providers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ProviderList>>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
     let contents = include_str!("providers_and_keys.json");
 
     let providers: HashMap<String, ProviderFile> =
@@ -125,7 +184,7 @@ async fn list_providers() -> Json<Vec<ProviderList>> {
         .collect();
 
     // Return the response as JSON.
-    Json(response)
+    Ok(Json(response))
 }
 
 pub fn routes(state: AppState) -> Router {
@@ -133,5 +192,6 @@ pub fn routes(state: AppState) -> Router {
         .route("/agent/versions", get(get_versions))
         .route("/agent/providers", get(list_providers))
         .route("/agent", post(create_agent))
+        .route("/agent/:id", delete(delete_agent))
         .with_state(state)
 }
@@ -1,13 +1,22 @@
 use std::collections::HashMap;
 
 use crate::state::AppState;
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
 use goose::{
-    agents::{extension::Envs, ExtensionConfig},
+    agents::{
+        extension::{Envs, ExtensionError},
+        ExtensionConfig, ExtensionStatus, ToolFilter,
+    },
     config::Config,
 };
 use http::{HeaderMap, StatusCode};
+use mcp_client::client::Error as ClientError;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 /// Enum representing the different types of extension configuration requests.
 #[derive(Deserialize)]
@@ -23,6 +32,29 @@ enum ExtensionConfigRequest {
         /// List of environment variable keys. The server will fetch their values from the keyring.
         #[serde(default)]
         env_keys: Vec<String>,
+        /// Headers sent with every request.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Which tools to offer and under which names.
+        #[serde(default)]
+        tools: ToolFilter,
+    },
+    /// Streamable HTTP extension.
+    #[serde(rename = "streamable_http")]
+    StreamableHttp {
+        /// The name to identify this extension
+        name: String,
+        /// The URI of the extension's endpoint.
+        uri: String,
+        /// Headers sent with every request.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Headers whose values the server fetches from the keyring, from header name to key.
+        #[serde(default)]
+        secret_headers: HashMap<String, String>,
+        /// Which tools to offer and under which names.
+        #[serde(default)]
+        tools: ToolFilter,
     },
     /// Standard I/O (stdio) extension.
     #[serde(rename = "stdio")]
@@ -37,12 +69,18 @@ enum ExtensionConfigRequest {
         /// List of environment variable keys. The server will fetch their values from the keyring.
         #[serde(default)]
         env_keys: Vec<String>,
+        /// Which tools to offer and under which names.
+        #[serde(default)]
+        tools: ToolFilter,
     },
     /// Built-in extension that is part of the goose binary.
     #[serde(rename = "builtin")]
     Builtin {
         /// The name of the built-in extension.
         name: String,
+        /// Which tools to offer and under which names.
+        #[serde(default)]
+        tools: ToolFilter,
     },
 }
 
@@ -63,14 +101,7 @@ async fn add_extension(
     Json(request): Json<ExtensionConfigRequest>,
 ) -> Result<Json<ExtensionResponse>, StatusCode> {
     // Verify the presence and validity of the secret key.
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
     // Load the configuration
     let config = Config::global();
@@ -84,6 +115,8 @@ async fn add_extension(
             name,
             uri,
             env_keys,
+            headers,
+            tools,
         } => {
             let mut env_map = HashMap::new();
             for key in env_keys {
@@ -111,6 +144,43 @@ async fn add_extension(
                 name,
                 uri,
                 envs: Envs::new(env_map),
+                headers,
+                tools,
+            }
+        }
+        ExtensionConfigRequest::StreamableHttp {
+            name,
+            uri,
+            mut headers,
+            secret_headers,
+            tools,
+        } => {
+            for (header, key) in secret_headers {
+                match config.get_secret(&key) {
+                    Ok(value) => {
+                        headers.insert(header, value);
+                    }
+                    Err(_) => {
+                        missing_keys.push(key);
+                    }
+                }
+            }
+
+            if !missing_keys.is_empty() {
+                return Ok(Json(ExtensionResponse {
+                    error: true,
+                    message: Some(format!(
+                        "Missing secrets for keys: {}",
+                        missing_keys.join(", ")
+                    )),
+                }));
+            }
+
+            ExtensionConfig::StreamableHttp {
+                name,
+                uri,
+                headers,
+                tools,
             }
         }
         ExtensionConfigRequest::Stdio {
@@ -118,6 +188,7 @@ async fn add_extension(
             cmd,
             args,
             env_keys,
+            tools,
         } => {
             let mut env_map = HashMap::new();
             for key in env_keys {
@@ -146,14 +217,15 @@ async fn add_extension(
                 cmd,
                 args,
                 envs: Envs::new(env_map),
+                tools,
             }
         }
-        ExtensionConfigRequest::Builtin { name } => ExtensionConfig::Builtin { name },
+        ExtensionConfigRequest::Builtin { name, tools } => ExtensionConfig::Builtin { name, tools },
     };
 
-    // Acquire a lock on the agent and attempt to add the extension.
-    let mut agent = state.agent.lock().await;
-    let agent = agent.as_mut().ok_or(StatusCode::PRECONDITION_REQUIRED)?;
+    // Acquire a lock on the session's agent and attempt to add the extension.
+    let (_, session) = state.agent_session(&headers)?;
+    let mut agent = session.agent.lock().await;
     let response = agent.add_extension(extension_config).await;
 
     // Respond with the result.
@@ -182,18 +254,11 @@ async fn remove_extension(
     Json(name): Json<String>,
 ) -> Result<Json<ExtensionResponse>, StatusCode> {
     // Verify the presence and validity of the secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+    state.verify_secret_key(&headers)?;
 
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
-    // Acquire a lock on the agent and attempt to remove the extension
-    let mut agent = state.agent.lock().await;
-    let agent = agent.as_mut().ok_or(StatusCode::PRECONDITION_REQUIRED)?;
+    // Acquire a lock on the session's agent and attempt to remove the extension
+    let (_, session) = state.agent_session(&headers)?;
+    let mut agent = session.agent.lock().await;
     agent.remove_extension(&name).await;
 
     Ok(Json(ExtensionResponse {
@@ -202,10 +267,90 @@ async fn remove_extension(
     }))
 }
 
+/// Handler listing the extensions with their health
+async fn list_extensions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ExtensionStatus>>, StatusCode> {
+    // Verify the presence and validity of the secret key
+    state.verify_secret_key(&headers)?;
+
+    let (_, session) = state.agent_session(&headers)?;
+    let agent = session.agent.lock().await;
+    Ok(Json(agent.list_extensions().await))
+}
+
+/// A JSON-RPC request to forward to an extension.
+#[derive(Deserialize)]
+struct PassthroughRequest {
+    /// The name of the extension to send the request to
+    extension: String,
+    /// The JSON-RPC method, e.g. `prompts/list`
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// The raw outcome of a forwarded request, holding either the `result` or the `error`
+/// the extension answered with.
+#[derive(Serialize)]
+struct PassthroughResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<PassthroughError>,
+}
+
+#[derive(Serialize)]
+struct PassthroughError {
+    code: i32,
+    message: String,
+}
+
+/// Handler forwarding a JSON-RPC request to an extension, so clients can call methods
+/// the agent doesn't use itself such as prompts and resource templates. Tool calls are
+/// refused, since they have to go through the agent's approval policy.
+async fn passthrough(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<PassthroughRequest>,
+) -> Result<Json<PassthroughResponse>, StatusCode> {
+    // Verify the presence and validity of the secret key
+    state.verify_secret_key(&headers)?;
+
+    let (_, session) = state.agent_session(&headers)?;
+    let agent = session.agent.lock().await;
+    let mut rpc_request = json!({ "method": request.method });
+    if let Some(params) = request.params {
+        rpc_request["params"] = params;
+    }
+
+    match agent.passthrough(&request.extension, rpc_request).await {
+        Ok(result) => Ok(Json(PassthroughResponse {
+            result: Some(result),
+            error: None,
+        })),
+        Err(ExtensionError::Client(ClientError::RpcError { code, message })) => {
+            Ok(Json(PassthroughResponse {
+                result: None,
+                error: Some(PassthroughError { code, message }),
+            }))
+        }
+        Err(ExtensionError::NotFound(_)) => Err(StatusCode::NOT_FOUND),
+        Err(ExtensionError::InvalidRequest(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            tracing::error!("Failed to forward request to extension: {}", e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
 /// Registers the extension management routes with the Axum router.
 pub fn routes(state: AppState) -> Router {
     Router::new()
+        .route("/extensions", get(list_extensions))
         .route("/extensions/add", post(add_extension))
         .route("/extensions/remove", post(remove_extension))
+        .route("/extensions/request", post(passthrough))
         .with_state(state)
 }
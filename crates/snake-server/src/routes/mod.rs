@@ -2,8 +2,10 @@
 pub mod agent;
 pub mod extension;
 pub mod health;
+pub mod prompt;
 pub mod reply;
 pub mod secrets;
+pub mod session;
 
 use axum::Router;
 
@@ -14,5 +16,7 @@ pub fn configure(state: crate::state::AppState) -> Router {
         .merge(reply::routes(state.clone()))
         .merge(agent::routes(state.clone()))
         .merge(extension::routes(state.clone()))
-        .merge(secrets::routes(state))
+        .merge(prompt::routes(state.clone()))
+        .merge(secrets::routes(state.clone()))
+        .merge(session::routes(state))
 }
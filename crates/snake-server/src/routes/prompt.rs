@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use goose::{agents::extension::ExtensionError, message::Message};
+use http::{HeaderMap, StatusCode};
+use mcp_client::client::Error as ClientError;
+use mcp_core::prompt::Prompt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Request to render one of an extension's prompts.
+#[derive(Deserialize)]
+struct GetPromptRequest {
+    /// The name of the extension offering the prompt
+    extension: String,
+    /// The name of the prompt
+    name: String,
+    #[serde(default)]
+    arguments: Map<String, Value>,
+}
+
+/// A rendered prompt, with its messages ready to add to the conversation.
+#[derive(Serialize)]
+struct GetPromptResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    messages: Vec<Message>,
+}
+
+/// Handler listing the prompts of every extension, keyed by extension name
+async fn list_prompts(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<HashMap<String, Vec<Prompt>>>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    let (_, session) = state.agent_session(&headers)?;
+    let agent = session.agent.lock().await;
+    Ok(Json(agent.list_prompts().await))
+}
+
+/// Handler rendering a prompt with the given arguments
+async fn get_prompt(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<GetPromptRequest>,
+) -> Result<Json<GetPromptResponse>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    let (_, session) = state.agent_session(&headers)?;
+    let agent = session.agent.lock().await;
+    let result = agent
+        .get_prompt(
+            &request.extension,
+            &request.name,
+            Value::Object(request.arguments),
+        )
+        .await
+        .map_err(|e| match e {
+            ExtensionError::NotFound(_) => StatusCode::NOT_FOUND,
+            // The extension rejected the prompt name or its arguments
+            ExtensionError::Client(ClientError::RpcError { .. }) => StatusCode::BAD_REQUEST,
+            e => {
+                tracing::error!("Failed to get prompt: {}", e);
+                StatusCode::BAD_GATEWAY
+            }
+        })?;
+
+    Ok(Json(GetPromptResponse {
+        description: result.description,
+        messages: result.messages.into_iter().map(Message::from).collect(),
+    }))
+}
+
+/// Registers the prompt routes with the Axum router.
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/prompts", get(list_prompts))
+        .route("/prompts/get", post(get_prompt))
+        .with_state(state)
+}
@@ -8,10 +8,13 @@ use axum::{
 };
 use bytes::Bytes;
 use futures::{stream::StreamExt, Stream};
+use goose::agents::{AgentEvent, LimitExceeded, ToolProgress};
 use goose::message::{Message, MessageContent};
+use goose::providers::base::{ProviderDelta, ProviderUsage};
+use goose::session::{SessionError, SessionMetadata};
 
-use mcp_core::{content::Content, role::Role};
-use serde::Deserialize;
+use mcp_core::{content::Content, role::Role, tool::ToolCall};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{
     convert::Infallible,
@@ -27,6 +30,12 @@ use tokio_stream::wrappers::ReceiverStream;
 #[derive(Debug, Deserialize)]
 struct ChatRequest {
     messages: Vec<IncomingMessage>,
+    /// The session to continue, in which case the messages are only the new ones
+    ///
+    /// Replies to an agent session named in `X-Session-Id` continue that session unless
+    /// this names another one.
+    #[serde(default, rename = "sessionId")]
+    session_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -160,6 +169,29 @@ impl ProtocolFormatter {
         format!("a:{}\n", response)
     }
 
+    fn format_confirmation_request(id: &str, tool_call: &ToolCall) -> String {
+        // Confirmation requests are sent as data parts, which start with "2:"
+        let request = json!([{
+            "type": "confirmationRequired",
+            "toolCallId": id,
+            "toolName": tool_call.name,
+            "args": tool_call.arguments
+        }]);
+        format!("2:{}\n", request)
+    }
+
+    fn format_progress(progress: &ToolProgress) -> String {
+        // Progress is sent as data parts too, for the tool call it belongs to
+        let progress = json!([{
+            "type": "toolProgress",
+            "toolCallId": progress.id,
+            "progress": progress.progress,
+            "total": progress.total,
+            "message": progress.message
+        }]);
+        format!("2:{}\n", progress)
+    }
+
     fn format_error(error: &str) -> String {
         // Error messages start with "3:" in the new protocol.
         let encoded_error = serde_json::to_string(error).unwrap_or_else(|_| String::new());
@@ -181,6 +213,7 @@ impl ProtocolFormatter {
 
 async fn stream_message(
     message: Message,
+    text_streamed: bool,
     tx: &mpsc::Sender<String>,
 ) -> Result<(), mpsc::error::SendError<String>> {
     match message.role {
@@ -240,6 +273,10 @@ async fn stream_message(
                             }
                         }
                     }
+                    MessageContent::Text(_) if text_streamed => {
+                        // The text was already sent as it was generated
+                        continue;
+                    }
                     MessageContent::Text(text) => {
                         for line in text.text.lines() {
                             let modified_line = format!("{}\n", line);
@@ -267,15 +304,7 @@ async fn handler(
     headers: HeaderMap,
     Json(request): Json<ChatRequest>,
 ) -> Result<SseResponse, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
     // Check protocol header (optional in our case)
     if let Some(protocol) = headers.get("x-protocol") {
@@ -284,30 +313,36 @@ async fn handler(
         }
     }
 
+    let named_session = headers.contains_key("X-Session-Id");
+    let (agent_session_id, agent_session) = state.agent_session(&headers)?;
+    let session_id = request
+        .session_id
+        .or_else(|| named_session.then_some(agent_session_id));
+
     // Create channel for streaming
     let (tx, rx) = mpsc::channel(100);
     let stream = ReceiverStream::new(rx);
 
-    // Convert incoming messages
-    let messages = convert_messages(request.messages);
-
-    // Get a lock on the shared agent
-    let agent = state.agent.clone();
+    // A session's earlier messages are kept by the server, so they come first. The session
+    // stays locked until the reply is saved, so replies to it run one after another.
+    let (mut messages, session) = match session_id {
+        Some(id) => {
+            let guard = state.session_locks.lock(&id).await;
+            let messages = session_messages(&state, &id).await?;
+            (messages, Some((state.clone(), id, guard)))
+        }
+        None => (Vec::new(), None),
+    };
+    messages.extend(convert_messages(request.messages));
 
     // Spawn task to handle streaming
     tokio::spawn(async move {
-        let agent = agent.lock().await;
-        let agent = match agent.as_ref() {
-            Some(agent) => agent,
-            None => {
-                let _ = tx
-                    .send(ProtocolFormatter::format_error("No agent configured"))
-                    .await;
-                let _ = tx.send(ProtocolFormatter::format_finish("error")).await;
-                return;
-            }
-        };
+        // Only this session's requests wait for its agent
+        let agent = agent_session.agent.lock().await;
+        let confirmations = &agent_session.confirmations;
 
+        let mut messages = messages;
+        let usage_before = agent.usage().await;
         let mut stream = match agent.reply(&messages).await {
             Ok(stream) => stream,
             Err(e) => {
@@ -320,16 +355,55 @@ async fn handler(
             }
         };
 
+        // Whether the text of the message being generated has been sent as deltas
+        let mut text_streamed = false;
         loop {
             tokio::select! {
                 response = timeout(Duration::from_millis(500), stream.next()) => {
                     match response {
-                        Ok(Some(Ok(message))) => {
-                            if let Err(e) = stream_message(message, &tx).await {
+                        Ok(Some(Ok(AgentEvent::Message(message)))) => {
+                            messages.push(message.clone());
+                            if let Err(e) = stream_message(message, text_streamed, &tx).await {
                                 tracing::error!("Error sending message through channel: {}", e);
                                 let _ = tx.send(ProtocolFormatter::format_error(&e.to_string())).await;
                                 break;
                             }
+                            text_streamed = false;
+                        }
+                        Ok(Some(Ok(AgentEvent::Delta(ProviderDelta::Text(text))))) => {
+                            text_streamed = true;
+                            if let Err(e) = tx.send(ProtocolFormatter::format_text(&text)).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::Delta(_)))) => {
+                            // Tool calls are sent once they are complete
+                            continue;
+                        }
+                        Ok(Some(Ok(AgentEvent::Progress(progress)))) => {
+                            if let Err(e) = tx.send(ProtocolFormatter::format_progress(&progress)).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::ConfirmationRequired(request)))) => {
+                            // Register before asking so an immediate answer isn't lost
+                            let mut answer = confirmations.register(&request.id);
+                            if let Err(e) = tx.send(ProtocolFormatter::format_confirmation_request(&request.id, &request.tool_call)).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+
+                            // Wait for the client to answer through /confirm, declining if it disconnects
+                            let confirmed = loop {
+                                match timeout(Duration::from_millis(500), &mut answer).await {
+                                    Ok(confirmed) => break confirmed.unwrap_or(false),
+                                    Err(_) if tx.is_closed() => break false,
+                                    Err(_) => continue,
+                                }
+                            };
+                            agent.handle_confirmation(&request.id, confirmed).await;
                         }
                         Ok(Some(Err(e))) => {
                             tracing::error!("Error processing message: {}", e);
@@ -353,6 +427,15 @@ async fn handler(
             }
         }
 
+        drop(stream);
+        agent_session.touch();
+        if let Some((state, id, _guard)) = session {
+            let usage = usage_since(&usage_before, agent.usage().await);
+            if let Err(e) = record_session(&state, &id, messages.clone(), usage).await {
+                tracing::error!("Failed to save session {}: {}", id, e);
+            }
+        }
+
         // Send finish message
         let _ = tx.send(ProtocolFormatter::format_finish("stop")).await;
     });
@@ -360,6 +443,74 @@ async fn handler(
     Ok(SseResponse::new(stream))
 }
 
+/// The messages of a session, starting it if it is new
+async fn session_messages(state: &AppState, id: &str) -> Result<Vec<Message>, StatusCode> {
+    let name = id.to_string();
+    let loaded = state
+        .with_sessions(move |store| match store.messages(&name) {
+            Err(SessionError::NotFound(_)) => {
+                store.create(&SessionMetadata::new(&name))?;
+                Ok(Vec::new())
+            }
+            loaded => loaded,
+        })
+        .await;
+    match loaded {
+        Ok(messages) => Ok(messages),
+        Err(SessionError::InvalidName(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            tracing::error!("Failed to load session {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// The usage added since an earlier total, as the agent totals it by provider and model
+fn usage_since(before: &[ProviderUsage], after: Vec<ProviderUsage>) -> Vec<ProviderUsage> {
+    let sub = |a: Option<i32>, b: Option<i32>| a.map(|a| a - b.unwrap_or(0));
+    after
+        .into_iter()
+        .filter_map(|mut usage| {
+            let Some(earlier) = before
+                .iter()
+                .find(|e| e.provider == usage.provider && e.model == usage.model)
+            else {
+                return Some(usage);
+            };
+            usage.usage.input_tokens = sub(usage.usage.input_tokens, earlier.usage.input_tokens);
+            usage.usage.output_tokens = sub(usage.usage.output_tokens, earlier.usage.output_tokens);
+            usage.usage.total_tokens = sub(usage.usage.total_tokens, earlier.usage.total_tokens);
+            usage.cost = usage.cost.map(|cost| cost - earlier.cost.unwrap_or(0.0));
+            // Models that weren't used in the reply are left out
+            (usage.usage.total_tokens != Some(0)).then_some(usage)
+        })
+        .collect()
+}
+
+/// Save a session's messages once a reply is done, along with the usage of the reply
+///
+/// The caller holds the session's lock, from loading its messages until they are saved.
+async fn record_session(
+    state: &AppState,
+    id: &str,
+    messages: Vec<Message>,
+    usage: Vec<ProviderUsage>,
+) -> Result<(), SessionError> {
+    let id = id.to_string();
+    state
+        .with_sessions(move |store| {
+            store.save_messages(&id, &messages)?;
+            let mut metadata = store.metadata(&id)?;
+            if let Some(last) = usage.last() {
+                metadata.provider = last.provider.clone().or(metadata.provider);
+                metadata.model = Some(last.model.clone());
+            }
+            metadata.usage.extend(usage);
+            store.update(&metadata)
+        })
+        .await
+}
+
 #[derive(Debug, Deserialize, serde::Serialize)]
 struct AskRequest {
     prompt: String,
@@ -376,19 +527,10 @@ async fn ask_handler(
     headers: HeaderMap,
     Json(request): Json<AskRequest>,
 ) -> Result<Json<AskResponse>, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
-    let agent = state.agent.clone();
-    let agent = agent.lock().await;
-    let agent = agent.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let (_, session) = state.agent_session(&headers)?;
+    let agent = session.agent.lock().await;
 
     // Create a single message for the prompt
     let messages = vec![Message::user().with_text(request.prompt)];
@@ -405,7 +547,7 @@ async fn ask_handler(
 
     while let Some(response) = stream.next().await {
         match response {
-            Ok(message) => {
+            Ok(AgentEvent::Message(message)) => {
                 if message.role == Role::Assistant {
                     for content in message.content {
                         if let MessageContent::Text(text) = content {
@@ -415,6 +557,13 @@ async fn ask_handler(
                     }
                 }
             }
+            Ok(AgentEvent::Delta(_) | AgentEvent::Progress(_)) => continue,
+            Ok(AgentEvent::ConfirmationRequired(request)) => {
+                // There is no one to ask, so only calls the policy allows outright can run
+                agent.handle_confirmation(&request.id, false).await;
+            }
+            // The agent's last message explains which limit it reached
+            Err(e) if e.is::<LimitExceeded>() => break,
             Err(e) => {
                 tracing::error!("Error processing as_ai message: {}", e);
                 return Err(StatusCode::INTERNAL_SERVER_ERROR);
@@ -427,17 +576,44 @@ async fn ask_handler(
     }))
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct ConfirmRequest {
+    id: String,
+    confirmed: bool,
+}
+
+// answer a tool call confirmation requested by a running /reply stream
+async fn confirm_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ConfirmRequest>,
+) -> Result<StatusCode, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    let (_, session) = state.agent_session(&headers)?;
+    if session
+        .confirmations
+        .confirm(&request.id, request.confirmed)
+    {
+        Ok(StatusCode::OK)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
 // Configure routes for this module
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/reply", post(handler))
         .route("/ask", post(ask_handler))
+        .route("/confirm", post(confirm_handler))
         .with_state(state)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use goose::session::{SessionStore, SqliteSessionStore};
     use goose::{
         agents::AgentFactory,
         model::ModelConfig,
@@ -558,9 +734,10 @@ mod tests {
 
     mod integration_tests {
         use super::*;
+        use crate::state::AgentSessions;
         use axum::{body::Body, http::Request};
+        use goose::agents::approval::{ToolApprovalMode, ToolApprovalPolicy};
         use std::sync::Arc;
-        use tokio::sync::Mutex;
         use tower::ServiceExt;
 
         // This test requires tokio runtime
@@ -573,9 +750,12 @@ mod tests {
             });
             let agent = AgentFactory::create("reference", mock_provider).unwrap();
             let state = AppState {
-                agent: Arc::new(Mutex::new(Some(agent))),
+                agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
                 secret_key: "test-secret".to_string(),
+                sessions: Arc::new(SqliteSessionStore::open_in_memory().unwrap()),
+                session_locks: Default::default(),
             };
+            state.agents.insert(agent);
 
             // Build router
             let app = routes(state);
@@ -600,5 +780,298 @@ mod tests {
             // Assert response status
             assert_eq!(response.status(), StatusCode::OK);
         }
+
+        #[tokio::test]
+        async fn test_sessions_do_not_block_each_other() {
+            let agent = |text: &str| {
+                let provider = Box::new(MockProvider {
+                    model_config: ModelConfig::new(text.to_string()),
+                });
+                AgentFactory::create("reference", provider).unwrap()
+            };
+            let state = AppState {
+                agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
+                secret_key: "test-secret".to_string(),
+                sessions: Arc::new(SqliteSessionStore::open_in_memory().unwrap()),
+                session_locks: Default::default(),
+            };
+            let busy = state.agents.insert(agent("busy"));
+            let idle = state.agents.insert(agent("idle"));
+            let app = routes(state.clone());
+
+            let ask = |session: &str| {
+                Request::builder()
+                    .uri("/ask")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .header("x-session-id", session)
+                    .body(Body::from(json!({"prompt": "test prompt"}).to_string()))
+                    .unwrap()
+            };
+
+            // A session in the middle of a request doesn't hold up the others
+            let session = state.agents.get(&busy).unwrap();
+            let _guard = session.agent.lock().await;
+            let response = timeout(Duration::from_secs(5), app.clone().oneshot(ask(&idle)))
+                .await
+                .expect("the idle session answers while the busy one is locked")
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = app.clone().oneshot(ask("unknown")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            // With several sessions, a request has to name the one it's for
+            let unnamed = Request::builder()
+                .uri("/ask")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from(json!({"prompt": "test prompt"}).to_string()))
+                .unwrap();
+            let response = app.oneshot(unnamed).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+        }
+
+        // Mock Provider which streams its reply in two text deltas
+        struct StreamingMockProvider {
+            model_config: ModelConfig,
+        }
+
+        #[async_trait::async_trait]
+        impl Provider for StreamingMockProvider {
+            fn metadata() -> goose::providers::base::ProviderMetadata {
+                goose::providers::base::ProviderMetadata::empty()
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                self.model_config.clone()
+            }
+
+            async fn complete(
+                &self,
+                _system: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+                Ok((
+                    Message::assistant().with_text("Hello world"),
+                    ProviderUsage::new("mock".to_string(), Usage::new(Some(10), Some(2), Some(12))),
+                ))
+            }
+
+            async fn stream(
+                &self,
+                system: &str,
+                messages: &[Message],
+                tools: &[Tool],
+            ) -> anyhow::Result<goose::providers::base::ProviderStream, ProviderError> {
+                // Stream the text of the complete reply in pieces, then finish with it
+                let (message, usage) = self.complete(system, messages, tools).await?;
+                Ok(Box::pin(futures::stream::iter(vec![
+                    Ok(ProviderDelta::Text("Hello ".to_string())),
+                    Ok(ProviderDelta::Text("world".to_string())),
+                    Ok(ProviderDelta::Finish { message, usage }),
+                ])))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_reply_streams_text_deltas() {
+            let mock_provider = Box::new(StreamingMockProvider {
+                model_config: ModelConfig::new("test-model".to_string()),
+            });
+            let agent = AgentFactory::create("truncate", mock_provider).unwrap();
+            let state = AppState {
+                agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
+                secret_key: "test-secret".to_string(),
+                sessions: Arc::new(SqliteSessionStore::open_in_memory().unwrap()),
+                session_locks: Default::default(),
+            };
+            state.agents.insert(agent);
+
+            let app = routes(state);
+
+            let request = Request::builder()
+                .uri("/reply")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from(
+                    json!({"messages": [{"role": "user", "content": "hi"}]}).to_string(),
+                ))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8(body.to_vec()).unwrap();
+
+            // Each delta is its own chunk and the complete message isn't sent again
+            assert!(body.starts_with("0:\"Hello \"\n0:\"world\"\nd:"));
+        }
+
+        #[tokio::test]
+        async fn test_reply_continues_session() {
+            let mock_provider = Box::new(StreamingMockProvider {
+                model_config: ModelConfig::new("test-model".to_string()),
+            });
+            let agent = AgentFactory::create("truncate", mock_provider).unwrap();
+            let sessions = Arc::new(SqliteSessionStore::open_in_memory().unwrap());
+            let state = AppState {
+                agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
+                secret_key: "test-secret".to_string(),
+                sessions: sessions.clone(),
+                session_locks: Default::default(),
+            };
+            state.agents.insert(agent);
+
+            let app = routes(state);
+
+            // Each request sends only its new message
+            for content in ["hi", "again"] {
+                let request = Request::builder()
+                    .uri("/reply")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(
+                        json!({
+                            "sessionId": "chat",
+                            "messages": [{"role": "user", "content": content}]
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap();
+                let response = app.clone().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+            }
+
+            let messages = sessions.messages("chat").unwrap();
+            let texts: Vec<_> = messages.iter().map(|m| m.as_concat_text()).collect();
+            assert_eq!(texts, vec!["hi", "Hello world", "again", "Hello world"]);
+            let metadata = sessions.metadata("chat").unwrap();
+            assert_eq!(metadata.message_count, 4);
+            assert_eq!(metadata.usage.len(), 2);
+            assert_eq!(metadata.total_tokens(), 24);
+            assert_eq!(metadata.model.as_deref(), Some("mock"));
+        }
+
+        // Mock Provider which calls a tool, then replies once it has the tool's response
+        struct ToolCallingMockProvider {
+            model_config: ModelConfig,
+        }
+
+        #[async_trait::async_trait]
+        impl Provider for ToolCallingMockProvider {
+            fn metadata() -> goose::providers::base::ProviderMetadata {
+                goose::providers::base::ProviderMetadata::empty()
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                self.model_config.clone()
+            }
+
+            async fn complete(
+                &self,
+                _system: &str,
+                messages: &[Message],
+                _tools: &[Tool],
+            ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+                let message = if messages.last().unwrap().get_tool_response_ids().is_empty() {
+                    Message::assistant().with_tool_request(
+                        "call-1",
+                        Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+                    )
+                } else {
+                    Message::assistant().with_text("done")
+                };
+                Ok((
+                    message,
+                    ProviderUsage::new("mock".to_string(), Usage::default()),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_reply_waits_for_confirmation() {
+            let mock_provider = Box::new(ToolCallingMockProvider {
+                model_config: ModelConfig::new("test-model".to_string()),
+            });
+            let agent = AgentFactory::create("truncate", mock_provider).unwrap();
+            agent
+                .set_approval_policy(ToolApprovalPolicy {
+                    mode: ToolApprovalMode::AlwaysAsk,
+                    ..Default::default()
+                })
+                .await;
+            let state = AppState {
+                agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
+                secret_key: "test-secret".to_string(),
+                sessions: Arc::new(SqliteSessionStore::open_in_memory().unwrap()),
+                session_locks: Default::default(),
+            };
+            state.agents.insert(agent);
+
+            let app = routes(state);
+
+            let request = Request::builder()
+                .uri("/reply")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from(
+                    json!({"messages": [{"role": "user", "content": "list files"}]}).to_string(),
+                ))
+                .unwrap();
+
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let mut body = response.into_body().into_data_stream();
+
+            // The reply pauses once it asks for confirmation
+            let mut received = String::new();
+            while !received.contains("2:") {
+                let chunk = body.next().await.unwrap().unwrap();
+                received.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+            assert!(received.contains("\"type\":\"confirmationRequired\""));
+            assert!(received.contains("\"toolCallId\":\"call-1\""));
+
+            let confirm = |id: &str| {
+                Request::builder()
+                    .uri("/confirm")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(
+                        serde_json::to_string(&ConfirmRequest {
+                            id: id.to_string(),
+                            confirmed: false,
+                        })
+                        .unwrap(),
+                    ))
+                    .unwrap()
+            };
+            let response = app.clone().oneshot(confirm("unknown")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let response = app.oneshot(confirm("call-1")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            // The declined call comes back as an error and the reply carries on
+            while let Some(chunk) = body.next().await {
+                received.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+            }
+            assert!(received.contains("The user declined to run developer__shell"));
+            assert!(received.contains("0:\"done\""));
+            assert!(received.contains("d:{\"finishReason\":\"stop\""));
+        }
     }
 }
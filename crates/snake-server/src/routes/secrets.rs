@@ -25,15 +25,7 @@ async fn store_secret(
     headers: HeaderMap,
     Json(request): Json<SecretRequest>,
 ) -> Result<Json<SecretResponse>, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
     let config = Config::global();
     let result = if request.is_secret {
@@ -84,15 +76,19 @@ fn check_key_status(key: &str) -> (bool, Option<String>) {
     if let Ok(_value) = std::env::var(key) {
         (true, Some("env".to_string()))
     } else if Config::global().get_secret::<String>(key).is_ok() {
-        (true, Some("keyring".to_string()))
+        (true, Some(Config::global().secrets_backend().to_string()))
     } else {
         (false, None)
     }
 }
 
 async fn check_provider_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<ProviderSecretRequest>,
 ) -> Result<Json<HashMap<String, ProviderResponse>>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
     let mut response = HashMap::new();
 
     for provider_name in request.providers {
@@ -147,15 +143,7 @@ async fn delete_secret(
     headers: HeaderMap,
     Json(request): Json<DeleteSecretRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    state.verify_secret_key(&headers)?;
 
     // Attempt to delete the key
     match Config::global().delete_secret(&request.key) {
@@ -175,6 +163,10 @@ pub fn routes(state: AppState) -> Router {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::AgentSessions;
+    use goose::session::SqliteSessionStore;
+    use std::sync::Arc;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_unsupported_provider() {
@@ -183,8 +175,17 @@ mod tests {
             providers: vec!["unsupported_provider".to_string()],
         };
 
+        let state = AppState {
+            agents: Arc::new(AgentSessions::new(Duration::from_secs(60))),
+            secret_key: "test-secret".to_string(),
+            sessions: Arc::new(SqliteSessionStore::open_in_memory().unwrap()),
+            session_locks: Default::default(),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Secret-Key", "test-secret".parse().unwrap());
+
         // Execute
-        let result = check_provider_secrets(Json(request)).await;
+        let result = check_provider_secrets(State(state), headers, Json(request)).await;
 
         // Assert
         assert!(result.is_ok());
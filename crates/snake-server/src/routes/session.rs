@@ -0,0 +1,106 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use goose::message::Message;
+use goose::session::{SessionError, SessionMetadata};
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct ListQuery {
+    /// Only list sessions whose name or messages contain this
+    q: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SessionResponse {
+    metadata: SessionMetadata,
+    messages: Vec<Message>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForkRequest {
+    /// The name of the new session
+    name: String,
+    /// How many of the session's messages the new one starts with
+    message_count: usize,
+}
+
+fn error_status(error: SessionError) -> StatusCode {
+    match error {
+        SessionError::NotFound(_) => StatusCode::NOT_FOUND,
+        SessionError::AlreadyExists(_) => StatusCode::CONFLICT,
+        SessionError::InvalidName(_) | SessionError::MessageOutOfRange { .. } => {
+            StatusCode::BAD_REQUEST
+        }
+        e => {
+            tracing::error!("Session store error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Every stored conversation, which any client with the secret key can read
+async fn list_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Vec<SessionMetadata>>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    state
+        .with_sessions(move |sessions| match query.q {
+            Some(q) => sessions.search(&q),
+            None => sessions.list(),
+        })
+        .await
+        .map(Json)
+        .map_err(error_status)
+}
+
+async fn get_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<SessionResponse>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    state
+        .with_sessions(move |sessions| {
+            Ok(SessionResponse {
+                metadata: sessions.metadata(&id)?,
+                messages: sessions.messages(&id)?,
+            })
+        })
+        .await
+        .map(Json)
+        .map_err(error_status)
+}
+
+async fn fork_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<ForkRequest>,
+) -> Result<Json<SessionMetadata>, StatusCode> {
+    state.verify_secret_key(&headers)?;
+
+    state
+        .with_sessions(move |sessions| sessions.fork(&id, request.message_count, &request.name))
+        .await
+        .map(Json)
+        .map_err(error_status)
+}
+
+// Configure routes for this module
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/:id", get(get_session))
+        .route("/sessions/:id/fork", post(fork_session))
+        .with_state(state)
+}
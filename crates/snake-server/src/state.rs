@@ -1,21 +1,280 @@
 use anyhow::Result;
-use goose::agents::Agent;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use goose::agents::{Agent, ToolConfirmations};
+use goose::config::Config;
+use goose::session::{SessionError, SessionResult, SessionStore};
+use http::{HeaderMap, StatusCode};
+use mcp_server::http::constant_time_eq;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 /// Shared application state
-#[allow(dead_code)]
 #[derive(Clone)]
 pub struct AppState {
-    pub agent: Arc<Mutex<Option<Box<dyn Agent>>>>,
+    /// The agents of the sessions created through `/agent`
+    pub agents: Arc<AgentSessions>,
+    /// The key clients send in `X-Secret-Key`
+    ///
+    /// It grants full access: any client with the key can use every agent session by its id
+    /// and read every stored conversation through `/sessions`. Sessions aren't scoped to
+    /// the client that created them, so only share the key with clients trusted with all of
+    /// them.
     pub secret_key: String,
+    /// The conversations of requests that name a session
+    pub sessions: Arc<dyn SessionStore>,
+    /// Held by the request updating a stored session, so concurrent replies don't
+    /// overwrite each other's messages
+    pub session_locks: Arc<SessionLocks>,
 }
 
 impl AppState {
-    pub async fn new(secret_key: String) -> Result<Self> {
+    pub async fn new(secret_key: String, idle_timeout: Duration) -> Result<Self> {
         Ok(Self {
-            agent: Arc::new(Mutex::new(None)),
+            agents: Arc::new(AgentSessions::new(idle_timeout)),
             secret_key,
+            sessions: goose::session::from_config(Config::global())?,
+            session_locks: Arc::new(SessionLocks::default()),
         })
     }
+
+    /// Run a call on the session store on the blocking thread pool, as the stores wait on
+    /// files and the database
+    pub async fn with_sessions<T, F>(&self, f: F) -> SessionResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn SessionStore) -> SessionResult<T> + Send + 'static,
+    {
+        let sessions = self.sessions.clone();
+        tokio::task::spawn_blocking(move || f(sessions.as_ref()))
+            .await
+            .map_err(|e| SessionError::Io(std::io::Error::other(e)))?
+    }
+
+    /// Check that a request carries the server's key in `X-Secret-Key`
+    pub fn verify_secret_key(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let secret_key = headers
+            .get("X-Secret-Key")
+            .and_then(|value| value.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        if !constant_time_eq(secret_key.as_bytes(), self.secret_key.as_bytes()) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(())
+    }
+
+    /// The agent session a request names in `X-Session-Id`
+    ///
+    /// Requests that don't name one use the only session when there is exactly one, so
+    /// clients that only ever use one agent don't need to track its id. With none or
+    /// several, they are refused rather than sent to a session another client started.
+    pub fn agent_session(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<(String, Arc<AgentSession>), StatusCode> {
+        let id = match headers.get("X-Session-Id") {
+            Some(id) => id
+                .to_str()
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+                .to_string(),
+            None => self
+                .agents
+                .only_id()
+                .ok_or(StatusCode::PRECONDITION_REQUIRED)?,
+        };
+        let session = self.agents.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+        Ok((id, session))
+    }
+}
+
+/// An agent with its own extensions, serving the requests of one session
+pub struct AgentSession {
+    pub agent: Mutex<Box<dyn Agent>>,
+    /// Tool calls waiting for the client to confirm them through `/confirm`
+    pub confirmations: ToolConfirmations,
+    last_used: std::sync::Mutex<Instant>,
+}
+
+impl AgentSession {
+    pub fn new(agent: Box<dyn Agent>) -> Self {
+        Self {
+            agent: Mutex::new(agent),
+            confirmations: ToolConfirmations::default(),
+            last_used: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Mark the session as used now, putting off its eviction
+    pub fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+}
+
+/// A lock for each stored session that a request is updating
+#[derive(Default)]
+pub struct SessionLocks {
+    locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl SessionLocks {
+    /// Wait for the lock of a session, which is held until the guard is dropped
+    pub async fn lock(&self, name: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            // Forget the locks no request holds or waits for
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(name.to_string()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// The agent sessions by id
+///
+/// Each session has its own lock, so a long reply in one session doesn't hold up requests
+/// to the others.
+pub struct AgentSessions {
+    sessions: RwLock<HashMap<String, Arc<AgentSession>>>,
+    idle_timeout: Duration,
+}
+
+impl AgentSessions {
+    /// Sessions which are evicted once unused for the idle timeout
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            sessions: RwLock::new(HashMap::new()),
+            idle_timeout,
+        }
+    }
+
+    /// Start a session with an agent, returning its new id
+    pub fn insert(&self, agent: Box<dyn Agent>) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), Arc::new(AgentSession::new(agent)));
+        id
+    }
+
+    /// The session with an id, marked as used
+    pub fn get(&self, id: &str) -> Option<Arc<AgentSession>> {
+        let session = self
+            .sessions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()?;
+        session.touch();
+        Some(session)
+    }
+
+    /// The id of the only session, if there is exactly one
+    pub fn only_id(&self) -> Option<String> {
+        let sessions = self.sessions.read().unwrap_or_else(|e| e.into_inner());
+        match sessions.len() {
+            1 => sessions.keys().next().cloned(),
+            _ => None,
+        }
+    }
+
+    /// End a session, returning false if there is none with the id
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+            .is_some()
+    }
+
+    /// End the sessions unused for longer than the idle timeout, returning their ids
+    ///
+    /// Sessions in the middle of a request are kept. So is the most recently used session
+    /// when every session is idle, so a client using one agent keeps it.
+    pub fn evict_idle(&self) -> Vec<String> {
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        let mut idle: Vec<(String, Duration)> = sessions
+            .iter()
+            .filter(|(_, session)| session.agent.try_lock().is_ok())
+            .map(|(id, session)| (id.clone(), session.idle_for()))
+            .filter(|(_, idle_for)| *idle_for > self.idle_timeout)
+            .collect();
+        if idle.len() == sessions.len() {
+            if let Some(latest) = idle.iter().min_by_key(|(_, idle_for)| *idle_for) {
+                let latest = latest.0.clone();
+                idle.retain(|(id, _)| *id != latest);
+            }
+        }
+        let idle: Vec<String> = idle.into_iter().map(|(id, _)| id).collect();
+        for id in &idle {
+            sessions.remove(id);
+        }
+        idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::agents::AgentFactory;
+    use goose::model::ModelConfig;
+    use goose::providers::ollama::OllamaProvider;
+
+    fn agent() -> Box<dyn Agent> {
+        let provider = OllamaProvider::from_env(ModelConfig::new("test-model".to_string()))
+            .expect("the ollama provider needs no secrets");
+        AgentFactory::create(AgentFactory::default_version(), Box::new(provider)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_evict_idle() {
+        let agents = AgentSessions::new(Duration::ZERO);
+        let first = agents.insert(agent());
+        let busy = agents.insert(agent());
+        let last = agents.insert(agent());
+        std::thread::sleep(Duration::from_millis(5));
+
+        // Sessions in use stay
+        let session = agents.get(&busy).unwrap();
+        let guard = session.agent.lock().await;
+        let mut evicted = agents.evict_idle();
+        evicted.sort();
+        let mut expected = vec![first.clone(), last.clone()];
+        expected.sort();
+        assert_eq!(evicted, expected);
+        assert!(agents.get(&first).is_none());
+        assert!(agents.get(&busy).is_some());
+        assert_eq!(agents.only_id(), Some(busy.clone()));
+
+        // The last session left is kept even when idle
+        drop(guard);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(agents.evict_idle().is_empty());
+
+        assert!(agents.remove(&busy));
+        assert_eq!(agents.only_id(), None);
+        assert!(!agents.remove(&busy));
+    }
+
+    #[tokio::test]
+    async fn test_session_locks() {
+        let locks = SessionLocks::default();
+        let guard = locks.lock("chat").await;
+
+        // Another session isn't held up, but the same one waits for the guard
+        drop(locks.lock("other").await);
+        let waiting = tokio::time::timeout(Duration::from_millis(20), locks.lock("chat")).await;
+        assert!(waiting.is_err());
+        drop(guard);
+        drop(locks.lock("chat").await);
+        assert!(locks.locks.lock().unwrap().len() <= 1);
+    }
 }
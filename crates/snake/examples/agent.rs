@@ -1,6 +1,6 @@
 use dotenv::dotenv;
 use futures::StreamExt;
-use goose::agents::{AgentFactory, ExtensionConfig};
+use goose::agents::{AgentEvent, AgentFactory, ExtensionConfig};
 use goose::message::Message;
 use goose::providers::databricks::DatabricksProvider;
 
@@ -19,18 +19,17 @@ async fn main() {
 
     println!("Extensions:");
     for extension in agent.list_extensions().await {
-        println!("  {}", extension);
+        println!("  {} ({})", extension.name, extension.state);
     }
 
     let messages = vec![Message::user()
         .with_text("can you summarize the readme.md in this dir using just a haiku?")];
 
     let mut stream = agent.reply(&messages).await.unwrap();
-    while let Some(message) = stream.next().await {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&message.unwrap()).unwrap()
-        );
-        println!("\n");
+    while let Some(event) = stream.next().await {
+        if let AgentEvent::Message(message) = event.unwrap() {
+            println!("{}", serde_json::to_string_pretty(&message).unwrap());
+            println!("\n");
+        }
     }
 }
@@ -1,17 +1,55 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::stream::BoxStream;
+use mcp_core::prompt::Prompt;
+use mcp_core::protocol::GetPromptResult;
 use serde_json::Value;
 
-use super::extension::{ExtensionConfig, ExtensionResult};
+use super::approval::{ToolApprovalPolicy, ToolConfirmationRequest};
+use super::extension::{ExtensionConfig, ExtensionResult, ExtensionStatus};
+use super::sampling::SamplingApprover;
 use crate::message::Message;
-use crate::providers::base::ProviderUsage;
+use crate::providers::base::{ProviderDelta, ProviderUsage};
+
+/// An event yielded while the agent replies
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// A complete message to add to the conversation
+    Message(Message),
+    /// A text or tool call fragment of the assistant message being generated, which is
+    /// followed by the complete message once the provider finishes
+    Delta(ProviderDelta),
+    /// A tool call needs the user's confirmation before it runs. The reply pauses until
+    /// it is answered through [`Agent::handle_confirmation`]
+    ConfirmationRequired(ToolConfirmationRequest),
+    /// An extension reported the progress of a tool call that is still running
+    Progress(ToolProgress),
+}
+
+/// Progress an extension reported while running a tool call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolProgress {
+    /// The id of the tool request being run
+    pub id: String,
+    /// The progress so far, which increases with every report
+    pub progress: f64,
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
 
 /// Core trait defining the behavior of an Agent
 #[async_trait]
 pub trait Agent: Send + Sync {
-    /// Create a stream that yields each message as it's generated by the agent
-    async fn reply(&self, messages: &[Message]) -> Result<BoxStream<'_, Result<Message>>>;
+    /// Create a stream that yields each message as it's generated by the agent, along with
+    /// the deltas of assistant messages while they are streamed from the provider
+    async fn reply(&self, messages: &[Message]) -> Result<BoxStream<'_, Result<AgentEvent>>>;
+
+    /// Answer a confirmation request for a tool call, letting the paused reply continue.
+    /// Declined tool calls are returned to the model as errors without running
+    async fn handle_confirmation(&self, request_id: &str, confirmed: bool);
 
     /// Add a new MCP client to the agent
     async fn add_extension(&mut self, config: ExtensionConfig) -> ExtensionResult<()>;
@@ -19,9 +57,27 @@ pub trait Agent: Send + Sync {
     /// Remove an extension by name
     async fn remove_extension(&mut self, name: &str);
 
-    /// List all extensions
-    // TODO this needs to also include status so we can tell if extensions are dropped
-    async fn list_extensions(&self) -> Vec<String>;
+    /// List all extensions with their health, restarting any stdio extension that stopped
+    /// once its backoff has elapsed
+    async fn list_extensions(&self) -> Vec<ExtensionStatus>;
+
+    /// List the prompts of every extension, keyed by extension name
+    async fn list_prompts(&self) -> HashMap<String, Vec<Prompt>>;
+
+    /// Render an extension's prompt with the given arguments
+    async fn get_prompt(
+        &self,
+        extension: &str,
+        name: &str,
+        arguments: Value,
+    ) -> ExtensionResult<GetPromptResult>;
+
+    /// Ask the approver before answering the sampling requests extensions send to use the
+    /// agent's model
+    async fn set_sampling_approver(&self, approver: Arc<dyn SamplingApprover>);
+
+    /// Replace the policy deciding which tool calls need the user's confirmation
+    async fn set_approval_policy(&self, policy: ToolApprovalPolicy);
 
     /// Pass through a JSON-RPC request to a specific extension
     async fn passthrough(&self, extension: &str, request: Value) -> ExtensionResult<Value>;
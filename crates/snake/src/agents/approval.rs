@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use mcp_core::tool::ToolCall;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::config::Config;
+
+/// The config key holding the tool approval policy
+pub const TOOL_APPROVAL_CONFIG_KEY: &str = "GOOSE_TOOL_APPROVAL";
+
+/// How the agent decides whether a tool call may run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApprovalMode {
+    /// Run every tool call without asking
+    #[default]
+    AlwaysAllow,
+    /// Ask the user before every tool call
+    AlwaysAsk,
+    /// Use the allow and deny lists, asking about any tool matching neither
+    Rules,
+}
+
+/// Policy deciding which tool calls need the user's confirmation
+///
+/// The allow and deny lists hold glob patterns matched against the prefixed tool name, so
+/// `developer__*` covers a whole extension and `*__shell` covers a tool in any extension.
+/// Deny patterns take precedence over allow patterns.
+///
+/// ```yaml
+/// GOOSE_TOOL_APPROVAL:
+///   mode: rules
+///   allow: ["developer__text_editor", "platform__*"]
+///   deny: ["*__shell"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolApprovalPolicy {
+    #[serde(default)]
+    pub mode: ToolApprovalMode,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// The outcome of checking a tool call against the approval policy, ordered from the least
+/// to the most strict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ToolApproval {
+    Allow,
+    Ask,
+    Deny,
+}
+
+impl ToolApprovalPolicy {
+    /// Load the policy from the config, falling back to allowing every tool call
+    pub fn from_config(config: &Config) -> Self {
+        config.get(TOOL_APPROVAL_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// Decide whether the tool with this prefixed name may run
+    pub fn check(&self, tool_name: &str) -> ToolApproval {
+        match self.mode {
+            ToolApprovalMode::AlwaysAllow => ToolApproval::Allow,
+            ToolApprovalMode::AlwaysAsk => ToolApproval::Ask,
+            ToolApprovalMode::Rules => {
+                if self
+                    .deny
+                    .iter()
+                    .any(|pattern| glob_match(pattern, tool_name))
+                {
+                    ToolApproval::Deny
+                } else if self
+                    .allow
+                    .iter()
+                    .any(|pattern| glob_match(pattern, tool_name))
+                {
+                    ToolApproval::Allow
+                } else {
+                    ToolApproval::Ask
+                }
+            }
+        }
+    }
+}
+
+/// Match a name against a glob pattern where `*` matches any run of characters and `?`
+/// matches a single character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and the name position it was tried against
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and try again
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A tool call that is waiting for the user to confirm it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolConfirmationRequest {
+    /// The id of the tool request in the assistant message
+    pub id: String,
+    pub tool_call: ToolCall,
+}
+
+/// Tracks the tool calls waiting for confirmation so the answers can be routed back
+/// to the reply that asked for them
+#[derive(Debug, Default)]
+pub struct ToolConfirmations {
+    pending: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+}
+
+impl ToolConfirmations {
+    /// Register a tool request as awaiting confirmation
+    ///
+    /// The receiver resolves once the request is confirmed, and errors if the
+    /// confirmations are dropped without an answer.
+    pub fn register(&self, id: &str) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.to_string(), tx);
+        rx
+    }
+
+    /// Answer a pending confirmation, returning false if no tool request with this id is waiting
+    pub fn confirm(&self, id: &str, confirmed: bool) -> bool {
+        match self.pending.lock().unwrap().remove(id) {
+            Some(tx) => tx.send(confirmed).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("developer__shell", "developer__shell"));
+        assert!(glob_match("developer__*", "developer__shell"));
+        assert!(glob_match("*__shell", "developer__shell"));
+        assert!(glob_match("*", "developer__shell"));
+        assert!(glob_match("dev*__*ll", "developer__shell"));
+        assert!(glob_match("developer__she?l", "developer__shell"));
+
+        assert!(!glob_match("developer__*", "developerx__shell"));
+        assert!(!glob_match("*__shell", "developer__shell_2"));
+        assert!(!glob_match("developer__she?l", "developer__shel"));
+        assert!(!glob_match("", "developer__shell"));
+    }
+
+    #[test]
+    fn test_policy_modes() {
+        let policy = ToolApprovalPolicy::default();
+        assert_eq!(policy.check("developer__shell"), ToolApproval::Allow);
+
+        let policy = ToolApprovalPolicy {
+            mode: ToolApprovalMode::AlwaysAsk,
+            ..Default::default()
+        };
+        assert_eq!(policy.check("developer__shell"), ToolApproval::Ask);
+    }
+
+    #[test]
+    fn test_policy_rules() {
+        let policy: ToolApprovalPolicy = serde_json::from_value(json!({
+            "mode": "rules",
+            "allow": ["developer__*", "platform__*"],
+            "deny": ["*__shell"]
+        }))
+        .unwrap();
+
+        assert_eq!(policy.check("developer__text_editor"), ToolApproval::Allow);
+        assert_eq!(policy.check("platform__read_resource"), ToolApproval::Allow);
+        // Deny takes precedence over allow
+        assert_eq!(policy.check("developer__shell"), ToolApproval::Deny);
+        assert_eq!(policy.check("jetbrains__shell"), ToolApproval::Deny);
+        assert_eq!(policy.check("memory__remember"), ToolApproval::Ask);
+    }
+
+    #[tokio::test]
+    async fn test_confirmations() {
+        let confirmations = ToolConfirmations::default();
+        let approved = confirmations.register("1");
+        let declined = confirmations.register("2");
+
+        assert!(confirmations.confirm("1", true));
+        assert!(confirmations.confirm("2", false));
+        assert!(!confirmations.confirm("3", true));
+        // Each request is answered once
+        assert!(!confirmations.confirm("1", false));
+
+        assert!(approved.await.unwrap());
+        assert!(!declined.await.unwrap());
+    }
+}
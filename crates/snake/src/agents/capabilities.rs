@@ -1,18 +1,46 @@
 use chrono::{DateTime, TimeZone, Utc};
-use futures::stream::{FuturesUnordered, StreamExt};
+use futures::future::Either;
+use futures::stream::{self, BoxStream, FuturesUnordered, StreamExt};
 use mcp_client::McpService;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::LazyLock;
 use std::time::Duration;
-use tokio::sync::Mutex;
-use tracing::{debug, instrument};
-
-use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+use tokio::sync::{Mutex, RwLock, Semaphore};
+use tokio::time::Instant;
+use tracing::{debug, info, instrument, warn};
+
+use super::agent::ToolProgress;
+use super::approval::{ToolApproval, ToolApprovalPolicy};
+use super::extension::{
+    ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ExtensionState,
+    ExtensionStatus, ToolFilter,
+};
+use super::limits::{AgentLimits, LimitExceeded};
+use super::sampling::{Sampling, SamplingApprover, SamplingPolicy};
+use super::tool_execution::ToolExecutionPolicy;
+use super::tool_output::{ToolOutputLimiter, ToolOutputPolicy};
+use crate::config::Config;
 use crate::prompt_template::load_prompt_file;
 use crate::providers::base::{Provider, ProviderUsage};
-use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
-use mcp_client::transport::{SseTransport, StdioTransport, Transport};
+use crate::providers::pricing::Pricing;
+use mcp_client::client::{
+    ClientCapabilities, ClientInfo, McpClient, McpClientTrait, SamplingCapability, SamplingHandler,
+};
+use mcp_client::transport::{
+    Error as TransportError, SseTransport, StdioTransport, StdioTransportHandle,
+    StreamableHttpTransport, Transport, TransportHandle,
+};
+use mcp_core::prompt::Prompt;
+use mcp_core::protocol::{
+    GetPromptResult, InitializeResult, JsonRpcNotification, ProgressNotificationParams,
+    ProgressToken,
+};
 use mcp_core::{Content, Tool, ToolCall, ToolError, ToolResult};
 use serde_json::Value;
 
@@ -21,15 +49,155 @@ use serde_json::Value;
 static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
     LazyLock::new(|| Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
 
-type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+type McpClientBox = Arc<dyn McpClientTrait>;
+
+/// How many times a stopped extension is restarted before it is left failed
+const MAX_EXTENSION_RESTARTS: u32 = 5;
+/// How long a restarted extension has to keep running for its restarts to be forgotten
+const HEALTHY_RESET: Duration = Duration::from_secs(300);
+/// How long an extension has to start and answer initialization before it counts as failed
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// How many lines of a stopped stdio extension's stderr are kept for its status
+const STDERR_TAIL_LINES: usize = 20;
+
+/// The backoff before restarting an extension that has been restarted this many times
+fn restart_backoff(restarts: u32) -> Duration {
+    match restarts {
+        0 => Duration::ZERO,
+        n => Duration::from_secs(1 << (n - 1).min(6)).min(Duration::from_secs(60)),
+    }
+}
+
+/// Create a client for an extension's transport, which answers the server's sampling requests
+async fn connect<T: TransportHandle>(
+    handle: T,
+    sampler: Arc<dyn SamplingHandler>,
+) -> Box<dyn McpClientTrait> {
+    let notifications = handle.subscribe();
+    let requests = handle.take_requests().await;
+    // Tool calls time out by the tool execution policy instead
+    let mut client = McpClient::new(McpService::new(handle))
+        .with_request_timeout(Duration::from_secs(300))
+        .with_notifications(notifications);
+    if let Some(requests) = requests {
+        client = client.with_requests(requests, Some(sampler));
+    }
+    Box::new(client)
+}
+
+/// A client for an extension whose server has started and been initialized
+struct StartedExtension {
+    client: Box<dyn McpClientTrait>,
+    /// The transport of a stdio extension, used to notice when its process stops
+    handle: Option<StdioTransportHandle>,
+    init_result: InitializeResult,
+}
+
+/// What is tracked about an extension to report its health and restart it
+struct ExtensionHealth {
+    config: ExtensionConfig,
+    status: ExtensionStatus,
+    handle: Option<StdioTransportHandle>,
+    /// When to next try restarting the extension after it stopped
+    next_restart: Option<Instant>,
+    /// When the extension last became ready
+    ready_since: Option<Instant>,
+}
+
+impl ExtensionHealth {
+    fn new(config: ExtensionConfig) -> Self {
+        Self {
+            status: ExtensionStatus::new(config.name()),
+            config,
+            handle: None,
+            next_restart: None,
+            ready_since: None,
+        }
+    }
+
+    /// The error the extension's process stopped with, if it stopped
+    async fn process_error(&self) -> Option<TransportError> {
+        let handle = self.handle.as_ref()?;
+        if let Err(e) = handle.check_for_errors().await {
+            return Some(e);
+        }
+        handle
+            .is_closed()
+            .then(|| TransportError::StdioProcessError(String::new()))
+    }
+
+    /// Mark the extension as stopped, scheduling a restart unless it was restarted too often
+    fn fail(&mut self, error: String) {
+        self.status.state = ExtensionState::Failed;
+        self.status.last_error = Some(error);
+        self.handle = None;
+        self.ready_since = None;
+        self.next_restart = (self.status.restarts < MAX_EXTENSION_RESTARTS)
+            .then(|| Instant::now() + restart_backoff(self.status.restarts));
+    }
+
+    /// Forget the restarts of an extension that has been running for a while since, so
+    /// the restart limit and backoff only apply to an extension that keeps stopping
+    fn forgive_restarts(&mut self) {
+        if self
+            .ready_since
+            .is_some_and(|since| since.elapsed() >= HEALTHY_RESET)
+        {
+            self.status.restarts = 0;
+        }
+    }
+}
 
 /// Manages MCP clients and their interactions
 pub struct Capabilities {
     clients: HashMap<String, McpClientBox>,
+    extensions: HashMap<String, ExtensionHealth>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
-    provider: Box<dyn Provider>,
-    provider_usage: Mutex<Vec<ProviderUsage>>,
+    /// Tools and resources of each extension, kept until the extension announces a change.
+    /// Tools are kept as offered to the model, with the name the extension knows them by.
+    tool_cache: HashMap<String, Vec<(Tool, String)>>,
+    /// The extension and tool serving each tool name offered to the model
+    tool_routes: HashMap<String, ToolRoute>,
+    /// The prefixed names of the tools left out of the last listing because their name was
+    /// taken, which can't be called by their prefixed name either
+    dropped_tools: HashSet<String>,
+    resource_cache: HashMap<String, Vec<ResourceItem>>,
+    /// URIs of the resources each extension notifies us about when they change
+    resource_subscriptions: HashMap<String, HashSet<String>>,
+    notifications: HashMap<String, broadcast::Receiver<JsonRpcNotification>>,
+    provider: Arc<dyn Provider>,
+    provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
+    sampling: Arc<Sampling>,
+    approval_policy: ToolApprovalPolicy,
+    pricing: Pricing,
+    limits: AgentLimits,
+    tool_calls: AtomicUsize,
+    tool_output: Arc<ToolOutputLimiter>,
+    tool_execution: ToolExecutionPolicy,
+    /// Limits how many tool calls run at once, when the policy sets a maximum
+    tool_slots: Option<Semaphore>,
+    extension_turns: std::sync::Mutex<HashMap<String, Arc<RwLock<()>>>>,
+}
+
+/// Tool names starting with this are reserved for the tools the agent adds itself
+const PLATFORM_TOOL_PREFIX: &str = "platform__";
+
+/// The extension serving a tool, the name the extension knows the tool by, and whether
+/// calls to it can run alongside other calls to the extension
+#[derive(Debug, Clone)]
+struct ToolRoute {
+    extension: String,
+    tool: String,
+    parallel: bool,
+}
+
+/// The extension already offering a tool name, if it is taken
+fn tool_owner<'a>(routes: &'a HashMap<String, ToolRoute>, name: &str) -> Option<&'a str> {
+    if name.starts_with(PLATFORM_TOOL_PREFIX) {
+        return Some("platform");
+    }
+    routes.get(name).map(|route| route.extension.as_str())
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -82,12 +250,99 @@ fn normalize(input: String) -> String {
 impl Capabilities {
     /// Create a new Capabilities with the specified provider
     pub fn new(provider: Box<dyn Provider>) -> Self {
+        let provider: Arc<dyn Provider> = Arc::from(provider);
+        let provider_usage = Arc::new(Mutex::new(Vec::new()));
+        let pricing = Pricing::from_config(Config::global());
+        let sampling = Arc::new(Sampling::new(
+            Arc::clone(&provider),
+            Arc::clone(&provider_usage),
+            pricing.clone(),
+            SamplingPolicy::from_config(Config::global()),
+        ));
+        let tool_execution = ToolExecutionPolicy::from_config(Config::global());
+        let tool_output = Arc::new(ToolOutputLimiter::new(
+            &ToolOutputPolicy::from_config(Config::global()),
+            &provider.get_model_config(),
+        ));
         Self {
             clients: HashMap::new(),
+            extensions: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            tool_cache: HashMap::new(),
+            tool_routes: HashMap::new(),
+            dropped_tools: HashSet::new(),
+            resource_cache: HashMap::new(),
+            resource_subscriptions: HashMap::new(),
+            notifications: HashMap::new(),
             provider,
-            provider_usage: Mutex::new(Vec::new()),
+            provider_usage,
+            sampling,
+            approval_policy: ToolApprovalPolicy::from_config(Config::global()),
+            pricing,
+            limits: AgentLimits::from_config(Config::global()),
+            tool_calls: AtomicUsize::new(0),
+            tool_output,
+            tool_slots: tool_execution.concurrency_limit().map(Semaphore::new),
+            tool_execution,
+            extension_turns: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the limits on turns, tokens, tool calls and spend
+    pub fn set_limits(&mut self, limits: AgentLimits) {
+        self.limits = limits;
+    }
+
+    /// Replace the policy for how tool calls are run
+    pub fn set_tool_execution(&mut self, policy: ToolExecutionPolicy) {
+        self.tool_slots = policy.concurrency_limit().map(Semaphore::new);
+        self.tool_execution = policy;
+    }
+
+    /// Check whether another completion may be requested, after the given number of
+    /// turns in the current reply
+    pub async fn check_limits(&self, turns: usize) -> Result<(), LimitExceeded> {
+        let usage = self.provider_usage.lock().await;
+        self.limits.check(turns, &usage)
+    }
+
+    /// Count a tool call against the session's limit, failing once the limit is reached
+    pub fn reserve_tool_call(&self) -> Result<(), LimitExceeded> {
+        let Some(max) = self.limits.max_tool_calls else {
+            self.tool_calls.fetch_add(1, Ordering::SeqCst);
+            return Ok(());
+        };
+        self.tool_calls
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |calls| {
+                (calls < max).then_some(calls + 1)
+            })
+            .map(|_| ())
+            .map_err(|_| LimitExceeded::ToolCalls(max))
+    }
+
+    /// Ask the approver before answering any extension's sampling request
+    pub fn set_sampling_approver(&self, approver: Arc<dyn SamplingApprover>) {
+        self.sampling.set_approver(approver);
+    }
+
+    /// Replace the policy deciding which tool calls need confirmation
+    pub fn set_approval_policy(&mut self, policy: ToolApprovalPolicy) {
+        self.approval_policy = policy;
+    }
+
+    /// Check whether a tool call may run, needs the user's confirmation, or is denied
+    ///
+    /// A tool offered under an alias is checked under its prefixed name as well, and the
+    /// stricter of the two decides, so an alias can't get around a rule for its extension.
+    pub fn tool_approval(&self, tool_call: &ToolCall) -> ToolApproval {
+        let approval = self.approval_policy.check(&tool_call.name);
+        match self.get_client_for_tool(&tool_call.name) {
+            Some((route, _)) => approval.max(
+                self.approval_policy
+                    .check(&format!("{}__{}", route.extension, route.tool)),
+            ),
+            None => approval,
         }
     }
 
@@ -96,24 +351,120 @@ impl Capabilities {
     }
 
     /// Add a new MCP extension based on the provided client type
-    // TODO IMPORTANT need to ensure this times out if the extension command is broken!
     pub async fn add_extension(&mut self, config: ExtensionConfig) -> ExtensionResult<()> {
-        let mut client: Box<dyn McpClientTrait> = match &config {
-            ExtensionConfig::Sse { uri, envs, .. } => {
-                let transport = SseTransport::new(uri, envs.get_env());
+        config
+            .tool_filter()
+            .validate()
+            .map_err(ExtensionError::InvalidRequest)?;
+        let sanitized_name = normalize(config.name().to_string());
+        self.extensions
+            .insert(sanitized_name.clone(), ExtensionHealth::new(config.clone()));
+
+        let sampler = self.sampling.handler(&sanitized_name);
+        match Self::start_extension(&config, sampler).await {
+            Ok(started) => {
+                self.register_extension(&sanitized_name, started);
+                if let Err(e) = self.check_tool_collisions(&sanitized_name).await {
+                    self.remove_extension(&sanitized_name).await?;
+                    return Err(e);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.extensions.remove(&sanitized_name);
+                Err(e)
+            }
+        }
+    }
+
+    /// Fail if a new extension would offer a tool under a name that is already taken
+    async fn check_tool_collisions(&mut self, sanitized_name: &str) -> ExtensionResult<()> {
+        let mut others: Vec<String> = self
+            .clients
+            .keys()
+            .filter(|name| *name != sanitized_name)
+            .cloned()
+            .collect();
+        others.sort();
+
+        let mut routes = HashMap::new();
+        for name in others {
+            // An extension that can't list its tools right now doesn't block this one
+            let Ok(tools) = self.extension_tools(&name).await else {
+                continue;
+            };
+            for (tool, original) in tools {
+                let parallel = tool.is_parallel();
+                routes.entry(tool.name).or_insert(ToolRoute {
+                    extension: name.clone(),
+                    tool: original,
+                    parallel,
+                });
+            }
+        }
+
+        for (tool, original) in self.extension_tools(sanitized_name).await? {
+            if let Some(existing) = tool_owner(&routes, &tool.name) {
+                return Err(ExtensionError::ToolCollision {
+                    tool: tool.name,
+                    extension: sanitized_name.to_string(),
+                    existing: existing.to_string(),
+                });
+            }
+            let parallel = tool.is_parallel();
+            routes.insert(
+                tool.name,
+                ToolRoute {
+                    extension: sanitized_name.to_string(),
+                    tool: original,
+                    parallel,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Start the server for an extension and initialize a client for it
+    ///
+    /// Gives up after [`STARTUP_TIMEOUT`], so a broken extension command can't hang whoever
+    /// holds the capabilities while it starts or restarts.
+    async fn start_extension(
+        config: &ExtensionConfig,
+        sampler: Arc<dyn SamplingHandler>,
+    ) -> ExtensionResult<StartedExtension> {
+        tokio::time::timeout(STARTUP_TIMEOUT, Self::connect_extension(config, sampler))
+            .await
+            .map_err(|_| {
+                ExtensionError::StartupTimeout(config.name().to_string(), STARTUP_TIMEOUT.as_secs())
+            })?
+    }
+
+    async fn connect_extension(
+        config: &ExtensionConfig,
+        sampler: Arc<dyn SamplingHandler>,
+    ) -> ExtensionResult<StartedExtension> {
+        let (mut client, handle) = match config {
+            ExtensionConfig::Sse {
+                uri, envs, headers, ..
+            } => {
+                let transport =
+                    SseTransport::new(uri, envs.get_env()).with_headers(headers.clone());
+                let handle = transport.start().await?;
+                (connect(handle, sampler).await, None)
+            }
+            ExtensionConfig::StreamableHttp { uri, headers, .. } => {
+                let transport = StreamableHttpTransport::new(uri, headers.clone());
                 let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+                (connect(handle, sampler).await, None)
             }
             ExtensionConfig::Stdio {
                 cmd, args, envs, ..
             } => {
                 let transport = StdioTransport::new(cmd, args.to_vec(), envs.get_env());
                 let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+                (connect(handle.clone(), sampler).await, Some(handle))
             }
-            ExtensionConfig::Builtin { name } => {
+            ExtensionConfig::Builtin { name, .. } => {
                 // For builtin extensions, we run the current executable with mcp and extension name
                 let cmd = std::env::current_exe()
                     .expect("should find the current executable")
@@ -126,42 +477,169 @@ impl Capabilities {
                     HashMap::new(),
                 );
                 let handle = transport.start().await?;
-                let service = McpService::with_timeout(handle, Duration::from_secs(300));
-                Box::new(McpClient::new(service))
+                (connect(handle.clone(), sampler).await, Some(handle))
             }
         };
 
-        // Initialize the client with default capabilities
+        // Initialize the client, offering the agent's model for sampling
         let info = ClientInfo {
             name: "goose".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         };
-        let capabilities = ClientCapabilities::default();
+        let capabilities = ClientCapabilities {
+            sampling: Some(SamplingCapability::default()),
+        };
 
         let init_result = client
             .initialize(info, capabilities)
             .await
-            .map_err(|e| ExtensionError::Initialization(config.clone(), e))?;
+            .map_err(|e| ExtensionError::Initialization(Box::new(config.clone()), e))?;
 
-        let sanitized_name = normalize(config.name().to_string());
+        Ok(StartedExtension {
+            client,
+            handle,
+            init_result,
+        })
+    }
 
+    /// Store the client of a started extension, replacing the one it had before a restart
+    fn register_extension(&mut self, sanitized_name: &str, started: StartedExtension) {
         // Store instructions if provided
-        if let Some(instructions) = init_result.instructions {
-            self.instructions
-                .insert(sanitized_name.clone(), instructions);
+        match started.init_result.instructions {
+            Some(instructions) => {
+                self.instructions
+                    .insert(sanitized_name.to_string(), instructions);
+            }
+            None => {
+                self.instructions.remove(sanitized_name);
+            }
         }
 
         // if the server is capable if resources we track it
-        if init_result.capabilities.resources.is_some() {
+        if started.init_result.capabilities.resources.is_some() {
             self.resource_capable_extensions
-                .insert(sanitized_name.clone());
+                .insert(sanitized_name.to_string());
+        } else {
+            self.resource_capable_extensions.remove(sanitized_name);
         }
 
+        // A restarted server starts without our subscriptions and may list different tools
+        self.invalidate_caches(sanitized_name);
+        self.resource_subscriptions.remove(sanitized_name);
+        self.notifications
+            .insert(sanitized_name.to_string(), started.client.subscribe());
+
         // Store the client using the provided name
         self.clients
-            .insert(sanitized_name.clone(), Arc::new(Mutex::new(client)));
+            .insert(sanitized_name.to_string(), Arc::from(started.client));
 
-        Ok(())
+        if let Some(health) = self.extensions.get_mut(sanitized_name) {
+            health.status.state = ExtensionState::Ready;
+            health.handle = started.handle;
+            health.next_restart = None;
+            health.ready_since = Some(Instant::now());
+        }
+    }
+
+    /// Check whether the stdio extensions are still running, and restart those that
+    /// stopped once their backoff has elapsed
+    pub async fn check_extensions(&mut self) {
+        self.handle_notifications();
+        let mut due = Vec::new();
+
+        for (name, health) in self.extensions.iter_mut() {
+            if health.status.state == ExtensionState::Ready {
+                if let Some(error) = health.process_error().await {
+                    let (error, stderr) = match error {
+                        TransportError::StdioProcessError(stderr) => {
+                            ("The extension process stopped".to_string(), stderr)
+                        }
+                        e => (e.to_string(), String::new()),
+                    };
+                    warn!("Extension {} stopped: {}", name, stderr.trim());
+
+                    let lines: Vec<String> = stderr
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(String::from)
+                        .collect();
+                    health.status.stderr_tail =
+                        lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].to_vec();
+                    health.fail(error);
+                } else {
+                    health.forgive_restarts();
+                }
+            }
+
+            if health.status.state == ExtensionState::Failed
+                && health.next_restart.is_some_and(|at| at <= Instant::now())
+            {
+                due.push(name.clone());
+            }
+        }
+
+        for name in due {
+            self.restart_extension(&name).await;
+        }
+    }
+
+    /// Drop the cached tools and resources of extensions that announced they changed
+    fn handle_notifications(&mut self) {
+        for (name, receiver) in self.notifications.iter_mut() {
+            loop {
+                match receiver.try_recv() {
+                    Ok(notification) => match notification.method.as_str() {
+                        "notifications/tools/list_changed" => {
+                            debug!("Tools of extension {} changed", name);
+                            self.tool_cache.remove(name);
+                        }
+                        "notifications/resources/list_changed"
+                        | "notifications/resources/updated" => {
+                            debug!("Resources of extension {} changed", name);
+                            self.resource_cache.remove(name);
+                        }
+                        method => debug!("Ignoring notification {} from {}", method, name),
+                    },
+                    // We can't tell what the missed notifications were about
+                    Err(TryRecvError::Lagged(_)) => {
+                        self.tool_cache.remove(name);
+                        self.resource_cache.remove(name);
+                    }
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                }
+            }
+        }
+    }
+
+    fn invalidate_caches(&mut self, sanitized_name: &str) {
+        self.tool_cache.remove(sanitized_name);
+        self.resource_cache.remove(sanitized_name);
+    }
+
+    async fn restart_extension(&mut self, sanitized_name: &str) {
+        let Some(health) = self.extensions.get_mut(sanitized_name) else {
+            return;
+        };
+        health.status.state = ExtensionState::Restarting;
+        health.status.restarts += 1;
+        let config = health.config.clone();
+
+        info!(
+            "Restarting extension {} (attempt {}/{})",
+            config.name(),
+            health.status.restarts,
+            MAX_EXTENSION_RESTARTS
+        );
+        let sampler = self.sampling.handler(sanitized_name);
+        match Self::start_extension(&config, sampler).await {
+            Ok(started) => self.register_extension(sanitized_name, started),
+            Err(e) => {
+                warn!("Failed to restart extension {}: {}", config.name(), e);
+                if let Some(health) = self.extensions.get_mut(sanitized_name) {
+                    health.fail(e.to_string());
+                }
+            }
+        }
     }
 
     /// Get a reference to the provider
@@ -169,9 +647,10 @@ impl Capabilities {
         &*self.provider
     }
 
-    /// Record provider usage
+    /// Record provider usage, pricing it with the configured model prices
     // TODO consider moving this off to the provider or as a form of logging
-    pub async fn record_usage(&self, usage: ProviderUsage) {
+    pub async fn record_usage(&self, mut usage: ProviderUsage) {
+        usage.cost = self.pricing.cost(&usage.model, &usage.usage);
         self.provider_usage.lock().await.push(usage);
     }
 
@@ -180,22 +659,33 @@ impl Capabilities {
         let sanitized_name = normalize(name.to_string());
 
         self.clients.remove(&sanitized_name);
+        self.extensions.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.invalidate_caches(&sanitized_name);
+        self.resource_subscriptions.remove(&sanitized_name);
+        self.notifications.remove(&sanitized_name);
         Ok(())
     }
 
-    pub async fn list_extensions(&self) -> ExtensionResult<Vec<String>> {
-        Ok(self.clients.keys().cloned().collect())
+    /// List the extensions with their health, sorted by name
+    pub async fn list_extensions(&self) -> ExtensionResult<Vec<ExtensionStatus>> {
+        let mut statuses: Vec<ExtensionStatus> = self
+            .extensions
+            .values()
+            .map(|health| health.status.clone())
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(statuses)
     }
 
     pub async fn get_usage(&self) -> Vec<ProviderUsage> {
         let provider_usage = self.provider_usage.lock().await.clone();
-        let mut usage_map: HashMap<String, ProviderUsage> = HashMap::new();
+        let mut usage_map: HashMap<(Option<String>, String), ProviderUsage> = HashMap::new();
 
         provider_usage.iter().for_each(|usage| {
             usage_map
-                .entry(usage.model.clone())
+                .entry((usage.provider.clone(), usage.model.clone()))
                 .and_modify(|e| {
                     e.usage.input_tokens = Some(
                         e.usage.input_tokens.unwrap_or(0) + usage.usage.input_tokens.unwrap_or(0),
@@ -206,6 +696,10 @@ impl Capabilities {
                     e.usage.total_tokens = Some(
                         e.usage.total_tokens.unwrap_or(0) + usage.usage.total_tokens.unwrap_or(0),
                     );
+                    e.cost = match (e.cost, usage.cost) {
+                        (None, None) => None,
+                        (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+                    };
                 })
                 .or_insert_with(|| usage.clone());
         });
@@ -213,39 +707,118 @@ impl Capabilities {
     }
 
     /// Get all tools from all clients with proper prefixing
+    ///
+    /// Tools are cached per extension until it sends `notifications/tools/list_changed`.
     pub async fn get_prefixed_tools(&mut self) -> ExtensionResult<Vec<Tool>> {
-        let mut tools = Vec::new();
-        for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let mut client_tools = client_guard.list_tools(None).await?;
+        self.handle_notifications();
 
-            loop {
-                for tool in client_tools.tools {
-                    tools.push(Tool::new(
-                        format!("{}__{}", name, tool.name),
-                        &tool.description,
-                        tool.input_schema,
-                    ));
+        // Extensions are visited in order so a collision always drops the same tool
+        let mut names: Vec<String> = self.clients.keys().cloned().collect();
+        names.sort();
+
+        let mut tools = Vec::new();
+        let mut routes = HashMap::new();
+        let mut dropped = HashSet::new();
+        for name in names {
+            for (tool, original) in self.extension_tools(&name).await? {
+                if let Some(existing) = tool_owner(&routes, &tool.name) {
+                    warn!(
+                        "Skipping tool {} of extension {}, which is already offered by {}",
+                        tool.name, name, existing
+                    );
+                    dropped.insert(format!("{}__{}", name, original));
+                    continue;
                 }
+                routes.insert(
+                    tool.name.clone(),
+                    ToolRoute {
+                        extension: name.clone(),
+                        tool: original,
+                        parallel: tool.is_parallel(),
+                    },
+                );
+                tools.push(tool);
+            }
+        }
+        self.tool_routes = routes;
+        self.dropped_tools = dropped;
+        Ok(tools)
+    }
+
+    /// The filter for an extension's tools, if it has one
+    fn tool_filter(&self, sanitized_name: &str) -> Option<&ToolFilter> {
+        self.extensions
+            .get(sanitized_name)
+            .map(|health| health.config.tool_filter())
+    }
+
+    /// List the tools an extension offers after its filter, each with its offered name and
+    /// the name the extension knows it by
+    async fn extension_tools(
+        &mut self,
+        sanitized_name: &str,
+    ) -> ExtensionResult<Vec<(Tool, String)>> {
+        if let Some(cached) = self.tool_cache.get(sanitized_name) {
+            return Ok(cached.clone());
+        }
+        let Some(client) = self.clients.get(sanitized_name) else {
+            return Err(ExtensionError::NotFound(sanitized_name.to_string()));
+        };
+
+        let mut client_tools = client.list_tools(None).await?;
+        let filter = self
+            .tool_filter(sanitized_name)
+            .cloned()
+            .unwrap_or_default();
+        let mut extension_tools = Vec::new();
 
-                // exit loop when there are no more pages
-                if client_tools.next_cursor.is_none() {
-                    break;
+        loop {
+            for tool in client_tools.tools {
+                if !filter.allows(&tool.name) {
+                    continue;
                 }
+                extension_tools.push((
+                    Tool {
+                        name: filter.offered_name(sanitized_name, &tool.name),
+                        description: tool.description,
+                        input_schema: tool.input_schema,
+                        annotations: tool.annotations,
+                    },
+                    tool.name,
+                ));
+            }
 
-                client_tools = client_guard.list_tools(client_tools.next_cursor).await?;
+            // exit loop when there are no more pages
+            if client_tools.next_cursor.is_none() {
+                break;
             }
+
+            client_tools = client.list_tools(client_tools.next_cursor).await?;
         }
-        Ok(tools)
+
+        self.tool_cache
+            .insert(sanitized_name.to_string(), extension_tools.clone());
+        Ok(extension_tools)
     }
 
     /// Get client resources and their contents
-    pub async fn get_resources(&self) -> ExtensionResult<Vec<ResourceItem>> {
+    ///
+    /// Resources are cached per extension until it announces that its list or one of the
+    /// active resources changed. Active resources are subscribed to when the server allows it.
+    pub async fn get_resources(&mut self) -> ExtensionResult<Vec<ResourceItem>> {
+        self.handle_notifications();
+
         let mut result: Vec<ResourceItem> = Vec::new();
 
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let resources = client_guard.list_resources(None).await?;
+            if let Some(cached) = self.resource_cache.get(name) {
+                result.extend(cached.iter().cloned());
+                continue;
+            }
+
+            let resources = client.list_resources(None).await?;
+            let mut extension_resources = Vec::new();
+            let subscriptions = self.resource_subscriptions.entry(name.clone()).or_default();
 
             for resource in resources.resources {
                 // Skip reading the resource if it's not marked active
@@ -254,7 +827,16 @@ impl Capabilities {
                     continue;
                 }
 
-                if let Ok(contents) = client_guard.read_resource(&resource.uri).await {
+                if !subscriptions.contains(&resource.uri) {
+                    match client.subscribe_resource(&resource.uri).await {
+                        Ok(()) => {
+                            subscriptions.insert(resource.uri.clone());
+                        }
+                        Err(e) => debug!("Not subscribed to {}: {}", resource.uri, e),
+                    }
+                }
+
+                if let Ok(contents) = client.read_resource(&resource.uri).await {
                     for content in contents.contents {
                         let (uri, content_str) = match content {
                             mcp_core::resource::ResourceContents::TextResourceContents {
@@ -269,7 +851,7 @@ impl Capabilities {
                             } => (uri, blob),
                         };
 
-                        result.push(ResourceItem::new(
+                        extension_resources.push(ResourceItem::new(
                             name.clone(),
                             uri,
                             resource.name.clone(),
@@ -280,6 +862,10 @@ impl Capabilities {
                     }
                 }
             }
+
+            result.extend(extension_resources.iter().cloned());
+            self.resource_cache
+                .insert(name.clone(), extension_resources);
         }
         Ok(result)
     }
@@ -301,12 +887,48 @@ impl Capabilities {
         load_prompt_file("system.md", &context).expect("Prompt should render")
     }
 
-    /// Find and return a reference to the appropriate client for a tool call
-    fn get_client_for_tool(&self, prefixed_name: &str) -> Option<(&str, McpClientBox)> {
-        self.clients
-            .iter()
-            .find(|(key, _)| prefixed_name.starts_with(*key))
-            .map(|(name, client)| (name.as_str(), Arc::clone(client)))
+    /// Find the client for a tool call, with the route to the tool on its extension
+    ///
+    /// Names offered in the last tool listing are looked up directly. Other names are split
+    /// on the longest extension name followed by `__`, so `dev_tools__run` never reaches an
+    /// extension named `dev`, and run one at a time as nothing is known about them. Tools the
+    /// last listing left out because their name was taken aren't routed this way either.
+    fn get_client_for_tool(&self, offered_name: &str) -> Option<(ToolRoute, McpClientBox)> {
+        let route = match self.tool_routes.get(offered_name) {
+            Some(route) => route.clone(),
+            None if self.dropped_tools.contains(offered_name) => return None,
+            None => self
+                .clients
+                .keys()
+                .filter_map(|name| {
+                    let tool = offered_name
+                        .strip_prefix(name.as_str())?
+                        .strip_prefix("__")?;
+                    (!tool.is_empty()).then(|| ToolRoute {
+                        extension: name.clone(),
+                        tool: tool.to_string(),
+                        parallel: false,
+                    })
+                })
+                .max_by_key(|route| route.extension.len())?,
+        };
+
+        // Filtered tools can't be called even by their prefixed name
+        if !self
+            .tool_filter(&route.extension)
+            .is_none_or(|filter| filter.allows(&route.tool))
+        {
+            return None;
+        }
+        let client = Arc::clone(self.clients.get(&route.extension)?);
+        Some((route, client))
+    }
+
+    /// The lock an extension's sequential tool calls hold while they run, and its parallel
+    /// calls share
+    fn extension_turn(&self, sanitized_name: &str) -> Arc<RwLock<()>> {
+        let mut turns = self.extension_turns.lock().unwrap();
+        Arc::clone(turns.entry(sanitized_name.to_string()).or_default())
     }
 
     // Function that gets executed for read_resource tool
@@ -374,8 +996,7 @@ impl Capabilities {
             .get(extension_name)
             .ok_or(ToolError::InvalidParameters(error_msg))?;
 
-        let client_guard = client.lock().await;
-        let read_result = client_guard.read_resource(uri).await.map_err(|_| {
+        let read_result = client.read_resource(uri).await.map_err(|_| {
             ToolError::ExecutionError(format!("Could not read resource with uri: {}", uri))
         })?;
 
@@ -400,8 +1021,7 @@ impl Capabilities {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_resources(None)
             .await
             .map_err(|e| {
@@ -472,34 +1092,206 @@ impl Capabilities {
         }
     }
 
+    /// List the prompts of every extension, keyed by the extension's name
+    ///
+    /// Extensions that fail to list their prompts are left out.
+    pub async fn list_prompts(&self) -> HashMap<String, Vec<Prompt>> {
+        let mut futures = FuturesUnordered::new();
+        for (name, client) in &self.clients {
+            futures.push(async move { (name.clone(), Self::all_prompts(client.as_ref()).await) });
+        }
+
+        let mut prompts = HashMap::new();
+        while let Some((name, result)) = futures.next().await {
+            match result {
+                Ok(result) if !result.is_empty() => {
+                    prompts.insert(name, result);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to list prompts of extension {}: {}", name, e),
+            }
+        }
+        prompts
+    }
+
+    /// Every page of an extension's prompts
+    async fn all_prompts(
+        client: &dyn McpClientTrait,
+    ) -> Result<Vec<Prompt>, mcp_client::client::Error> {
+        let mut page = client.list_prompts(None).await?;
+        let mut prompts = std::mem::take(&mut page.prompts);
+        while let Some(cursor) = page.next_cursor {
+            page = client.list_prompts(Some(cursor)).await?;
+            prompts.append(&mut page.prompts);
+        }
+        Ok(prompts)
+    }
+
+    /// Render one of an extension's prompts with the given arguments
+    pub async fn get_prompt(
+        &self,
+        extension: &str,
+        name: &str,
+        arguments: Value,
+    ) -> ExtensionResult<GetPromptResult> {
+        let client = self
+            .clients
+            .get(&normalize(extension.to_string()))
+            .ok_or_else(|| ExtensionError::NotFound(extension.to_string()))?;
+
+        Ok(client.get_prompt(name, arguments).await?)
+    }
+
+    /// Forward a JSON-RPC request to an extension, returning the raw result
+    ///
+    /// The request names the `method` and optionally its `params`, which default to an
+    /// empty object. Tool calls are rejected, since they have to go through
+    /// [`Self::dispatch_tool_call`] for the approval policy, tool filters and output limits
+    /// to apply, as are sampling requests, which only extensions make. Every forwarded
+    /// request is subject to the extension's request timeout.
+    pub async fn passthrough(&self, extension: &str, request: Value) -> ExtensionResult<Value> {
+        let client = self
+            .clients
+            .get(&normalize(extension.to_string()))
+            .ok_or_else(|| ExtensionError::NotFound(extension.to_string()))?;
+
+        let method = request
+            .get("method")
+            .and_then(|m| m.as_str())
+            .ok_or_else(|| ExtensionError::InvalidRequest("missing method".to_string()))?;
+        if method == "tools/call" || method.starts_with("sampling/") {
+            return Err(ExtensionError::InvalidRequest(format!(
+                "{} can't be forwarded",
+                method
+            )));
+        }
+        let params = request
+            .get("params")
+            .cloned()
+            .unwrap_or_else(|| Value::Object(Default::default()));
+
+        Ok(client.request(method, params).await?)
+    }
+
     /// Dispatch a single tool call to the appropriate client
-    #[instrument(skip(self, tool_call), fields(input, output))]
+    ///
+    /// Calls the approval policy denies are rejected without running. Calls that need
+    /// confirmation are expected to have been confirmed by the user before being dispatched.
     pub async fn dispatch_tool_call(&self, tool_call: ToolCall) -> ToolResult<Vec<Content>> {
-        let result = if tool_call.name == "platform__read_resource" {
+        self.dispatch(tool_call, None).await
+    }
+
+    /// Dispatch the tool call of a tool request like [`Self::dispatch_tool_call`], asking the
+    /// extension to report its progress, which [`Self::tool_progress`] passes on
+    pub async fn dispatch_tool_request(
+        &self,
+        request_id: &str,
+        tool_call: ToolCall,
+    ) -> ToolResult<Vec<Content>> {
+        let token = ProgressToken::String(request_id.to_string());
+        self.dispatch(tool_call, Some(token)).await
+    }
+
+    /// The progress extensions report for the tool requests being dispatched, as it arrives
+    pub fn tool_progress(&self) -> BoxStream<'static, ToolProgress> {
+        let receivers = self.notifications.values().map(|receiver| {
+            stream::unfold(receiver.resubscribe(), |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(notification) => return Some((notification, receiver)),
+                        // Progress is only informative, so missed reports can be skipped
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            })
+            .boxed()
+        });
+
+        stream::select_all(receivers)
+            .filter_map(|notification| async move {
+                if notification.method != "notifications/progress" {
+                    return None;
+                }
+                let params: ProgressNotificationParams =
+                    serde_json::from_value(notification.params?).ok()?;
+                let ProgressToken::String(id) = params.progress_token else {
+                    return None;
+                };
+                Some(ToolProgress {
+                    id,
+                    progress: params.progress,
+                    total: params.total,
+                    message: params.message,
+                })
+            })
+            .boxed()
+    }
+
+    #[instrument(skip(self, tool_call, progress_token), fields(input, output))]
+    async fn dispatch(
+        &self,
+        tool_call: ToolCall,
+        progress_token: Option<ProgressToken>,
+    ) -> ToolResult<Vec<Content>> {
+        let result = if self.tool_approval(&tool_call) == ToolApproval::Deny {
+            Err(ToolError::ExecutionError(format!(
+                "The tool call {} is denied by the tool approval policy",
+                tool_call.name
+            )))
+        } else if tool_call.name == "platform__read_resource" {
             // Check if the tool is read_resource and handle it separately
             self.read_resource(tool_call.arguments.clone()).await
         } else if tool_call.name == "platform__list_resources" {
             self.list_resources(tool_call.arguments.clone()).await
         } else {
-            // Else, dispatch tool call based on the prefix naming convention
-            let (client_name, client) = self
+            // Else, dispatch tool call to the extension serving the offered name
+            let (route, client) = self
                 .get_client_for_tool(&tool_call.name)
                 .ok_or_else(|| ToolError::NotFound(tool_call.name.clone()))?;
 
-            // rsplit returns the iterator in reverse, tool_name is then at 0
-            let tool_name = tool_call
-                .name
-                .strip_prefix(client_name)
-                .and_then(|s| s.strip_prefix("__"))
-                .ok_or_else(|| ToolError::NotFound(tool_call.name.clone()))?;
-
-            let client_guard = client.lock().await;
-
-            client_guard
-                .call_tool(tool_name, tool_call.clone().arguments)
-                .await
-                .map(|result| result.content)
-                .map_err(|e| ToolError::ExecutionError(e.to_string()))
+            // Calls wait for the extension's sequential calls requested before them, then for
+            // a free slot. Locks are fair, so calls start in the order they were requested
+            let turn = self.extension_turn(&route.extension);
+            let _turn = if route.parallel {
+                Either::Left(turn.read_owned().await)
+            } else {
+                Either::Right(turn.write_owned().await)
+            };
+            let _slot = match &self.tool_slots {
+                Some(slots) => slots.acquire().await.ok(),
+                None => None,
+            };
+
+            let arguments = tool_call.arguments.clone();
+            let call = async {
+                match progress_token {
+                    Some(token) => {
+                        client
+                            .call_tool_with_progress(&route.tool, arguments, token)
+                            .await
+                    }
+                    None => client.call_tool(&route.tool, arguments).await,
+                }
+            };
+            // Dropping a call that timed out cancels it on the server
+            let timeout = self
+                .tool_execution
+                .timeout(&tool_call.name, &route.extension);
+            match tokio::time::timeout(timeout, call).await {
+                Ok(result) => result
+                    .map(|result| result.content)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string())),
+                Err(_) => Err(ToolError::ExecutionError(format!(
+                    "The tool call {} timed out after {} seconds",
+                    tool_call.name,
+                    timeout.as_secs()
+                ))),
+            }
+        };
+        let result = match result {
+            Ok(contents) => Ok(self.tool_output.limit(&tool_call.name, contents).await),
+            Err(e) => Err(e),
         };
 
         debug!(
@@ -518,11 +1310,15 @@ mod tests {
     use crate::model::ModelConfig;
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
     use crate::providers::errors::ProviderError;
+    use crate::providers::pricing::ModelPricing;
     use mcp_client::client::Error;
     use mcp_client::client::McpClientTrait;
+    use mcp_core::prompt::{PromptArgument, PromptMessage, PromptMessageRole};
     use mcp_core::protocol::{
-        CallToolResult, InitializeResult, ListResourcesResult, ListToolsResult, ReadResourceResult,
+        CallToolResult, InitializeResult, ListPromptsResult, ListResourcesResult, ListToolsResult,
+        ReadResourceResult, INVALID_PARAMS, METHOD_NOT_FOUND,
     };
+    use mcp_core::ToolAnnotations;
     use serde_json::json;
 
     // Mock Provider implementation for testing
@@ -554,7 +1350,15 @@ mod tests {
         }
     }
 
-    struct MockClient {}
+    #[derive(Default)]
+    struct MockClient {
+        tool_lists: Arc<AtomicUsize>,
+        /// Whether the tool is annotated as parallel, and how long its calls take
+        parallel: bool,
+        delay: Duration,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
 
     #[async_trait::async_trait]
     impl McpClientTrait for MockClient {
@@ -578,10 +1382,21 @@ mod tests {
         }
 
         async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
-            Err(Error::NotInitialized)
+            self.tool_lists.fetch_add(1, Ordering::SeqCst);
+            let tool = Tool::new("tool", "A tool", json!({})).with_annotations(ToolAnnotations {
+                parallel_hint: Some(self.parallel),
+            });
+            Ok(ListToolsResult {
+                tools: vec![tool],
+                next_cursor: None,
+            })
         }
 
         async fn call_tool(&self, name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
             match name {
                 "tool" | "test__tool" => Ok(CallToolResult {
                     content: vec![],
@@ -590,6 +1405,72 @@ mod tests {
                 _ => Err(Error::NotInitialized),
             }
         }
+
+        async fn list_prompts(
+            &self,
+            next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            // The prompts come in two pages
+            if next_cursor.is_some() {
+                return Ok(ListPromptsResult {
+                    prompts: vec![Prompt::new("summarize", "Summarize the changes", vec![])],
+                    next_cursor: None,
+                });
+            }
+            Ok(ListPromptsResult {
+                prompts: vec![Prompt::new(
+                    "review",
+                    "Review the changes",
+                    vec![PromptArgument {
+                        name: "focus".to_string(),
+                        description: "What to focus on".to_string(),
+                        required: true,
+                    }],
+                )],
+                next_cursor: Some("2".to_string()),
+            })
+        }
+
+        async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error> {
+            match name {
+                "review" => Ok(GetPromptResult {
+                    description: None,
+                    messages: vec![PromptMessage::new_text(
+                        PromptMessageRole::User,
+                        format!(
+                            "Review the changes, focusing on {}",
+                            arguments["focus"].as_str().unwrap_or_default()
+                        ),
+                    )],
+                }),
+                _ => Err(Error::RpcError {
+                    code: INVALID_PARAMS,
+                    message: format!("Prompt '{}' not found", name),
+                }),
+            }
+        }
+
+        async fn subscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn unsubscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+            Err(Error::NotInitialized)
+        }
+
+        fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+            broadcast::channel(1).1
+        }
+
+        async fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+            match method {
+                "prompts/list" => Ok(json!({"prompts": [], "params": params})),
+                _ => Err(Error::RpcError {
+                    code: METHOD_NOT_FOUND,
+                    message: format!("Method not found: {}", method),
+                }),
+            }
+        }
     }
 
     #[test]
@@ -604,22 +1485,22 @@ mod tests {
         // Add some mock clients
         capabilities.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         capabilities.clients.insert(
             normalize("__client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         capabilities.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         capabilities.clients.insert(
             normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         // Test basic case
@@ -639,6 +1520,65 @@ mod tests {
         assert!(capabilities.get_client_for_tool("client___tool").is_some());
     }
 
+    #[tokio::test]
+    async fn test_tool_filters_and_collisions() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        let mut add = |name: &str, filter: ToolFilter| {
+            capabilities
+                .clients
+                .insert(name.to_string(), Arc::new(MockClient::default()));
+            capabilities.extensions.insert(
+                name.to_string(),
+                ExtensionHealth::new(ExtensionConfig::stdio(name, "true").with_tool_filter(filter)),
+            );
+        };
+        add("dev", ToolFilter::default());
+        add("dev_tools", ToolFilter::default());
+        add(
+            "other",
+            ToolFilter {
+                aliases: HashMap::from([("tool".to_string(), "dev__tool".to_string())]),
+                ..Default::default()
+            },
+        );
+        add(
+            "hidden",
+            ToolFilter {
+                deny: vec!["tool".to_string()],
+                ..Default::default()
+            },
+        );
+
+        // One extension name being a prefix of another doesn't confuse the routing
+        let (route, _) = capabilities.get_client_for_tool("dev_tools__tool").unwrap();
+        assert_eq!(
+            (route.extension.as_str(), route.tool.as_str()),
+            ("dev_tools", "tool")
+        );
+
+        // The alias collides with a tool of an extension listed earlier, so it is skipped
+        let tools = capabilities.get_prefixed_tools().await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, vec!["dev__tool", "dev_tools__tool"]);
+        assert!(matches!(
+            capabilities.check_tool_collisions("other").await,
+            Err(ExtensionError::ToolCollision { existing, .. }) if existing == "dev"
+        ));
+        // Nor can the skipped tool be called by its prefixed name
+        assert!(capabilities.get_client_for_tool("other__tool").is_none());
+
+        // Denied tools can't be called even by name
+        let tool_call = ToolCall {
+            name: "hidden__tool".to_string(),
+            arguments: json!({}),
+        };
+        let result = capabilities.dispatch_tool_call(tool_call).await;
+        assert!(matches!(result.err().unwrap(), ToolError::NotFound(_)));
+    }
+
     #[tokio::test]
     async fn test_dispatch_tool_call() {
         // test that dispatch_tool_call parses out the sanitized name correctly, and extracts
@@ -653,17 +1593,17 @@ mod tests {
         // Add some mock clients
         capabilities.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         capabilities.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         capabilities.clients.insert(
             normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient::default()),
         );
 
         // verify a normal tool call
@@ -731,4 +1671,422 @@ mod tests {
         let result = capabilities.dispatch_tool_call(invalid_tool_call).await;
         assert!(matches!(result.err().unwrap(), ToolError::NotFound(_)));
     }
+
+    #[tokio::test]
+    async fn test_dispatch_denied_tool_call() {
+        let mock_model_config =
+            ModelConfig::new("test-model".to_string()).with_context_limit(200_000.into());
+
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient::default()),
+        );
+        capabilities.set_approval_policy(
+            serde_json::from_value(json!({
+                "mode": "rules",
+                "allow": ["test_client__*"],
+                "deny": ["*__shell"]
+            }))
+            .unwrap(),
+        );
+
+        let tool_call = ToolCall {
+            name: "test_client__tool".to_string(),
+            arguments: json!({}),
+        };
+        assert_eq!(capabilities.tool_approval(&tool_call), ToolApproval::Allow);
+        assert!(capabilities.dispatch_tool_call(tool_call).await.is_ok());
+
+        // Denied calls never reach the client
+        let tool_call = ToolCall {
+            name: "test_client__shell".to_string(),
+            arguments: json!({}),
+        };
+        assert_eq!(capabilities.tool_approval(&tool_call), ToolApproval::Deny);
+        let result = capabilities.dispatch_tool_call(tool_call).await;
+        assert!(matches!(result, Err(ToolError::ExecutionError(e)) if e.contains("denied")));
+    }
+
+    #[tokio::test]
+    async fn test_tool_approval_under_alias() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        let filter = ToolFilter {
+            aliases: HashMap::from([("tool".to_string(), "edit".to_string())]),
+            ..Default::default()
+        };
+        capabilities
+            .clients
+            .insert("dev".to_string(), Arc::new(MockClient::default()));
+        capabilities.extensions.insert(
+            "dev".to_string(),
+            ExtensionHealth::new(ExtensionConfig::stdio("dev", "true").with_tool_filter(filter)),
+        );
+        capabilities.set_approval_policy(
+            serde_json::from_value(json!({
+                "mode": "rules",
+                "allow": ["edit"],
+                "deny": ["dev__*"]
+            }))
+            .unwrap(),
+        );
+        let names: Vec<String> = capabilities
+            .get_prefixed_tools()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, vec!["edit"]);
+
+        // The rule for the extension applies under the alias too
+        let tool_call = ToolCall {
+            name: "edit".to_string(),
+            arguments: json!({}),
+        };
+        assert_eq!(capabilities.tool_approval(&tool_call), ToolApproval::Deny);
+        let result = capabilities.dispatch_tool_call(tool_call).await;
+        assert!(matches!(result, Err(ToolError::ExecutionError(e)) if e.contains("denied")));
+
+        // Aliases have to be valid tool names
+        let filter = ToolFilter {
+            aliases: HashMap::from([("tool".to_string(), "edit file".to_string())]),
+            ..Default::default()
+        };
+        let result = capabilities
+            .add_extension(ExtensionConfig::stdio("bad", "true").with_tool_filter(filter))
+            .await;
+        assert!(
+            matches!(result, Err(ExtensionError::InvalidRequest(e)) if e.contains("edit file"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_includes_cost() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.pricing = Pricing::new(HashMap::from([(
+            "gpt-4o".to_string(),
+            ModelPricing::new(2.0, 10.0),
+        )]));
+
+        for _ in 0..2 {
+            capabilities
+                .record_usage(ProviderUsage::new(
+                    "gpt-4o".to_string(),
+                    Usage::new(Some(500_000), Some(100_000), Some(600_000)),
+                ))
+                .await;
+        }
+        capabilities
+            .record_usage(ProviderUsage::new(
+                "llama3.2".to_string(),
+                Usage::new(Some(1000), Some(1000), Some(2000)),
+            ))
+            .await;
+
+        let usage = capabilities.get_usage().await;
+        let priced = usage.iter().find(|u| u.model == "gpt-4o").unwrap();
+        assert_eq!(priced.usage.input_tokens, Some(1_000_000));
+        assert!((priced.cost.unwrap() - 4.0).abs() < 1e-9);
+        let unpriced = usage.iter().find(|u| u.model == "llama3.2").unwrap();
+        assert_eq!(unpriced.cost, None);
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        assert_eq!(restart_backoff(0), Duration::ZERO);
+        assert_eq!(restart_backoff(1), Duration::from_secs(1));
+        assert_eq!(restart_backoff(3), Duration::from_secs(4));
+        assert_eq!(restart_backoff(10), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_check_extensions_restarts_stopped_extension() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+
+        // A process that crashes right away, and keeps crashing when restarted
+        let config = ExtensionConfig::stdio("crashing", "sh")
+            .with_args(["-c", "echo starting >&2; echo boom >&2; exit 1"]);
+        let ExtensionConfig::Stdio { cmd, args, .. } = &config else {
+            unreachable!()
+        };
+        let handle = StdioTransport::new(cmd, args.clone(), HashMap::new())
+            .start()
+            .await
+            .unwrap();
+        let mut health = ExtensionHealth::new(config.clone());
+        health.status.state = ExtensionState::Ready;
+        health.handle = Some(handle.clone());
+        capabilities
+            .extensions
+            .insert("crashing".to_string(), health);
+
+        while !handle.is_closed() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        capabilities.check_extensions().await;
+
+        let status = &capabilities.list_extensions().await.unwrap()[0];
+        assert_eq!(status.name, "crashing");
+        assert_eq!(status.stderr_tail, vec!["starting", "boom"]);
+        // The first restart is immediate, and fails to initialize
+        assert_eq!(status.restarts, 1);
+        assert_eq!(status.state, ExtensionState::Failed);
+        assert!(status
+            .last_error
+            .as_deref()
+            .is_some_and(|e| e.contains("Failed to start")));
+
+        // The next restart waits for the backoff
+        capabilities.check_extensions().await;
+        let status = &capabilities.list_extensions().await.unwrap()[0];
+        assert_eq!(status.restarts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_extensions_forgives_restarts() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+
+        let mut health = ExtensionHealth::new(ExtensionConfig::stdio("flaky", "true"));
+        health.status.state = ExtensionState::Ready;
+        health.status.restarts = 3;
+        health.ready_since = Some(Instant::now());
+        capabilities.extensions.insert("flaky".to_string(), health);
+
+        // Restarts are kept until the extension has been running for a while
+        capabilities.check_extensions().await;
+        assert_eq!(capabilities.list_extensions().await.unwrap()[0].restarts, 3);
+
+        let health = capabilities.extensions.get_mut("flaky").unwrap();
+        health.ready_since = Instant::now().checked_sub(HEALTHY_RESET);
+        capabilities.check_extensions().await;
+        assert_eq!(capabilities.list_extensions().await.unwrap()[0].restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_prompts() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient::default()),
+        );
+
+        let prompts = capabilities.list_prompts().await;
+        let names: Vec<_> = prompts["test_client"].iter().map(|p| &p.name).collect();
+        assert_eq!(names, vec!["review", "summarize"]);
+
+        let result = capabilities
+            .get_prompt("test_client", "review", json!({"focus": "tests"}))
+            .await
+            .unwrap();
+        let messages: Vec<Message> = result.messages.into_iter().map(Message::from).collect();
+        assert_eq!(messages[0].role, mcp_core::role::Role::User);
+        assert_eq!(
+            messages[0].as_concat_text(),
+            "Review the changes, focusing on tests"
+        );
+
+        let result = capabilities
+            .get_prompt("missing", "review", json!({}))
+            .await;
+        assert!(matches!(result, Err(ExtensionError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_passthrough() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient::default()),
+        );
+
+        let result = capabilities
+            .passthrough(
+                "test_client",
+                json!({"method": "prompts/list", "params": {"cursor": "a"}}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result, json!({"prompts": [], "params": {"cursor": "a"}}));
+
+        // Errors from the extension are returned as they are
+        let result = capabilities
+            .passthrough("test_client", json!({"method": "prompts/get"}))
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExtensionError::Client(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                ..
+            }))
+        ));
+
+        let result = capabilities
+            .passthrough("missing", json!({"method": "prompts/list"}))
+            .await;
+        assert!(matches!(result, Err(ExtensionError::NotFound(_))));
+
+        let result = capabilities.passthrough("test_client", json!({})).await;
+        assert!(matches!(result, Err(ExtensionError::InvalidRequest(_))));
+
+        // Tool calls can't skip the approval policy, and sampling is only for extensions
+        for method in ["tools/call", "sampling/createMessage"] {
+            let result = capabilities
+                .passthrough("test_client", json!({"method": method}))
+                .await;
+            assert!(matches!(result, Err(ExtensionError::InvalidRequest(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.set_limits(AgentLimits {
+            max_turns: Some(2),
+            max_output_tokens: Some(100),
+            max_tool_calls: Some(2),
+            ..Default::default()
+        });
+
+        assert_eq!(capabilities.check_limits(1).await, Ok(()));
+        assert_eq!(
+            capabilities.check_limits(2).await,
+            Err(LimitExceeded::Turns(2))
+        );
+
+        capabilities
+            .record_usage(ProviderUsage::new(
+                "test-model".to_string(),
+                Usage::new(Some(10), Some(100), Some(110)),
+            ))
+            .await;
+        assert_eq!(
+            capabilities.check_limits(0).await,
+            Err(LimitExceeded::OutputTokens(100))
+        );
+
+        assert_eq!(capabilities.reserve_tool_call(), Ok(()));
+        assert_eq!(capabilities.reserve_tool_call(), Ok(()));
+        assert_eq!(
+            capabilities.reserve_tool_call(),
+            Err(LimitExceeded::ToolCalls(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tools_cached_until_list_changed() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        let client = MockClient::default();
+        let tool_lists = Arc::clone(&client.tool_lists);
+        let (notifications, receiver) = broadcast::channel(4);
+        capabilities
+            .clients
+            .insert("test_client".to_string(), Arc::new(client));
+        capabilities
+            .notifications
+            .insert("test_client".to_string(), receiver);
+
+        let notification = |method: &str| JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: None,
+        };
+
+        let tools = capabilities.get_prefixed_tools().await.unwrap();
+        assert_eq!(tools[0].name, "test_client__tool");
+        capabilities.get_prefixed_tools().await.unwrap();
+        assert_eq!(tool_lists.load(Ordering::SeqCst), 1);
+
+        // Other notifications leave the tools cached
+        notifications
+            .send(notification("notifications/resources/list_changed"))
+            .unwrap();
+        capabilities.get_prefixed_tools().await.unwrap();
+        assert_eq!(tool_lists.load(Ordering::SeqCst), 1);
+
+        notifications
+            .send(notification("notifications/tools/list_changed"))
+            .unwrap();
+        capabilities.get_prefixed_tools().await.unwrap();
+        assert_eq!(tool_lists.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_scheduled_by_annotation() {
+        let mock_model_config = ModelConfig::new("test-model".to_string());
+        let mut capabilities = Capabilities::new(Box::new(MockProvider {
+            model_config: mock_model_config,
+        }));
+        capabilities.set_tool_execution(ToolExecutionPolicy::default());
+        let mut max_in_flight = HashMap::new();
+        for (name, parallel) in [("sequential", false), ("parallel", true)] {
+            let client = MockClient {
+                parallel,
+                delay: Duration::from_millis(50),
+                ..Default::default()
+            };
+            max_in_flight.insert(name, Arc::clone(&client.max_in_flight));
+            capabilities
+                .clients
+                .insert(name.to_string(), Arc::new(client));
+        }
+        capabilities.get_prefixed_tools().await.unwrap();
+
+        let calls = |name: &str| {
+            let tool_call = ToolCall::new(format!("{}__tool", name), json!({}));
+            let capabilities = &capabilities;
+            futures::future::join_all(
+                (0..3).map(move |_| capabilities.dispatch_tool_call(tool_call.clone())),
+            )
+        };
+        let (sequential, parallel) = tokio::join!(calls("sequential"), calls("parallel"));
+        assert!(sequential.iter().chain(&parallel).all(Result::is_ok));
+        assert_eq!(max_in_flight["sequential"].load(Ordering::SeqCst), 1);
+        assert_eq!(max_in_flight["parallel"].load(Ordering::SeqCst), 3);
+
+        // Calls that run too long are stopped
+        capabilities.set_tool_execution(ToolExecutionPolicy {
+            timeouts: HashMap::from([("parallel".to_string(), 0)]),
+            ..Default::default()
+        });
+        let result = capabilities
+            .dispatch_tool_call(ToolCall::new("parallel__tool", json!({})))
+            .await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+
+        // A concurrency of 0 means no limit rather than no calls at all
+        capabilities.set_tool_execution(ToolExecutionPolicy {
+            max_concurrency: Some(0),
+            ..Default::default()
+        });
+        let call = capabilities.dispatch_tool_call(ToolCall::new("parallel__tool", json!({})));
+        let result = tokio::time::timeout(Duration::from_secs(5), call).await;
+        assert!(result.expect("the call should not wait for a slot").is_ok());
+    }
 }
@@ -8,13 +8,25 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum ExtensionError {
     #[error("Failed to start the MCP server from configuration `{0}` `{1}`")]
-    Initialization(ExtensionConfig, ClientError),
+    Initialization(Box<ExtensionConfig>, ClientError),
     #[error("Failed a client call to an MCP server: {0}")]
     Client(#[from] ClientError),
     #[error("User Message exceeded context-limit. History could not be truncated to accomodate.")]
     ContextLimit,
     #[error("Transport error: {0}")]
     Transport(#[from] mcp_client::transport::Error),
+    #[error("No extension named `{0}` is running")]
+    NotFound(String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("The extension `{0}` did not start within {1} seconds")]
+    StartupTimeout(String, u64),
+    #[error("The tool `{tool}` of extension `{extension}` is already offered by `{existing}`, so it needs an alias")]
+    ToolCollision {
+        tool: String,
+        extension: String,
+        existing: String,
+    },
 }
 
 pub type ExtensionResult<T> = Result<T, ExtensionError>;
@@ -40,6 +52,72 @@ impl Envs {
     }
 }
 
+/// Which of an extension's tools are offered to the model, and under which names
+///
+/// Tools are named as the extension names them. Offered tools are named
+/// `{extension}__{tool}` unless they have an alias, which is used as the whole name and
+/// so must also only contain letters, digits, `_` and `-`.
+///
+/// ```yaml
+/// tools:
+///   deny: [shell]
+///   aliases:
+///     text_editor: edit_file
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ToolFilter {
+    /// Only these tools are offered when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+    /// These tools are never offered, even when allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+}
+
+impl ToolFilter {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Whether a tool may be offered to the model and called
+    pub fn allows(&self, tool: &str) -> bool {
+        self.allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(|t| t == tool))
+            && !self.deny.iter().any(|t| t == tool)
+    }
+
+    /// Check that every alias is a name models accept for a tool
+    pub fn validate(&self) -> Result<(), String> {
+        for alias in self.aliases.values() {
+            let valid = (1..=MAX_TOOL_NAME_LEN).contains(&alias.len())
+                && alias
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(format!(
+                    "The tool alias `{}` must be 1 to {} letters, digits, `_` or `-`",
+                    alias, MAX_TOOL_NAME_LEN
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The name a tool is offered under
+    pub fn offered_name(&self, extension: &str, tool: &str) -> String {
+        self.aliases
+            .get(tool)
+            .cloned()
+            .unwrap_or_else(|| format!("{}__{}", extension, tool))
+    }
+}
+
+/// The longest tool name providers accept
+const MAX_TOOL_NAME_LEN: usize = 64;
+
 /// Represents the different types of MCP extensions that can be added to the manager
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type")]
@@ -52,6 +130,23 @@ pub enum ExtensionConfig {
         uri: String,
         #[serde(default)]
         envs: Envs,
+        /// Headers sent with every request, e.g. Authorization -> Bearer some_token
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        headers: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "ToolFilter::is_empty")]
+        tools: ToolFilter,
+    },
+    /// Streamable HTTP client posting to a single endpoint
+    #[serde(rename = "streamable_http")]
+    StreamableHttp {
+        /// The name used to identify this extension
+        name: String,
+        uri: String,
+        /// Headers sent with every request, e.g. Authorization -> Bearer some_token
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "ToolFilter::is_empty")]
+        tools: ToolFilter,
     },
     /// Standard I/O client with command and arguments
     #[serde(rename = "stdio")]
@@ -62,12 +157,16 @@ pub enum ExtensionConfig {
         args: Vec<String>,
         #[serde(default)]
         envs: Envs,
+        #[serde(default, skip_serializing_if = "ToolFilter::is_empty")]
+        tools: ToolFilter,
     },
     /// Built-in extension that is part of the goose binary
     #[serde(rename = "builtin")]
     Builtin {
         /// The name used to identify this extension
         name: String,
+        #[serde(default, skip_serializing_if = "ToolFilter::is_empty")]
+        tools: ToolFilter,
     },
 }
 
@@ -75,6 +174,7 @@ impl Default for ExtensionConfig {
     fn default() -> Self {
         Self::Builtin {
             name: String::from("default"),
+            tools: ToolFilter::default(),
         }
     }
 }
@@ -85,6 +185,17 @@ impl ExtensionConfig {
             name: name.into(),
             uri: uri.into(),
             envs: Envs::default(),
+            headers: HashMap::new(),
+            tools: ToolFilter::default(),
+        }
+    }
+
+    pub fn streamable_http<S: Into<String>>(name: S, uri: S) -> Self {
+        Self::StreamableHttp {
+            name: name.into(),
+            uri: uri.into(),
+            headers: HashMap::new(),
+            tools: ToolFilter::default(),
         }
     }
 
@@ -94,6 +205,7 @@ impl ExtensionConfig {
             cmd: cmd.into(),
             args: vec![],
             envs: Envs::default(),
+            tools: ToolFilter::default(),
         }
     }
 
@@ -104,11 +216,16 @@ impl ExtensionConfig {
     {
         match self {
             Self::Stdio {
-                name, cmd, envs, ..
+                name,
+                cmd,
+                envs,
+                tools,
+                ..
             } => Self::Stdio {
                 name,
                 cmd,
                 envs,
+                tools,
                 args: args.into_iter().map(Into::into).collect(),
             },
             other => other,
@@ -119,8 +236,29 @@ impl ExtensionConfig {
     pub fn name(&self) -> &str {
         match self {
             Self::Sse { name, .. } => name,
+            Self::StreamableHttp { name, .. } => name,
             Self::Stdio { name, .. } => name,
-            Self::Builtin { name } => name,
+            Self::Builtin { name, .. } => name,
+        }
+    }
+
+    pub fn with_tool_filter(mut self, filter: ToolFilter) -> Self {
+        match &mut self {
+            Self::Sse { tools, .. }
+            | Self::StreamableHttp { tools, .. }
+            | Self::Stdio { tools, .. }
+            | Self::Builtin { tools, .. } => *tools = filter,
+        }
+        self
+    }
+
+    /// Get the filter for the extension's tools regardless of variant
+    pub fn tool_filter(&self) -> &ToolFilter {
+        match self {
+            Self::Sse { tools, .. } => tools,
+            Self::StreamableHttp { tools, .. } => tools,
+            Self::Stdio { tools, .. } => tools,
+            Self::Builtin { tools, .. } => tools,
         }
     }
 }
@@ -129,12 +267,65 @@ impl std::fmt::Display for ExtensionConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExtensionConfig::Sse { name, uri, .. } => write!(f, "SSE({}: {})", name, uri),
+            ExtensionConfig::StreamableHttp { name, uri, .. } => {
+                write!(f, "StreamableHttp({}: {})", name, uri)
+            }
             ExtensionConfig::Stdio {
                 name, cmd, args, ..
             } => {
                 write!(f, "Stdio({}: {} {})", name, cmd, args.join(" "))
             }
-            ExtensionConfig::Builtin { name } => write!(f, "Builtin({})", name),
+            ExtensionConfig::Builtin { name, .. } => write!(f, "Builtin({})", name),
+        }
+    }
+}
+
+/// The lifecycle state of a running extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionState {
+    Starting,
+    Ready,
+    /// The extension stopped, or could not be restarted after it stopped
+    Failed,
+    Restarting,
+}
+
+impl std::fmt::Display for ExtensionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionState::Starting => write!(f, "starting"),
+            ExtensionState::Ready => write!(f, "ready"),
+            ExtensionState::Failed => write!(f, "failed"),
+            ExtensionState::Restarting => write!(f, "restarting"),
+        }
+    }
+}
+
+/// The health of an extension, as reported when listing extensions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionStatus {
+    pub name: String,
+    pub state: ExtensionState,
+    /// Why the extension last stopped or failed to start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// The last lines a stdio extension wrote to stderr before it stopped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stderr_tail: Vec<String>,
+    /// How many times the extension was restarted after stopping
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+impl ExtensionStatus {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            state: ExtensionState::Starting,
+            last_error: None,
+            stderr_tail: Vec::new(),
+            restarts: 0,
         }
     }
 }
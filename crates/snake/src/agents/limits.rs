@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::Config;
+use crate::providers::base::ProviderUsage;
+
+/// The config key holding the limits on how much work the agent does
+pub const LIMITS_CONFIG_KEY: &str = "GOOSE_AGENT_LIMITS";
+
+/// Limits that stop the agent before a runaway tool loop burns through the budget
+///
+/// Turns are counted per reply, everything else across the whole session. Every limit
+/// is off unless configured.
+///
+/// ```yaml
+/// GOOSE_AGENT_LIMITS:
+///   max_turns: 25
+///   max_input_tokens: 2000000
+///   max_output_tokens: 200000
+///   max_tool_calls: 100
+///   max_cost: 5.0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentLimits {
+    /// Completions requested from the provider while answering one message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_input_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<usize>,
+    /// Spend in USD, counting only requests to models with a known price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+/// The limit that stopped the agent
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LimitExceeded {
+    #[error("Reached the limit of {0} turns for a single reply")]
+    Turns(usize),
+    #[error("Reached the session limit of {0} input tokens")]
+    InputTokens(u64),
+    #[error("Reached the session limit of {0} output tokens")]
+    OutputTokens(u64),
+    #[error("Reached the session limit of {0} tool calls")]
+    ToolCalls(usize),
+    #[error("Reached the session spending limit of ${0:.2}")]
+    Cost(f64),
+}
+
+impl LimitExceeded {
+    /// The assistant message explaining why the agent stopped
+    pub fn message(&self) -> String {
+        format!(
+            "{}, so I stopped here. The limits can be changed with {}.",
+            self, LIMITS_CONFIG_KEY
+        )
+    }
+}
+
+impl AgentLimits {
+    /// Load the limits from the config, falling back to no limits
+    pub fn from_config(config: &Config) -> Self {
+        config.get(LIMITS_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// Check whether the agent may request another completion, given the turns taken in
+    /// this reply and the usage recorded so far in the session
+    pub fn check(&self, turns: usize, usage: &[ProviderUsage]) -> Result<(), LimitExceeded> {
+        if let Some(max) = self.max_turns {
+            if turns >= max {
+                return Err(LimitExceeded::Turns(max));
+            }
+        }
+
+        if let Some(max) = self.max_input_tokens {
+            let input: u64 = usage
+                .iter()
+                .map(|u| u.usage.input_tokens.unwrap_or(0).max(0) as u64)
+                .sum();
+            if input >= max {
+                return Err(LimitExceeded::InputTokens(max));
+            }
+        }
+
+        if let Some(max) = self.max_output_tokens {
+            let output: u64 = usage
+                .iter()
+                .map(|u| u.usage.output_tokens.unwrap_or(0).max(0) as u64)
+                .sum();
+            if output >= max {
+                return Err(LimitExceeded::OutputTokens(max));
+            }
+        }
+
+        if let Some(max) = self.max_cost {
+            let cost: f64 = usage.iter().filter_map(|u| u.cost).sum();
+            if cost >= max {
+                return Err(LimitExceeded::Cost(max));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use serde_json::json;
+
+    fn usage(input: i32, output: i32, cost: Option<f64>) -> ProviderUsage {
+        let mut usage = ProviderUsage::new(
+            "model".to_string(),
+            Usage::new(Some(input), Some(output), Some(input + output)),
+        );
+        usage.cost = cost;
+        usage
+    }
+
+    #[test]
+    fn test_no_limits_by_default() {
+        let limits = AgentLimits::default();
+        let usage = vec![usage(1_000_000_000, 1_000_000_000, Some(1000.0))];
+        assert_eq!(limits.check(usize::MAX, &usage), Ok(()));
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let limits: AgentLimits = serde_json::from_value(json!({
+            "max_turns": 3,
+            "max_input_tokens": 1000,
+            "max_output_tokens": 100,
+            "max_cost": 1.0
+        }))
+        .unwrap();
+
+        let usage = vec![usage(400, 40, Some(0.25)), usage(500, 50, None)];
+        assert_eq!(limits.check(2, &usage), Ok(()));
+        assert_eq!(limits.check(3, &usage), Err(LimitExceeded::Turns(3)));
+
+        let mut more = usage.clone();
+        more.push(self::usage(100, 0, None));
+        assert_eq!(
+            limits.check(0, &more),
+            Err(LimitExceeded::InputTokens(1000))
+        );
+
+        let mut more = usage.clone();
+        more.push(self::usage(0, 10, None));
+        assert_eq!(
+            limits.check(0, &more),
+            Err(LimitExceeded::OutputTokens(100))
+        );
+
+        let mut more = usage;
+        more.push(self::usage(0, 0, Some(0.75)));
+        assert_eq!(limits.check(0, &more), Err(LimitExceeded::Cost(1.0)));
+    }
+}
@@ -1,11 +1,23 @@
 mod agent;
+pub mod approval;
 mod capabilities;
 pub mod extension;
 mod factory;
+pub mod limits;
 mod reference;
+mod reply;
+pub mod sampling;
+mod summarize;
+pub mod tool_execution;
+pub mod tool_output;
 mod truncate;
 
-pub use agent::Agent;
+pub use agent::{Agent, AgentEvent, ToolProgress};
+pub use approval::{ToolApprovalPolicy, ToolConfirmationRequest, ToolConfirmations};
 pub use capabilities::Capabilities;
-pub use extension::ExtensionConfig;
+pub use extension::{ExtensionConfig, ExtensionState, ExtensionStatus, ToolFilter};
 pub use factory::{register_agent, AgentFactory};
+pub use limits::{AgentLimits, LimitExceeded};
+pub use sampling::{SamplingApprover, SamplingPolicy};
+pub use tool_execution::ToolExecutionPolicy;
+pub use tool_output::ToolOutputPolicy;
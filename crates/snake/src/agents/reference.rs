@@ -2,24 +2,33 @@
 /// It makes no attempt to handle context limits, and cannot read resources
 use async_trait::async_trait;
 use futures::stream::BoxStream;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
-use super::Agent;
+use super::{Agent, AgentEvent};
+use crate::agents::approval::{ToolApprovalPolicy, ToolConfirmations};
 use crate::agents::capabilities::Capabilities;
-use crate::agents::extension::{ExtensionConfig, ExtensionResult};
+use crate::agents::extension::{ExtensionConfig, ExtensionResult, ExtensionStatus};
+use crate::agents::reply::{self, Step};
+use crate::agents::sampling::SamplingApprover;
 use crate::message::{Message, ToolRequest};
 use crate::providers::base::Provider;
 use crate::providers::base::ProviderUsage;
 use crate::register_agent;
 use crate::token_counter::TokenCounter;
 use indoc::indoc;
+use mcp_core::prompt::Prompt;
+use mcp_core::protocol::GetPromptResult;
 use mcp_core::tool::Tool;
 use serde_json::{json, Value};
 
 /// Reference implementation of an Agent
 pub struct ReferenceAgent {
     capabilities: Mutex<Capabilities>,
+    confirmations: ToolConfirmations,
     _token_counter: TokenCounter,
 }
 
@@ -28,6 +37,7 @@ impl ReferenceAgent {
         let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
         Self {
             capabilities: Mutex::new(Capabilities::new(provider)),
+            confirmations: ToolConfirmations::default(),
             _token_counter: token_counter,
         }
     }
@@ -35,6 +45,12 @@ impl ReferenceAgent {
 
 #[async_trait]
 impl Agent for ReferenceAgent {
+    async fn handle_confirmation(&self, request_id: &str, confirmed: bool) {
+        if !self.confirmations.confirm(request_id, confirmed) {
+            warn!("No tool call is waiting for confirmation {}", request_id);
+        }
+    }
+
     async fn add_extension(&mut self, extension: ExtensionConfig) -> ExtensionResult<()> {
         let mut capabilities = self.capabilities.lock().await;
         capabilities.add_extension(extension).await
@@ -48,30 +64,57 @@ impl Agent for ReferenceAgent {
             .expect("Failed to remove extension");
     }
 
-    async fn list_extensions(&self) -> Vec<String> {
-        let capabilities = self.capabilities.lock().await;
+    async fn list_extensions(&self) -> Vec<ExtensionStatus> {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.check_extensions().await;
         capabilities
             .list_extensions()
             .await
             .expect("Failed to list extensions")
     }
 
-    async fn passthrough(&self, _extension: &str, _request: Value) -> ExtensionResult<Value> {
-        // TODO implement
-        Ok(Value::Null)
+    async fn list_prompts(&self) -> HashMap<String, Vec<Prompt>> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.list_prompts().await
+    }
+
+    async fn get_prompt(
+        &self,
+        extension: &str,
+        name: &str,
+        arguments: Value,
+    ) -> ExtensionResult<GetPromptResult> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.get_prompt(extension, name, arguments).await
+    }
+
+    async fn set_sampling_approver(&self, approver: Arc<dyn SamplingApprover>) {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.set_sampling_approver(approver);
+    }
+
+    async fn set_approval_policy(&self, policy: ToolApprovalPolicy) {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.set_approval_policy(policy);
+    }
+
+    async fn passthrough(&self, extension: &str, request: Value) -> ExtensionResult<Value> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.passthrough(extension, request).await
     }
 
     #[instrument(skip(self, messages), fields(user_message))]
     async fn reply(
         &self,
         messages: &[Message],
-    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<Message>>> {
+    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<AgentEvent>>> {
         let mut messages = messages.to_vec();
         let reply_span = tracing::Span::current();
         let mut capabilities = self.capabilities.lock().await;
+        capabilities.check_extensions().await;
         let mut tools = capabilities.get_prefixed_tools().await?;
         // we add in the read_resource tool by default
-        // TODO: make sure there is no collision with another extension's tool name
+        // Names starting with platform__ are reserved, so extension tools never collide with these
         let read_resource_tool = Tool::new(
             "platform__read_resource".to_string(),
             indoc! {r#"
@@ -128,54 +171,68 @@ impl Agent for ReferenceAgent {
 
         Ok(Box::pin(async_stream::try_stream! {
             let _reply_guard = reply_span.enter();
+            let mut turns: usize = 0;
             loop {
-                // Get completion from provider
-                let (response, usage) = capabilities.provider().complete(
-                    &system_prompt,
-                    &messages,
-                    &tools,
-                ).await?;
+                // Stop before the next completion once a turn, token or spend limit is reached
+                if let Err(e) = capabilities.check_limits(turns).await {
+                    yield AgentEvent::Message(Message::assistant().with_text(e.message()));
+                    Err(e)?;
+                }
+
+                // Get the completion from the provider, passing its deltas through as they arrive
+                let completion = {
+                    let mut steps = reply::complete(&capabilities, &system_prompt, &messages, &tools);
+                    let mut completion = None;
+                    while let Some(step) = steps.next().await {
+                        match step {
+                            Step::Event(event) => yield event,
+                            Step::Done(done) => completion = Some(done),
+                        }
+                    }
+                    completion.expect("the completion step always finishes")
+                };
+                let (response, usage) = completion?;
                 capabilities.record_usage(usage).await;
+                turns += 1;
 
                 // Yield the assistant's response
-                yield response.clone();
+                yield AgentEvent::Message(response.clone());
 
                 tokio::task::yield_now().await;
 
                 // First collect any tool requests
-                let tool_requests: Vec<&ToolRequest> = response.content
+                let tool_requests: Vec<ToolRequest> = response.content
                     .iter()
                     .filter_map(|content| content.as_tool_request())
+                    .cloned()
                     .collect();
 
                 if tool_requests.is_empty() {
                     break;
                 }
 
-                // Then dispatch each in parallel
-                let futures: Vec<_> = tool_requests
-                    .iter()
-                    .filter_map(|request| request.tool_call.clone().ok())
-                    .map(|tool_call| capabilities.dispatch_tool_call(tool_call))
-                    .collect();
-
-                // Process all the futures in parallel but wait until all are finished
-                let outputs = futures::future::join_all(futures).await;
-
-                // Create a message with the responses
-                let mut message_tool_response = Message::user();
-                // Now combine these into MessageContent::ToolResponse using the original ID
-                for (request, output) in tool_requests.iter().zip(outputs.into_iter()) {
-                    message_tool_response = message_tool_response.with_tool_response(
-                        request.id.clone(),
-                        output,
-                    );
-                }
-
-                yield message_tool_response.clone();
+                // Then run them, passing on the confirmations they need and their progress
+                let (message_tool_response, tool_limit) = {
+                    let mut steps = reply::call_tools(&mut capabilities, &self.confirmations, tool_requests);
+                    let mut done = None;
+                    while let Some(step) = steps.next().await {
+                        match step {
+                            Step::Event(event) => yield event,
+                            Step::Done(result) => done = Some(result),
+                        }
+                    }
+                    done.expect("the tool call step always finishes")
+                };
+
+                yield AgentEvent::Message(message_tool_response.clone());
 
                 messages.push(response);
                 messages.push(message_tool_response);
+
+                if let Some(e) = tool_limit {
+                    yield AgentEvent::Message(Message::assistant().with_text(e.message()));
+                    Err(e)?;
+                }
             }
         }))
     }
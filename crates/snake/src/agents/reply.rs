@@ -0,0 +1,146 @@
+//! The steps of a reply every agent shares: streaming the provider's completion and running
+//! the tool calls it requests
+//!
+//! Each step is a stream of the events to pass on to the client, ending with the step's
+//! result. Dropping the stream cancels the step, along with any tool calls still running.
+use std::collections::HashSet;
+
+use futures::future::Either;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use mcp_core::tool::Tool;
+use mcp_core::ToolError;
+
+use super::approval::{ToolApproval, ToolConfirmationRequest, ToolConfirmations};
+use super::capabilities::Capabilities;
+use super::limits::LimitExceeded;
+use super::AgentEvent;
+use crate::message::{Message, ToolRequest};
+use crate::providers::base::{ProviderDelta, ProviderUsage};
+use crate::providers::errors::ProviderError;
+
+/// An event of a reply step, or the step's result once it's finished
+pub(crate) enum Step<T> {
+    Event(AgentEvent),
+    Done(T),
+}
+
+/// Get the provider's completion, passing its deltas on as they arrive
+pub(crate) fn complete<'a>(
+    capabilities: &'a Capabilities,
+    system_prompt: &'a str,
+    messages: &'a [Message],
+    tools: &'a [Tool],
+) -> BoxStream<'a, Step<Result<(Message, ProviderUsage), ProviderError>>> {
+    Box::pin(async_stream::stream! {
+        let mut deltas = match capabilities.provider().stream(system_prompt, messages, tools).await {
+            Ok(deltas) => deltas,
+            Err(e) => {
+                yield Step::Done(Err(e));
+                return;
+            }
+        };
+
+        while let Some(delta) = deltas.next().await {
+            match delta {
+                Ok(ProviderDelta::Finish { message, usage }) => {
+                    yield Step::Done(Ok((message, usage)));
+                    return;
+                }
+                Ok(delta) => yield Step::Event(AgentEvent::Delta(delta)),
+                Err(e) => {
+                    yield Step::Done(Err(e));
+                    return;
+                }
+            }
+        }
+        yield Step::Done(Err(ProviderError::RequestFailed(
+            "Response stream ended before the completion finished".to_string(),
+        )));
+    })
+}
+
+/// Run the tool calls of a response, finishing with the message holding their results and
+/// the tool call limit the calls ran into, if any
+///
+/// The calls the approval policy holds back are confirmed one at a time before any of them
+/// is dispatched, and the progress extensions report is passed on while the calls run.
+pub(crate) fn call_tools<'a>(
+    capabilities: &'a mut Capabilities,
+    confirmations: &'a ToolConfirmations,
+    tool_requests: Vec<ToolRequest>,
+) -> BoxStream<'a, Step<(Message, Option<LimitExceeded>)>> {
+    Box::pin(async_stream::stream! {
+        let mut declined = HashSet::new();
+        for request in &tool_requests {
+            if let Ok(tool_call) = &request.tool_call {
+                if capabilities.tool_approval(tool_call) == ToolApproval::Ask {
+                    let confirmation = confirmations.register(&request.id);
+                    yield Step::Event(AgentEvent::ConfirmationRequired(ToolConfirmationRequest {
+                        id: request.id.clone(),
+                        tool_call: tool_call.clone(),
+                    }));
+                    // A confirmation that is never answered counts as declined
+                    if !confirmation.await.unwrap_or(false) {
+                        declined.insert(request.id.clone());
+                    }
+                }
+            }
+        }
+
+        // Restart any extension that stopped since the last turn before calling its tools
+        capabilities.check_extensions().await;
+        let capabilities = &*capabilities;
+
+        // Then dispatch each in parallel, refusing the calls past the session's tool call limit
+        let mut tool_limit = None;
+        let futures: Vec<_> = tool_requests
+            .iter()
+            .filter_map(|request| request.tool_call.clone().ok().map(|tool_call| (request, tool_call)))
+            .map(|(request, tool_call)| {
+                let declined = declined.contains(&request.id);
+                let reserved = if declined { Ok(()) } else { capabilities.reserve_tool_call() };
+                if let Err(e) = &reserved {
+                    tool_limit = Some(e.clone());
+                }
+                async move {
+                    if declined {
+                        Err(ToolError::ExecutionError(format!(
+                            "The user declined to run {}",
+                            tool_call.name
+                        )))
+                    } else if let Err(e) = reserved {
+                        Err(ToolError::ExecutionError(e.to_string()))
+                    } else {
+                        capabilities.dispatch_tool_request(&request.id, tool_call).await
+                    }
+                }
+            })
+            .collect();
+
+        // Run the calls, which Capabilities orders by each tool's annotations, and wait until
+        // all are finished, passing on the progress the extensions report in the meantime
+        let outputs = {
+            let mut tool_progress = capabilities.tool_progress();
+            let mut dispatch = Box::pin(futures::future::join_all(futures));
+            loop {
+                let next = tokio::select! {
+                    outputs = &mut dispatch => Either::Left(outputs),
+                    Some(progress) = tool_progress.next() => Either::Right(progress),
+                };
+                match next {
+                    Either::Left(outputs) => break outputs,
+                    Either::Right(progress) => yield Step::Event(AgentEvent::Progress(progress)),
+                }
+            }
+        };
+
+        // Combine the outputs into one message, using the ids of the original requests
+        let mut message_tool_response = Message::user();
+        for (request, output) in tool_requests.iter().zip(outputs.into_iter()) {
+            message_tool_response =
+                message_tool_response.with_tool_response(request.id.clone(), output);
+        }
+        yield Step::Done((message_tool_response, tool_limit));
+    })
+}
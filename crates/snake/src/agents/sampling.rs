@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mcp_client::SamplingHandler;
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, INTERNAL_ERROR, INVALID_REQUEST,
+};
+use mcp_core::{Content, Role};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::pricing::Pricing;
+
+/// The config key holding the limits on completions requested by extensions
+pub const SAMPLING_CONFIG_KEY: &str = "GOOSE_SAMPLING";
+
+/// Limits on the completions one extension may request from the agent's model in a session
+///
+/// Limits left out of an extension's entry are off.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingLimits {
+    /// Completions the extension may request, where 0 turns sampling off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests: Option<u32>,
+    /// Input and output tokens the extension's completions may use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+}
+
+impl SamplingLimits {
+    /// Limits that refuse every request
+    pub fn off() -> Self {
+        Self {
+            max_requests: Some(0),
+            max_tokens: None,
+        }
+    }
+}
+
+/// Policy for the `sampling/createMessage` requests extensions send to use the agent's model
+///
+/// Extensions without limits of their own get the default limits. Sampling is off for them
+/// unless the default is configured, since nothing asks the user before a request goes to
+/// the model.
+///
+/// ```yaml
+/// GOOSE_SAMPLING:
+///   default:
+///     max_requests: 10
+///   extensions:
+///     summarizer:
+///       max_requests: 50
+///       max_tokens: 500000
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingPolicy {
+    #[serde(default = "SamplingLimits::off")]
+    pub default: SamplingLimits,
+    #[serde(default)]
+    pub extensions: HashMap<String, SamplingLimits>,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        Self {
+            default: SamplingLimits::off(),
+            extensions: HashMap::new(),
+        }
+    }
+}
+
+impl SamplingPolicy {
+    /// Load the policy from the config, falling back to sampling being off
+    pub fn from_config(config: &Config) -> Self {
+        config.get(SAMPLING_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// The limits that apply to an extension
+    pub fn limits(&self, extension: &str) -> &SamplingLimits {
+        self.extensions.get(extension).unwrap_or(&self.default)
+    }
+}
+
+/// Decides whether a sampling request from an extension may go to the model, for example
+/// by asking the user
+#[async_trait]
+pub trait SamplingApprover: Send + Sync {
+    async fn approve(&self, extension: &str, params: &CreateMessageParams) -> bool;
+}
+
+/// What an extension has used of its sampling limits so far
+#[derive(Debug, Default, Clone, Copy)]
+struct SamplingUsage {
+    requests: u32,
+    tokens: u64,
+}
+
+/// Answers the sampling requests of extensions with the agent's provider
+///
+/// The usage of these completions is recorded with the rest of the session's usage, so it
+/// counts towards the agent's limits and costs.
+pub struct Sampling {
+    provider: Arc<dyn Provider>,
+    provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
+    pricing: Pricing,
+    policy: SamplingPolicy,
+    approver: RwLock<Option<Arc<dyn SamplingApprover>>>,
+    used: Mutex<HashMap<String, SamplingUsage>>,
+}
+
+impl Sampling {
+    pub fn new(
+        provider: Arc<dyn Provider>,
+        provider_usage: Arc<Mutex<Vec<ProviderUsage>>>,
+        pricing: Pricing,
+        policy: SamplingPolicy,
+    ) -> Self {
+        Self {
+            provider,
+            provider_usage,
+            pricing,
+            policy,
+            approver: RwLock::new(None),
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Ask the approver before every sampling request, instead of allowing them all
+    pub fn set_approver(&self, approver: Arc<dyn SamplingApprover>) {
+        *self.approver.write().unwrap() = Some(approver);
+    }
+
+    /// The handler answering the sampling requests of one extension
+    pub fn handler(self: &Arc<Self>, extension: &str) -> Arc<dyn SamplingHandler> {
+        Arc::new(ExtensionSampler {
+            extension: extension.to_string(),
+            sampling: Arc::clone(self),
+        })
+    }
+
+    /// Complete the messages of an extension's sampling request
+    pub async fn create_message(
+        &self,
+        extension: &str,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        let error = |code, message: String| ErrorData {
+            code,
+            message,
+            data: None,
+        };
+
+        // Count the request up front so concurrent requests can't exceed the limit
+        let limits = self.policy.limits(extension);
+        {
+            let mut used = self.used.lock().await;
+            let used = used.entry(extension.to_string()).or_default();
+            if let Some(max) = limits.max_requests {
+                if used.requests >= max {
+                    return Err(error(
+                        INVALID_REQUEST,
+                        format!("Reached the limit of {} sampling requests", max),
+                    ));
+                }
+            }
+            if let Some(max) = limits.max_tokens {
+                if used.tokens >= max {
+                    return Err(error(
+                        INVALID_REQUEST,
+                        format!("Reached the limit of {} sampling tokens", max),
+                    ));
+                }
+            }
+            used.requests += 1;
+        }
+
+        let approver = self.approver.read().unwrap().clone();
+        if let Some(approver) = approver {
+            if !approver.approve(extension, &params).await {
+                return Err(error(
+                    INVALID_REQUEST,
+                    "The user declined the sampling request".to_string(),
+                ));
+            }
+        }
+
+        let messages: Vec<Message> = params
+            .messages
+            .into_iter()
+            .map(|message| Message {
+                role: message.role,
+                created: Utc::now().timestamp(),
+                content: vec![MessageContent::from(message.content)],
+            })
+            .collect();
+        let system = params.system_prompt.unwrap_or_default();
+        let model = self
+            .provider
+            .get_model_config()
+            .with_max_tokens(Some(params.max_tokens.min(i32::MAX as u32) as i32));
+
+        let (response, mut usage) = self
+            .provider
+            .complete_with_model(&model, &system, &messages, &[])
+            .await
+            .map_err(|e| error(INTERNAL_ERROR, e.to_string()))?;
+
+        let tokens = usage.usage.input_tokens.unwrap_or(0).max(0) as u64
+            + usage.usage.output_tokens.unwrap_or(0).max(0) as u64;
+        if let Some(used) = self.used.lock().await.get_mut(extension) {
+            used.tokens += tokens;
+        }
+        let model = usage.model.clone();
+        usage.cost = self.pricing.cost(&usage.model, &usage.usage);
+        self.provider_usage.lock().await.push(usage);
+
+        Ok(CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text(response.as_concat_text()),
+            model,
+            stop_reason: Some("endTurn".to_string()),
+        })
+    }
+}
+
+/// The sampling handler given to an extension's client
+struct ExtensionSampler {
+    extension: String,
+    sampling: Arc<Sampling>,
+}
+
+#[async_trait]
+impl SamplingHandler for ExtensionSampler {
+    async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        self.sampling.create_message(&self.extension, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, Usage};
+    use crate::providers::errors::ProviderError;
+    use mcp_core::protocol::SamplingMessage;
+    use mcp_core::tool::Tool;
+    use serde_json::json;
+
+    struct EchoProvider;
+
+    #[async_trait]
+    impl Provider for EchoProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("echo".to_string())
+        }
+
+        async fn complete(
+            &self,
+            system: &str,
+            messages: &[Message],
+            tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.complete_with_model(&self.get_model_config(), system, messages, tools)
+                .await
+        }
+
+        async fn complete_with_model(
+            &self,
+            model: &ModelConfig,
+            system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let text = format!(
+                "{}: {} ({:?} tokens)",
+                system,
+                messages[0].as_concat_text(),
+                model.max_tokens
+            );
+            Ok((
+                Message::assistant().with_text(text),
+                ProviderUsage::new("echo".to_string(), Usage::new(Some(60), Some(40), None)),
+            ))
+        }
+    }
+
+    struct Decline;
+
+    #[async_trait]
+    impl SamplingApprover for Decline {
+        async fn approve(&self, _extension: &str, _params: &CreateMessageParams) -> bool {
+            false
+        }
+    }
+
+    fn params() -> CreateMessageParams {
+        CreateMessageParams {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text("Summarize this"),
+            }],
+            system_prompt: Some("Be brief".to_string()),
+            max_tokens: 100,
+            model_preferences: None,
+            temperature: None,
+            stop_sequences: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampling_limits() {
+        let policy: SamplingPolicy = serde_json::from_value(json!({
+            "extensions": {"summarizer": {"max_tokens": 150}}
+        }))
+        .unwrap();
+        let usage = Arc::new(Mutex::new(Vec::new()));
+        let sampling = Arc::new(Sampling::new(
+            Arc::new(EchoProvider),
+            Arc::clone(&usage),
+            Pricing::default(),
+            policy,
+        ));
+
+        let handler = sampling.handler("summarizer");
+        let result = handler.create_message(params()).await.unwrap();
+        assert_eq!(
+            result.content,
+            Content::text("Be brief: Summarize this (Some(100) tokens)")
+        );
+        assert_eq!(result.model, "echo");
+        assert_eq!(usage.lock().await.len(), 1);
+
+        // The second request uses up the tokens, so the third is refused
+        handler.create_message(params()).await.unwrap();
+        let err = handler.create_message(params()).await.unwrap_err();
+        assert_eq!(err.code, INVALID_REQUEST);
+
+        // Sampling is off for extensions without limits of their own
+        let err = sampling
+            .handler("developer")
+            .create_message(params())
+            .await
+            .unwrap_err();
+        assert_eq!(err.message, "Reached the limit of 0 sampling requests");
+    }
+
+    #[tokio::test]
+    async fn test_sampling_approver() {
+        let usage = Arc::new(Mutex::new(Vec::new()));
+        let sampling = Arc::new(Sampling::new(
+            Arc::new(EchoProvider),
+            Arc::clone(&usage),
+            Pricing::default(),
+            serde_json::from_value(json!({"default": {}})).unwrap(),
+        ));
+        sampling.set_approver(Arc::new(Decline));
+
+        let err = sampling
+            .create_message("summarizer", params())
+            .await
+            .unwrap_err();
+        assert_eq!(err.message, "The user declined the sampling request");
+        assert!(usage.lock().await.is_empty());
+    }
+}
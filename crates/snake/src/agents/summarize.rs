@@ -0,0 +1,545 @@
+/// A summarize agent that compresses older turns into a single summary message when the
+/// conversation exceeds the model's context limit, keeping the most recent turns verbatim
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, error, instrument, warn};
+
+use super::{Agent, AgentEvent};
+use crate::agents::approval::{ToolApprovalPolicy, ToolConfirmations};
+use crate::agents::capabilities::Capabilities;
+use crate::agents::extension::{ExtensionConfig, ExtensionResult, ExtensionStatus};
+use crate::agents::reply::{self, Step};
+use crate::agents::sampling::SamplingApprover;
+use crate::message::{Message, MessageContent, ToolRequest};
+use crate::prompt_template::load_prompt_file;
+use crate::providers::base::Provider;
+use crate::providers::base::ProviderUsage;
+use crate::providers::errors::ProviderError;
+use crate::register_agent;
+use crate::token_counter::TokenCounter;
+use crate::truncate::{truncate_messages, OldestFirstTruncation};
+use indoc::indoc;
+use mcp_core::prompt::Prompt;
+use mcp_core::protocol::GetPromptResult;
+use mcp_core::role::Role;
+use mcp_core::tool::Tool;
+use serde_json::{json, Value};
+
+const MAX_SUMMARIZATION_ATTEMPTS: usize = 3;
+const ESTIMATE_FACTOR_DECAY: f32 = 0.9;
+/// Share of the target context that recent turns may use before they get summarized
+const RECENT_CONTEXT_FRACTION: f32 = 0.5;
+/// Tool results are clipped to this many characters in the transcript we summarize
+const MAX_TOOL_RESULT_CHARS: usize = 2_000;
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+/// Summarize implementation of an Agent
+pub struct SummarizeAgent {
+    capabilities: Mutex<Capabilities>,
+    confirmations: ToolConfirmations,
+    token_counter: TokenCounter,
+}
+
+impl SummarizeAgent {
+    pub fn new(provider: Box<dyn Provider>) -> Self {
+        let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
+        Self {
+            capabilities: Mutex::new(Capabilities::new(provider)),
+            confirmations: ToolConfirmations::default(),
+            token_counter,
+        }
+    }
+
+    /// Replaces the older part of the conversation with a summary written by the provider
+    /// Recent turns are kept verbatim, and tool request/response pairs are never split
+    async fn summarize_messages(
+        &self,
+        capabilities: &Capabilities,
+        messages: &mut Vec<Message>,
+        estimate_factor: f32,
+    ) -> anyhow::Result<()> {
+        // Our conservative estimate of the **target** context limit
+        // Our token count is an estimate since model providers often don't provide the tokenizer (eg. Claude)
+        let context_limit = capabilities.provider().get_model_config().context_limit();
+        let context_limit = (context_limit as f32 * estimate_factor) as usize;
+
+        let mut token_counts: Vec<usize> = messages
+            .iter()
+            .map(|msg| self.token_counter.count_tokens(&render_message(msg)))
+            .collect();
+
+        let recent_budget = (context_limit as f32 * RECENT_CONTEXT_FRACTION) as usize;
+        let split = match find_summary_split(messages, &token_counts, recent_budget) {
+            Some(split) => split,
+            None => {
+                // Nothing we can summarize without breaking the conversation, fall back to
+                // dropping the oldest messages
+                warn!("No summary boundary found, falling back to truncation");
+                return truncate_messages(
+                    messages,
+                    &mut token_counts,
+                    context_limit,
+                    &OldestFirstTruncation,
+                );
+            }
+        };
+
+        let summary = self
+            .summarize(capabilities, &messages[..split], context_limit / 2)
+            .await?;
+
+        debug!(
+            "Summarized {} messages, keeping {} recent messages",
+            split,
+            messages.len() - split
+        );
+
+        let mut summarized =
+            vec![Message::user().with_text(format!("{}\n\n{}", SUMMARY_PREFIX, summary))];
+        summarized.extend(messages.drain(split..));
+        *messages = summarized;
+
+        Ok(())
+    }
+
+    /// Ask the provider to summarize the messages, in chunks that fit within `chunk_limit`
+    async fn summarize(
+        &self,
+        capabilities: &Capabilities,
+        messages: &[Message],
+        chunk_limit: usize,
+    ) -> anyhow::Result<String> {
+        let system_prompt = load_prompt_file("summarize.md", &json!({}))?;
+
+        let mut chunks: Vec<String> = Vec::new();
+        let mut chunk = String::new();
+        let mut chunk_tokens = 0;
+        for entry in messages.iter().map(render_message) {
+            let entry_tokens = self.token_counter.count_tokens(&entry);
+            if !chunk.is_empty() && chunk_tokens + entry_tokens > chunk_limit {
+                chunks.push(std::mem::take(&mut chunk));
+                chunk_tokens = 0;
+            }
+            chunk.push_str(&entry);
+            chunk.push_str("\n\n");
+            chunk_tokens += entry_tokens;
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+
+        // Each chunk is folded into the summary of everything before it
+        let mut summary = String::new();
+        for chunk in chunks {
+            let request = if summary.is_empty() {
+                format!("Transcript:\n\n{}", chunk)
+            } else {
+                format!(
+                    "Summary so far:\n\n{}\n\nTranscript continues:\n\n{}",
+                    summary, chunk
+                )
+            };
+
+            let (response, usage) = capabilities
+                .provider()
+                .complete(&system_prompt, &[Message::user().with_text(request)], &[])
+                .await?;
+            capabilities.record_usage(usage).await;
+
+            summary = response.as_concat_text();
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Finds the index where the conversation is split into a summarized prefix and a verbatim
+/// suffix. The suffix starts at an assistant message so the summary (a user message) keeps the
+/// roles alternating, and no tool request is separated from its response.
+///
+/// Prefers the earliest boundary whose suffix fits within `recent_budget`, otherwise keeps as
+/// little as possible. Returns None when there is no valid boundary.
+fn find_summary_split(
+    messages: &[Message],
+    token_counts: &[usize],
+    recent_budget: usize,
+) -> Option<usize> {
+    // A boundary at index i is valid when every tool request before i is answered before i
+    let mut pending: HashSet<&str> = HashSet::new();
+    let mut valid = vec![false; messages.len()];
+    for (i, message) in messages.iter().enumerate() {
+        valid[i] = i > 0 && pending.is_empty() && message.role == Role::Assistant;
+        pending.extend(message.get_tool_request_ids());
+        for id in message.get_tool_response_ids() {
+            pending.remove(id);
+        }
+    }
+
+    let mut split = None;
+    let mut recent_tokens = 0;
+    for i in (1..messages.len()).rev() {
+        recent_tokens += token_counts[i];
+        if !valid[i] {
+            continue;
+        }
+        if split.is_some() && recent_tokens > recent_budget {
+            break;
+        }
+        split = Some(i);
+    }
+    split
+}
+
+/// Renders a message as plain text for the summarization transcript
+fn render_message(message: &Message) -> String {
+    let role = match message.role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    };
+
+    message
+        .content
+        .iter()
+        .map(|content| match content {
+            MessageContent::Text(text) => format!("{}: {}", role, text.text),
+            MessageContent::Image(_) => format!("{}: [image]", role),
+            MessageContent::ToolRequest(request) => match &request.tool_call {
+                Ok(tool_call) => format!(
+                    "{} called tool {} with arguments {}",
+                    role, tool_call.name, tool_call.arguments
+                ),
+                Err(e) => format!("{} made an invalid tool call: {}", role, e),
+            },
+            MessageContent::ToolResponse(response) => match &response.tool_result {
+                Ok(contents) => {
+                    let text = contents
+                        .iter()
+                        .filter_map(|c| c.as_text())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let mut clipped: String = text.chars().take(MAX_TOOL_RESULT_CHARS).collect();
+                    if clipped.len() < text.len() {
+                        clipped.push_str(" [...]");
+                    }
+                    format!("tool result: {}", clipped)
+                }
+                Err(e) => format!("tool error: {}", e),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl Agent for SummarizeAgent {
+    async fn handle_confirmation(&self, request_id: &str, confirmed: bool) {
+        if !self.confirmations.confirm(request_id, confirmed) {
+            warn!("No tool call is waiting for confirmation {}", request_id);
+        }
+    }
+
+    async fn add_extension(&mut self, extension: ExtensionConfig) -> ExtensionResult<()> {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.add_extension(extension).await
+    }
+
+    async fn remove_extension(&mut self, name: &str) {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities
+            .remove_extension(name)
+            .await
+            .expect("Failed to remove extension");
+    }
+
+    async fn list_extensions(&self) -> Vec<ExtensionStatus> {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.check_extensions().await;
+        capabilities
+            .list_extensions()
+            .await
+            .expect("Failed to list extensions")
+    }
+
+    async fn list_prompts(&self) -> HashMap<String, Vec<Prompt>> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.list_prompts().await
+    }
+
+    async fn get_prompt(
+        &self,
+        extension: &str,
+        name: &str,
+        arguments: Value,
+    ) -> ExtensionResult<GetPromptResult> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.get_prompt(extension, name, arguments).await
+    }
+
+    async fn set_sampling_approver(&self, approver: Arc<dyn SamplingApprover>) {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.set_sampling_approver(approver);
+    }
+
+    async fn set_approval_policy(&self, policy: ToolApprovalPolicy) {
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.set_approval_policy(policy);
+    }
+
+    async fn passthrough(&self, extension: &str, request: Value) -> ExtensionResult<Value> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.passthrough(extension, request).await
+    }
+
+    #[instrument(skip(self, messages), fields(user_message))]
+    async fn reply(
+        &self,
+        messages: &[Message],
+    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<AgentEvent>>> {
+        let mut messages = messages.to_vec();
+        let reply_span = tracing::Span::current();
+        let mut capabilities = self.capabilities.lock().await;
+        capabilities.check_extensions().await;
+        let mut tools = capabilities.get_prefixed_tools().await?;
+        let mut summarization_attempt: usize = 0;
+
+        // we add in the read_resource tool by default
+        // Names starting with platform__ are reserved, so extension tools never collide with these
+        let read_resource_tool = Tool::new(
+            "platform__read_resource".to_string(),
+            indoc! {r#"
+                Read a resource from an extension.
+
+                Resources allow extensions to share data that provide context to LLMs, such as
+                files, database schemas, or application-specific information. This tool searches for the
+                resource URI in the provided extension, and reads in the resource content. If no extension
+                is provided, the tool will search all extensions for the resource.
+            "#}.to_string(),
+            json!({
+                "type": "object",
+                "required": ["uri"],
+                "properties": {
+                    "uri": {"type": "string", "description": "Resource URI"},
+                    "extension_name": {"type": "string", "description": "Optional extension name"}
+                }
+            }),
+        );
+
+        let list_resources_tool = Tool::new(
+            "platform__list_resources".to_string(),
+            indoc! {r#"
+                List resources from an extension(s).
+
+                Resources allow extensions to share data that provide context to LLMs, such as
+                files, database schemas, or application-specific information. This tool lists resources
+                in the provided extension, and returns a list for the user to browse. If no extension
+                is provided, the tool will search all extensions for the resource.
+            "#}.to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "extension_name": {"type": "string", "description": "Optional extension name"}
+                }
+            }),
+        );
+
+        if capabilities.supports_resources() {
+            tools.push(read_resource_tool);
+            tools.push(list_resources_tool);
+        }
+
+        let system_prompt = capabilities.get_system_prompt().await;
+
+        // Set the user_message field in the span instead of creating a new event
+        if let Some(content) = messages
+            .last()
+            .and_then(|msg| msg.content.first())
+            .and_then(|c| c.as_text())
+        {
+            debug!("user_message" = &content);
+        }
+
+        Ok(Box::pin(async_stream::try_stream! {
+            let _reply_guard = reply_span.enter();
+            let mut turns: usize = 0;
+            loop {
+                // Stop before the next completion once a turn, token or spend limit is reached
+                if let Err(e) = capabilities.check_limits(turns).await {
+                    yield AgentEvent::Message(Message::assistant().with_text(e.message()));
+                    Err(e)?;
+                }
+
+                // Get the completion from the provider, passing its deltas through as they arrive
+                let completion = {
+                    let mut steps = reply::complete(&capabilities, &system_prompt, &messages, &tools);
+                    let mut completion = None;
+                    while let Some(step) = steps.next().await {
+                        match step {
+                            Step::Event(event) => yield event,
+                            Step::Done(done) => completion = Some(done),
+                        }
+                    }
+                    completion.expect("the completion step always finishes")
+                };
+
+                match completion {
+                    Ok((response, usage)) => {
+                        capabilities.record_usage(usage).await;
+                        turns += 1;
+
+                        // Reset summarization attempt
+                        summarization_attempt = 0;
+
+                        // Yield the assistant's response
+                        yield AgentEvent::Message(response.clone());
+
+                        tokio::task::yield_now().await;
+
+                        // First collect any tool requests
+                        let tool_requests: Vec<ToolRequest> = response.content
+                            .iter()
+                            .filter_map(|content| content.as_tool_request())
+                            .cloned()
+                            .collect();
+
+                        if tool_requests.is_empty() {
+                            break;
+                        }
+
+                        // Then run them, passing on the confirmations they need and their progress
+                        let (message_tool_response, tool_limit) = {
+                            let mut steps = reply::call_tools(&mut capabilities, &self.confirmations, tool_requests);
+                            let mut done = None;
+                            while let Some(step) = steps.next().await {
+                                match step {
+                                    Step::Event(event) => yield event,
+                                    Step::Done(result) => done = Some(result),
+                                }
+                            }
+                            done.expect("the tool call step always finishes")
+                        };
+
+                        yield AgentEvent::Message(message_tool_response.clone());
+
+                        messages.push(response);
+                        messages.push(message_tool_response);
+
+                        if let Some(e) = tool_limit {
+                            yield AgentEvent::Message(Message::assistant().with_text(e.message()));
+                            Err(e)?;
+                        }
+                    },
+                    Err(ProviderError::ContextLengthExceeded(_)) => {
+                        if summarization_attempt >= MAX_SUMMARIZATION_ATTEMPTS {
+                            // Create an error message & terminate the stream
+                            yield AgentEvent::Message(Message::assistant().with_text("Error: Context length exceeds limits even after multiple attempts to summarize."));
+                            break;
+                        }
+
+                        summarization_attempt += 1;
+                        warn!("Context length exceeded. Summarization Attempt: {}/{}.", summarization_attempt, MAX_SUMMARIZATION_ATTEMPTS);
+
+                        // Decay the estimate factor as we make more summarization attempts
+                        // Estimate factor decays like this over time: 0.9, 0.81, 0.729, ...
+                        let estimate_factor: f32 = ESTIMATE_FACTOR_DECAY.powi(summarization_attempt as i32);
+
+                        self.summarize_messages(&capabilities, &mut messages, estimate_factor).await?;
+
+                        // Retry the loop after summarization
+                        continue;
+                    },
+                    Err(e) => {
+                        // Create an error message & terminate the stream
+                        error!("Error: {}", e);
+                        yield AgentEvent::Message(Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")));
+                        break;
+                    }
+                }
+
+                // Yield control back to the scheduler to prevent blocking
+                tokio::task::yield_now().await;
+            }
+        }))
+    }
+
+    async fn usage(&self) -> Vec<ProviderUsage> {
+        let capabilities = self.capabilities.lock().await;
+        capabilities.get_usage().await
+    }
+}
+
+register_agent!("summarize", SummarizeAgent);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::tool::ToolCall;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::user().with_text("first request"),
+            Message::assistant().with_tool_request("1", Ok(ToolCall::new("shell", json!({})))),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("output")])),
+            Message::assistant().with_text("first answer"),
+            Message::user().with_text("second request"),
+            Message::assistant().with_tool_request("2", Ok(ToolCall::new("shell", json!({})))),
+            Message::user().with_tool_response("2", Ok(vec![Content::text("output")])),
+            Message::assistant().with_text("second answer"),
+            Message::user().with_text("third request"),
+        ]
+    }
+
+    #[test]
+    fn test_split_keeps_recent_turns_within_budget() {
+        let messages = conversation();
+        let token_counts = vec![10; messages.len()];
+
+        // The last four messages fit, and index 5 starts a tool request/response pair
+        assert_eq!(find_summary_split(&messages, &token_counts, 40), Some(5));
+    }
+
+    #[test]
+    fn test_split_never_separates_tool_pairs() {
+        let messages = conversation();
+        let token_counts = vec![10; messages.len()];
+
+        // Keeping the second tool pair would exceed the budget, and starting at its
+        // response would orphan it, so only the final answer is kept
+        assert_eq!(find_summary_split(&messages, &token_counts, 20), Some(7));
+    }
+
+    #[test]
+    fn test_split_keeps_latest_turn_when_over_budget() {
+        let messages = conversation();
+        let token_counts = vec![100; messages.len()];
+
+        assert_eq!(find_summary_split(&messages, &token_counts, 10), Some(7));
+    }
+
+    #[test]
+    fn test_split_none_without_assistant_boundary() {
+        let messages = vec![Message::user().with_text("only message")];
+        assert_eq!(find_summary_split(&messages, &[10], 100), None);
+    }
+
+    #[test]
+    fn test_render_message_includes_tool_calls() {
+        let request = Message::assistant().with_tool_request(
+            "1",
+            Ok(ToolCall::new("developer__shell", json!({"cmd": "ls"}))),
+        );
+        assert!(render_message(&request).contains("developer__shell"));
+
+        let response = Message::user().with_tool_response(
+            "1",
+            Ok(vec![Content::text("x".repeat(MAX_TOOL_RESULT_CHARS + 10))]),
+        );
+        let rendered = render_message(&response);
+        assert!(rendered.starts_with("tool result: "));
+        assert!(rendered.ends_with("[...]"));
+    }
+}
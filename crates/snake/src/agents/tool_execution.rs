@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// The config key holding how tool calls are run
+pub const TOOL_EXECUTION_CONFIG_KEY: &str = "GOOSE_TOOL_EXECUTION";
+
+/// How long a tool call may run when nothing is configured for it
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// How the agent runs the tool calls a model requests in one turn
+///
+/// Calls to different extensions run concurrently. Calls to the same extension run one at a
+/// time in the order requested, unless the tool is annotated as parallel. Timeouts are in
+/// seconds, and can be set for a tool by the name the model calls it, or for all the tools
+/// of an extension by its name.
+///
+/// ```yaml
+/// GOOSE_TOOL_EXECUTION:
+///   max_concurrency: 4
+///   timeout: 300
+///   timeouts:
+///     developer__shell: 1800
+///     computercontroller: 600
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolExecutionPolicy {
+    /// The most tool calls that run at once, unlimited if unset or 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub timeouts: HashMap<String, u64>,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl Default for ToolExecutionPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrency: None,
+            timeout: DEFAULT_TIMEOUT_SECS,
+            timeouts: HashMap::new(),
+        }
+    }
+}
+
+impl ToolExecutionPolicy {
+    /// Load the policy from the config, falling back to the default timeout
+    pub fn from_config(config: &Config) -> Self {
+        config.get(TOOL_EXECUTION_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// The most tool calls that may run at once, if there is a limit
+    pub fn concurrency_limit(&self) -> Option<usize> {
+        self.max_concurrency.filter(|&max| max > 0)
+    }
+
+    /// How long a call to a tool of an extension may run
+    pub fn timeout(&self, tool_name: &str, extension: &str) -> Duration {
+        let secs = self
+            .timeouts
+            .get(tool_name)
+            .or_else(|| self.timeouts.get(extension))
+            .copied()
+            .unwrap_or(self.timeout);
+        Duration::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_prefers_tool_over_extension() {
+        let policy: ToolExecutionPolicy = serde_json::from_value(serde_json::json!({
+            "timeouts": {"developer__shell": 1800, "developer": 600}
+        }))
+        .unwrap();
+        assert_eq!(
+            policy.timeout("developer__shell", "developer"),
+            Duration::from_secs(1800)
+        );
+        assert_eq!(
+            policy.timeout("developer__text_editor", "developer"),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            policy.timeout("memory__remember", "memory"),
+            Duration::from_secs(DEFAULT_TIMEOUT_SECS)
+        );
+    }
+
+    #[test]
+    fn test_zero_concurrency_is_unlimited() {
+        let policy: ToolExecutionPolicy =
+            serde_json::from_value(serde_json::json!({"max_concurrency": 0})).unwrap();
+        assert_eq!(policy.concurrency_limit(), None);
+
+        let policy: ToolExecutionPolicy =
+            serde_json::from_value(serde_json::json!({"max_concurrency": 2})).unwrap();
+        assert_eq!(policy.concurrency_limit(), Some(2));
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use mcp_core::role::Role;
+use mcp_core::Content;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::model::ModelConfig;
+use crate::token_counter::TokenCounter;
+
+/// The config key holding the limits on the size of tool results
+pub const TOOL_OUTPUT_CONFIG_KEY: &str = "GOOSE_TOOL_OUTPUT";
+
+/// The share of the context a tool result may use when nothing is configured
+const DEFAULT_CONTEXT_FRACTION: f64 = 0.2;
+
+/// Tokens set aside for the note explaining where the rest of the output went
+const NOTICE_TOKENS: usize = 100;
+
+/// The most text a result may show the user, since only the model's copy is budgeted
+const MAX_USER_TEXT_BYTES: usize = 400_000;
+
+/// Saved outputs older than this are removed when the next one is saved
+const SPILL_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The most space saved outputs may take, removing the oldest ones first
+const SPILL_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// How much of the model's context a single tool result may use
+///
+/// Results over the budget are cut down to their start and end, and the full output is
+/// saved to a file the model can read if it needs more. The budget is a fraction of the
+/// model's context limit, which can be set per model.
+///
+/// ```yaml
+/// GOOSE_TOOL_OUTPUT:
+///   context_fraction: 0.2
+///   models:
+///     gpt-4o-mini: 0.1
+///   spill_dir: /tmp/goose-tool-output
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutputPolicy {
+    #[serde(default = "default_context_fraction")]
+    pub context_fraction: f64,
+    /// Fractions for specific models, replacing the default fraction
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub models: HashMap<String, f64>,
+    /// Where full outputs are saved, by default in the user's cache directory. Outputs are
+    /// removed after a week, or sooner once they take more than 100MB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_dir: Option<PathBuf>,
+}
+
+fn default_context_fraction() -> f64 {
+    DEFAULT_CONTEXT_FRACTION
+}
+
+impl Default for ToolOutputPolicy {
+    fn default() -> Self {
+        Self {
+            context_fraction: DEFAULT_CONTEXT_FRACTION,
+            models: HashMap::new(),
+            spill_dir: None,
+        }
+    }
+}
+
+impl ToolOutputPolicy {
+    /// Load the policy from the config, falling back to the default fraction
+    pub fn from_config(config: &Config) -> Self {
+        config.get(TOOL_OUTPUT_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// The tokens a single tool result may use with a model
+    pub fn budget(&self, model: &ModelConfig) -> usize {
+        let fraction = self
+            .models
+            .get(&model.model_name)
+            .copied()
+            .unwrap_or(self.context_fraction)
+            .clamp(0.0, 1.0);
+        (model.context_limit() as f64 * fraction) as usize
+    }
+
+    fn spill_dir(&self) -> PathBuf {
+        self.spill_dir.clone().unwrap_or_else(|| {
+            dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("goose")
+                .join("tool-output")
+        })
+    }
+}
+
+/// Applies a tool output policy to the results of tool calls for one model
+pub struct ToolOutputLimiter {
+    budget: usize,
+    spill_dir: PathBuf,
+    tokenizer_name: String,
+    // Loading a tokenizer is slow, so it waits until a result is large enough to need it
+    token_counter: OnceLock<TokenCounter>,
+}
+
+impl ToolOutputLimiter {
+    pub fn new(policy: &ToolOutputPolicy, model: &ModelConfig) -> Self {
+        Self {
+            budget: policy.budget(model),
+            spill_dir: policy.spill_dir(),
+            tokenizer_name: model.tokenizer_name().to_string(),
+            token_counter: OnceLock::new(),
+        }
+    }
+
+    /// Cut a tool result down to the budget if it is over, saving the full output to a file
+    ///
+    /// Only text the model sees counts towards the budget. Output meant just for the user
+    /// is only cut down to its start and end when it is over `MAX_USER_TEXT_BYTES`.
+    pub async fn limit(
+        self: &Arc<Self>,
+        tool_name: &str,
+        mut contents: Vec<Content>,
+    ) -> Vec<Content> {
+        limit_user_text(&mut contents);
+
+        // A token always covers at least one byte, so short results skip the tokenizer
+        let bytes: usize = visible_texts(&contents)
+            .iter()
+            .map(|&i| contents[i].as_text().unwrap().len())
+            .sum();
+        if bytes <= self.budget {
+            return contents;
+        }
+
+        // Counting the tokens of a large result and saving it would block the runtime
+        let limiter = Arc::clone(self);
+        let name = tool_name.to_string();
+        match tokio::task::spawn_blocking(move || limiter.limit_blocking(&name, contents)).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                tracing::error!("Failed to limit the output of {}: {}", tool_name, e);
+                vec![Content::text(format!(
+                    "The output of {} was too large and could not be shortened",
+                    tool_name
+                ))]
+            }
+        }
+    }
+
+    fn limit_blocking(&self, tool_name: &str, mut contents: Vec<Content>) -> Vec<Content> {
+        let visible = visible_texts(&contents);
+
+        // A token always covers at least one byte, so short results skip the tokenizer
+        let bytes: usize = visible
+            .iter()
+            .map(|&i| contents[i].as_text().unwrap().len())
+            .sum();
+        if bytes <= self.budget {
+            return contents;
+        }
+
+        let counter = self
+            .token_counter
+            .get_or_init(|| TokenCounter::new(&self.tokenizer_name));
+        let tokens: Vec<usize> = visible
+            .iter()
+            .map(|&i| counter.count_tokens(contents[i].as_text().unwrap()))
+            .collect();
+        let total: usize = tokens.iter().sum();
+        if total <= self.budget {
+            return contents;
+        }
+
+        let full_output = visible
+            .iter()
+            .map(|&i| contents[i].as_text().unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let saved = match spill(&self.spill_dir, tool_name, &full_output) {
+            Ok(path) => format!("The full output is saved at {}", path.display()),
+            Err(e) => {
+                tracing::warn!("Failed to save the output of {}: {}", tool_name, e);
+                "The full output could not be saved".to_string()
+            }
+        };
+
+        // Each text keeps its share of the budget, less room for the notice
+        let budget = self.budget.saturating_sub(NOTICE_TOKENS * visible.len());
+        for (&i, &count) in visible.iter().zip(&tokens) {
+            let share = budget * count / total;
+            if let Content::Text(text) = &mut contents[i] {
+                let (head, tail) = excerpt(&text.text, count, share);
+                let notice = format!(
+                    "[... {} of {} tokens omitted. {}. Read or search it rather than \
+                     rerunning the tool ...]",
+                    count.saturating_sub(share),
+                    count,
+                    saved
+                );
+                text.text = format!("{}\n\n{}\n\n{}", head, notice, tail);
+            }
+        }
+        contents
+    }
+}
+
+/// The indices of the texts in a result the model sees
+fn visible_texts(contents: &[Content]) -> Vec<usize> {
+    contents
+        .iter()
+        .enumerate()
+        .filter(|(_, content)| {
+            content.as_text().is_some()
+                && content
+                    .audience()
+                    .is_none_or(|audience| audience.contains(&Role::Assistant))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Cut the texts only the user sees down to their start and end when they are over
+/// `MAX_USER_TEXT_BYTES`
+fn limit_user_text(contents: &mut [Content]) {
+    for content in contents.iter_mut() {
+        let user_only = content
+            .audience()
+            .is_some_and(|audience| !audience.contains(&Role::Assistant));
+        if let (true, Content::Text(text)) = (user_only, content) {
+            if text.text.len() > MAX_USER_TEXT_BYTES {
+                let len = text.text.len();
+                let (head, tail) = excerpt(&text.text, len, MAX_USER_TEXT_BYTES);
+                let omitted = len - head.len() - tail.len();
+                text.text = format!(
+                    "{}\n\n[... {} bytes omitted ...]\n\n{}",
+                    head, omitted, tail
+                );
+            }
+        }
+    }
+}
+
+/// Save a tool's full output, returning the path of the file
+fn spill(dir: &Path, tool_name: &str, output: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    if let Err(e) = prune(dir, SPILL_MAX_AGE, SPILL_MAX_BYTES) {
+        tracing::warn!("Failed to remove old tool outputs: {}", e);
+    }
+    let name: String = tool_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}-{}.txt", name, uuid::Uuid::new_v4().simple()));
+    std::fs::write(&path, output)?;
+    Ok(path)
+}
+
+/// Remove the saved outputs older than `max_age`, then the oldest ones until the rest fit
+/// in `max_bytes`
+fn prune(dir: &Path, max_age: Duration, max_bytes: u64) -> std::io::Result<()> {
+    let now = SystemTime::now();
+    let mut saved = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        // Only remove the files we saved, in case the directory is shared
+        if !metadata.is_file() || path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        let modified = metadata.modified()?;
+        if now.duration_since(modified).unwrap_or_default() > max_age {
+            std::fs::remove_file(&path)?;
+        } else {
+            saved.push((modified, metadata.len(), path));
+        }
+    }
+
+    // Newest first, keeping files while they fit
+    saved.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+    let mut total = 0;
+    for (_, len, path) in saved {
+        total += len;
+        if total > max_bytes {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep the start and end of a text within a budget of tokens, estimating the bytes
+/// per token from the whole text and cutting at line breaks where there are some
+fn excerpt(text: &str, tokens: usize, budget: usize) -> (&str, &str) {
+    let keep = text.len() * budget / tokens.max(1) / 2;
+
+    let mut head_end = floor_char_boundary(text, keep);
+    if let Some(newline) = text[..head_end].rfind('\n') {
+        if newline >= head_end / 2 {
+            head_end = newline;
+        }
+    }
+
+    let mut tail_start = floor_char_boundary(text, text.len() - keep).max(head_end);
+    if let Some(newline) = text[tail_start..].find('\n') {
+        if newline <= keep / 2 {
+            tail_start += newline + 1;
+        }
+    }
+
+    (&text[..head_end], &text[tail_start..])
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn limiter(budget: usize, spill_dir: &Path) -> ToolOutputLimiter {
+        ToolOutputLimiter {
+            budget,
+            spill_dir: spill_dir.to_path_buf(),
+            tokenizer_name: "Xenova--gpt-4o".to_string(),
+            token_counter: OnceLock::new(),
+        }
+    }
+
+    #[test]
+    fn test_budget_uses_model_fraction() {
+        let policy: ToolOutputPolicy = serde_json::from_value(serde_json::json!({
+            "models": {"small-model": 0.05}
+        }))
+        .unwrap();
+        let model = ModelConfig::new("small-model".to_string()).with_context_limit(Some(100_000));
+        assert_eq!(policy.budget(&model), 5_000);
+
+        let model = ModelConfig::new("other-model".to_string()).with_context_limit(Some(100_000));
+        assert_eq!(policy.budget(&model), 20_000);
+    }
+
+    #[test]
+    fn test_small_results_are_unchanged() {
+        let dir = tempdir().unwrap();
+        let contents = vec![Content::text("a short result")];
+        let limited = limiter(100, dir.path()).limit_blocking("developer__shell", contents.clone());
+        assert_eq!(limited, contents);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_large_results_are_truncated_and_saved() {
+        let dir = tempdir().unwrap();
+        let output: String = (0..5_000).map(|i| format!("line {}\n", i)).collect();
+        let contents = vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output.clone()).with_audience(vec![Role::User]),
+        ];
+
+        let limited = Arc::new(limiter(1_000, dir.path()))
+            .limit("developer__shell", contents)
+            .await;
+
+        let text = limited[0].as_text().unwrap();
+        assert!(text.starts_with("line 0\n"));
+        assert!(text.trim_end().ends_with("line 4999"));
+        assert!(text.contains("tokens omitted"));
+        assert!(text.len() < output.len() / 2);
+        // Output only the user sees is not cut
+        assert_eq!(limited[1].as_text().unwrap(), output);
+
+        let saved: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(saved.len(), 1);
+        let path = saved[0].as_ref().unwrap().path();
+        assert!(text.contains(&path.display().to_string()));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), output);
+    }
+
+    #[test]
+    fn test_large_user_text_is_capped() {
+        let output = "x".repeat(MAX_USER_TEXT_BYTES * 2);
+        let mut contents = vec![Content::text(output).with_audience(vec![Role::User])];
+        limit_user_text(&mut contents);
+
+        let text = contents[0].as_text().unwrap();
+        assert!(text.len() <= MAX_USER_TEXT_BYTES + 100);
+        assert!(text.contains(&format!("[... {} bytes omitted ...]", MAX_USER_TEXT_BYTES)));
+    }
+
+    #[test]
+    fn test_prune_removes_old_and_excess_outputs() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, len: usize, age: u64| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "x".repeat(len)).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("expired.txt", 10, 1_000);
+        write("oldest.txt", 10, 30);
+        write("newer.txt", 10, 20);
+        write("newest.txt", 10, 10);
+        write("notes.md", 10, 1_000);
+
+        prune(dir.path(), Duration::from_secs(100), 25).unwrap();
+
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["newer.txt", "newest.txt", "notes.md"]);
+    }
+}
@@ -1,14 +1,37 @@
-use keyring::Entry;
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
 use std::env;
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once, RwLock};
 use thiserror::Error;
 
+use super::extensions::EXTENSIONS_CONFIG_KEY;
+
+use super::secrets::{
+    EnvSecretStore, FileSecretStore, KeyringSecretStore, SecretStore, SecretsBackend,
+    SECRETS_BACKEND_CONFIG_KEY, SECRETS_FILE_NAME, SECRETS_PASSPHRASE_ENV,
+};
+
 const KEYRING_SERVICE: &str = "goose";
-const KEYRING_USERNAME: &str = "secrets";
+
+/// The directory holding a project's config, found by walking up from the working directory
+pub const PROJECT_CONFIG_DIR: &str = ".goose";
+const CONFIG_FILE_NAME: &str = "config.yaml";
+
+/// The key in the user's config listing the projects whose config is read
+pub const TRUSTED_PROJECTS_CONFIG_KEY: &str = "GOOSE_TRUSTED_PROJECTS";
+
+/// The keys a project config can set besides turning extensions on and off
+///
+/// None of them can point goose at another host, change where secrets are kept, or let
+/// tools run without approval.
+pub const PROJECT_CONFIG_KEYS: &[&str] =
+    &["GOOSE_MODEL", "GOOSE_TOOL_OUTPUT", "GOOSE_TOOL_EXECUTION"];
+
+static UNTRUSTED_PROJECT_WARNING: Once = Once::new();
 
 #[cfg(test)]
 const TEST_KEYRING_SERVICE: &str = "goose-test";
@@ -25,6 +48,8 @@ pub enum ConfigError {
     DirectoryError(String),
     #[error("Failed to access keyring: {0}")]
     KeyringError(String),
+    #[error("Failed to access secrets: {0}")]
+    SecretsError(String),
 }
 
 impl From<serde_json::Error> for ConfigError {
@@ -53,15 +78,29 @@ impl From<keyring::Error> for ConfigError {
 /// - Environment variable overrides
 /// - YAML-based configuration file storage
 /// - Hot reloading of configuration changes
-/// - Secure secret storage in system keyring
+/// - Secure secret storage in the system keyring or an encrypted file
 ///
-/// Configuration values are loaded with the following precedence:
-/// 1. Environment variables (exact key match)
-/// 2. Configuration file (~/.config/goose/config.yaml by default)
+/// Configuration values are loaded with the following precedence, highest first:
+/// 1. Values set on the command line with [`Config::set_flag`]
+/// 2. Environment variables (exact key match)
+/// 3. The project's `.goose/config.yaml`, in the working directory or the nearest parent
+///    that has one, once the user trusts the project. See [`Config::trust_project`]
+/// 4. The user's config file (~/.config/goose/config.yaml by default)
+/// 5. The system config file (/etc/goose/config.yaml on unix)
+///
+/// Values from the config files are merged, so a project can override one field of a map
+/// such as `extensions` and keep the rest from the user's config. Values from flags and
+/// environment variables replace the whole value. [`Config::set`] always writes to the
+/// user's config file.
+///
+/// A project config comes with the repository it's in, so it is limited to the `enabled`
+/// flags of extensions the user or system config defines, and the keys in
+/// [`PROJECT_CONFIG_KEYS`]. Anything else in it is ignored.
 ///
 /// Secrets are loaded with the following precedence:
 /// 1. Environment variables (exact key match)
-/// 2. System keyring
+/// 2. The backend chosen in `GOOSE_SECRETS_BACKEND`: the system keyring by default, a
+///    passphrase encrypted file beside the config file, or nothing beyond the environment
 ///
 /// # Examples
 ///
@@ -91,7 +130,144 @@ impl From<keyring::Error> for ConfigError {
 /// For Goose-specific configuration, consider prefixing with "goose_" to avoid conflicts.
 pub struct Config {
     config_path: PathBuf,
+    system_path: Option<PathBuf>,
+    /// Where to start looking for a project config
+    project_dir: Option<PathBuf>,
+    flags: RwLock<HashMap<String, Value>>,
     keyring_service: String,
+    secrets_passphrase: RwLock<Option<String>>,
+    /// The store of the current backend, kept so the encrypted file is only decrypted once
+    secret_store: Mutex<Option<(SecretsBackend, Arc<dyn SecretStore>)>>,
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// The environment variable the value was read from
+    Env(String),
+    Flag,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::System(path) => write!(f, "system {}", path.display()),
+            ConfigOrigin::User(path) => write!(f, "user {}", path.display()),
+            ConfigOrigin::Project(path) => write!(f, "project {}", path.display()),
+            ConfigOrigin::Env(name) => write!(f, "env {}", name),
+            ConfigOrigin::Flag => write!(f, "command line"),
+        }
+    }
+}
+
+/// The values of one config file
+type ConfigLayer = (ConfigOrigin, HashMap<String, Value>);
+
+/// The config files that values can be written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    User,
+    /// The nearest project config, or a new one in the working directory if there is none
+    Project,
+}
+
+/// The effective value of a key and the layers it was built from, lowest precedence first
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub value: Value,
+    pub origins: Vec<ConfigOrigin>,
+}
+
+/// The config file shared by every user of the machine
+fn system_config_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA")
+            .map(|dir| PathBuf::from(dir).join("goose").join(CONFIG_FILE_NAME))
+    } else {
+        Some(PathBuf::from("/etc/goose").join(CONFIG_FILE_NAME))
+    }
+}
+
+/// Find the nearest `.goose/config.yaml` in a directory or its parents
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_DIR).join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// The directory a project config belongs to, the one holding its `.goose` directory
+fn project_root(path: &Path) -> Option<PathBuf> {
+    path.parent()?.parent().map(Path::to_path_buf)
+}
+
+/// The values of a project config it's allowed to set, given the lower config layers
+fn project_values(
+    path: &Path,
+    values: HashMap<String, Value>,
+    lower: &[ConfigLayer],
+) -> HashMap<String, Value> {
+    let defined: HashSet<&String> = lower
+        .iter()
+        .filter_map(|(_, values)| values.get(EXTENSIONS_CONFIG_KEY)?.as_object())
+        .flat_map(|extensions| extensions.keys())
+        .collect();
+
+    let mut allowed = HashMap::new();
+    for (key, value) in values {
+        if PROJECT_CONFIG_KEYS.contains(&key.as_str()) {
+            allowed.insert(key, value);
+        } else if key == EXTENSIONS_CONFIG_KEY {
+            let mut extensions = serde_json::Map::new();
+            for (name, entry) in value.as_object().into_iter().flatten() {
+                match entry.get("enabled") {
+                    Some(Value::Bool(enabled)) if defined.contains(name) => {
+                        extensions.insert(
+                            name.clone(),
+                            serde_json::json!({ "enabled": enabled }),
+                        );
+                    }
+                    _ => tracing::debug!(
+                        "Ignoring extension {} in {}: projects can only turn on and off the extensions in the user's config",
+                        name,
+                        path.display()
+                    ),
+                }
+            }
+            allowed.insert(key, Value::Object(extensions));
+        } else {
+            tracing::debug!(
+                "Ignoring {} in {}: project configs can't set it",
+                key,
+                path.display()
+            );
+        }
+    }
+    allowed
+}
+
+/// Merge a value into another, recursing into maps so fields the new value doesn't set are kept
+fn merge_value(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Parse the value of an environment variable or flag, which is JSON or else a plain string
+fn parse_override(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
 }
 
 // Global instance
@@ -105,10 +281,15 @@ impl Default for Config {
             .join("goose");
         std::fs::create_dir_all(&config_dir).expect("Failed to create config directory");
 
-        let config_path = config_dir.join("config.yaml");
+        let config_path = config_dir.join(CONFIG_FILE_NAME);
         Config {
             config_path,
+            system_path: system_config_path(),
+            project_dir: env::current_dir().ok(),
+            flags: RwLock::new(HashMap::new()),
             keyring_service: KEYRING_SERVICE.to_string(),
+            secrets_passphrase: RwLock::new(None),
+            secret_store: Mutex::new(None),
         }
     }
 }
@@ -125,14 +306,33 @@ impl Config {
     /// Create a new configuration instance with custom paths
     ///
     /// This is primarily useful for testing or for applications that need
-    /// to manage multiple configuration files.
+    /// to manage multiple configuration files. The config has no system or project
+    /// layers unless they are added with [`Config::with_system_config`] and
+    /// [`Config::with_project_dir`].
     pub fn new<P: AsRef<Path>>(config_path: P, service: &str) -> Result<Self, ConfigError> {
         Ok(Config {
             config_path: config_path.as_ref().to_path_buf(),
+            system_path: None,
+            project_dir: None,
+            flags: RwLock::new(HashMap::new()),
             keyring_service: service.to_string(),
+            secrets_passphrase: RwLock::new(None),
+            secret_store: Mutex::new(None),
         })
     }
 
+    /// Read defaults from a system config file, below the user's config
+    pub fn with_system_config<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.system_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Look for a project config in a directory and its parents
+    pub fn with_project_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.project_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
     /// Check if this config already exists
     pub fn exists(&self) -> bool {
         self.config_path.exists()
@@ -148,10 +348,85 @@ impl Config {
         self.config_path.to_string_lossy().to_string()
     }
 
-    // Load current values from the config file
-    fn load_values(&self) -> Result<HashMap<String, Value>, ConfigError> {
-        if self.config_path.exists() {
-            let file_content = std::fs::read_to_string(&self.config_path)?;
+    /// The path of the nearest project config, if there is one
+    ///
+    /// Its values are only read once the project is trusted.
+    pub fn project_path(&self) -> Option<PathBuf> {
+        self.project_dir.as_deref().and_then(find_project_config)
+    }
+
+    /// The directory of the nearest project, or the working directory if there is none
+    pub fn project_root(&self) -> Option<PathBuf> {
+        self.project_path()
+            .and_then(|path| project_root(&path))
+            .or_else(|| self.project_dir.clone())
+    }
+
+    /// The project directories the user trusts, from the user's config alone
+    pub fn trusted_projects(&self) -> Vec<PathBuf> {
+        self.get_in(ConfigScope::User, TRUSTED_PROJECTS_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+
+    /// Whether the user trusts the config of the project in a directory
+    pub fn is_trusted_project(&self, dir: &Path) -> bool {
+        let canonical = |dir: &Path| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let dir = canonical(dir);
+        self.trusted_projects()
+            .iter()
+            .any(|trusted| canonical(trusted) == dir)
+    }
+
+    /// Read the config of the project in a directory from now on, returning the directory
+    /// as it's recorded in the user's config
+    pub fn trust_project(&self, dir: &Path) -> Result<PathBuf, ConfigError> {
+        let dir = dir.canonicalize()?;
+        let mut trusted = self.trusted_projects();
+        if !trusted.contains(&dir) {
+            trusted.push(dir.clone());
+            self.set(TRUSTED_PROJECTS_CONFIG_KEY, serde_json::to_value(&trusted)?)?;
+        }
+        Ok(dir)
+    }
+
+    /// Stop reading the config of a project, returning false if it wasn't trusted
+    pub fn untrust_project(&self, dir: &Path) -> Result<bool, ConfigError> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut trusted = self.trusted_projects();
+        let count = trusted.len();
+        trusted.retain(|trusted| trusted.canonicalize().unwrap_or_else(|_| trusted.clone()) != dir);
+        if trusted.len() == count {
+            return Ok(false);
+        }
+        self.set(TRUSTED_PROJECTS_CONFIG_KEY, serde_json::to_value(&trusted)?)?;
+        Ok(true)
+    }
+
+    /// The file values in a scope are read from and written to
+    fn scope_path(&self, scope: ConfigScope) -> Result<PathBuf, ConfigError> {
+        match scope {
+            ConfigScope::User => Ok(self.config_path.clone()),
+            ConfigScope::Project => match (self.project_path(), &self.project_dir) {
+                (Some(path), _) => Ok(path),
+                (None, Some(dir)) => Ok(dir.join(PROJECT_CONFIG_DIR).join(CONFIG_FILE_NAME)),
+                (None, None) => Err(ConfigError::DirectoryError(
+                    "No project directory to write the config to".to_string(),
+                )),
+            },
+        }
+    }
+
+    /// Override a value for the life of this process, e.g. from a command line flag
+    pub fn set_flag(&self, key: &str, value: Value) {
+        self.flags
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), value);
+    }
+
+    fn load_file(path: &Path) -> Result<HashMap<String, Value>, ConfigError> {
+        if path.exists() {
+            let file_content = std::fs::read_to_string(path)?;
             // Parse YAML into JSON Value for consistent internal representation
             let yaml_value: serde_yaml::Value = serde_yaml::from_str(&file_content)?;
             let json_value: Value = serde_json::to_value(yaml_value)?;
@@ -165,25 +440,186 @@ impl Config {
         }
     }
 
-    // Load current secrets from the keyring
-    fn load_secrets(&self) -> Result<HashMap<String, Value>, ConfigError> {
-        let entry = Entry::new(&self.keyring_service, KEYRING_USERNAME)?;
+    /// The values of each config file that exists, lowest precedence first
+    ///
+    /// The project config is left out unless the project is trusted, and limited to what
+    /// a project may set when it is.
+    fn file_layers(&self) -> Result<Vec<ConfigLayer>, ConfigError> {
+        let mut files = Vec::new();
+        if let Some(path) = &self.system_path {
+            files.push((path.clone(), ConfigOrigin::System(path.clone())));
+        }
+        files.push((
+            self.config_path.clone(),
+            ConfigOrigin::User(self.config_path.clone()),
+        ));
 
-        match entry.get_password() {
-            Ok(content) => {
-                let values: HashMap<String, Value> = serde_json::from_str(&content)?;
-                Ok(values)
+        let mut layers = Vec::new();
+        for (path, origin) in files {
+            if path.exists() {
+                layers.push((origin, Self::load_file(&path)?));
+            }
+        }
+
+        if let Some(path) = self.project_path() {
+            let root = project_root(&path).unwrap_or_default();
+            if self.is_trusted_project(&root) {
+                let values = project_values(&path, Self::load_file(&path)?, &layers);
+                layers.push((ConfigOrigin::Project(path), values));
+            } else {
+                UNTRUSTED_PROJECT_WARNING.call_once(|| {
+                    tracing::warn!(
+                        "Ignoring {} as the project isn't trusted, run 'goose config trust' to use it",
+                        path.display()
+                    )
+                });
+            }
+        }
+        Ok(layers)
+    }
+
+    /// Resolve the effective value of a key and where it came from
+    pub fn get_entry(&self, key: &str) -> Result<ConfigEntry, ConfigError> {
+        if let Some(value) = self
+            .flags
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+        {
+            return Ok(ConfigEntry {
+                value: value.clone(),
+                origins: vec![ConfigOrigin::Flag],
+            });
+        }
+
+        let env_key = key.to_uppercase();
+        if let Ok(val) = env::var(&env_key) {
+            return Ok(ConfigEntry {
+                value: parse_override(&val),
+                origins: vec![ConfigOrigin::Env(env_key)],
+            });
+        }
+
+        let mut entry: Option<ConfigEntry> = None;
+        for (origin, mut values) in self.file_layers()? {
+            let Some(value) = values.remove(key) else {
+                continue;
+            };
+            match &mut entry {
+                Some(entry) => {
+                    merge_value(&mut entry.value, value);
+                    entry.origins.push(origin);
+                }
+                None => {
+                    entry = Some(ConfigEntry {
+                        value,
+                        origins: vec![origin],
+                    })
+                }
+            }
+        }
+        entry.ok_or_else(|| ConfigError::NotFound(key.to_string()))
+    }
+
+    /// Every key set in a config file or by a flag, with its effective value
+    ///
+    /// Environment variables only show up here when they override a key that is set
+    /// somewhere else, as there is no telling which variables are meant for goose.
+    pub fn entries(&self) -> Result<BTreeMap<String, ConfigEntry>, ConfigError> {
+        let mut keys: Vec<String> = self
+            .flags
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        for (_, values) in self.file_layers()? {
+            keys.extend(values.into_keys());
+        }
+
+        let mut entries = BTreeMap::new();
+        for key in keys {
+            if let btree_map::Entry::Vacant(vacant) = entries.entry(key) {
+                let entry = self.get_entry(vacant.key())?;
+                vacant.insert(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Read this config's values and secrets under their own names
+    pub fn resolver(&self) -> ConfigResolver<'_> {
+        ConfigResolver {
+            config: self,
+            refs: None,
+        }
+    }
+
+    /// The backend secrets are stored in, the keyring unless `GOOSE_SECRETS_BACKEND` is set
+    pub fn secrets_backend(&self) -> SecretsBackend {
+        self.get(SECRETS_BACKEND_CONFIG_KEY).unwrap_or_default()
+    }
+
+    /// The path of the encrypted secrets file, beside the user's config file
+    pub fn secrets_path(&self) -> PathBuf {
+        self.config_path.with_file_name(SECRETS_FILE_NAME)
+    }
+
+    /// Unlock the encrypted secrets file with a passphrase
+    ///
+    /// Without one, the passphrase is read from `GOOSE_SECRETS_PASSPHRASE`.
+    pub fn set_secrets_passphrase(&self, passphrase: String) {
+        *self
+            .secrets_passphrase
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(passphrase);
+        *self.secret_store.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Whether the encrypted secrets file has a passphrase to unlock it with
+    pub fn has_secrets_passphrase(&self) -> bool {
+        self.secrets_passphrase().is_some()
+    }
+
+    fn secrets_passphrase(&self) -> Option<String> {
+        self.secrets_passphrase
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .or_else(|| env::var(SECRETS_PASSPHRASE_ENV).ok())
+    }
+
+    /// A store for the secrets in a backend
+    pub fn secret_store_for(&self, backend: SecretsBackend) -> Box<dyn SecretStore> {
+        match backend {
+            SecretsBackend::Keyring => Box::new(KeyringSecretStore::new(&self.keyring_service)),
+            SecretsBackend::File => Box::new(FileSecretStore::new(
+                self.secrets_path(),
+                self.secrets_passphrase(),
+            )),
+            SecretsBackend::Env => Box::new(EnvSecretStore),
+        }
+    }
+
+    fn secret_store(&self) -> Arc<dyn SecretStore> {
+        let backend = self.secrets_backend();
+        let mut store = self.secret_store.lock().unwrap_or_else(|e| e.into_inner());
+        match store.as_ref() {
+            Some((current, store)) if *current == backend => store.clone(),
+            _ => {
+                let created: Arc<dyn SecretStore> = self.secret_store_for(backend).into();
+                *store = Some((backend, created.clone()));
+                created
             }
-            Err(keyring::Error::NoEntry) => Ok(HashMap::new()),
-            Err(e) => Err(ConfigError::KeyringError(e.to_string())),
         }
     }
 
     /// Get a configuration value.
     ///
     /// This will attempt to get the value from:
-    /// 1. Environment variable with the exact key name
-    /// 2. Configuration file
+    /// 1. A command line flag
+    /// 2. Environment variable with the exact key name
+    /// 3. The project, user and system config files, merged
     ///
     /// The value will be deserialized into the requested type. This works with
     /// both simple types (String, i32, etc.) and complex types that implement
@@ -192,26 +628,24 @@ impl Config {
     /// # Errors
     ///
     /// Returns a ConfigError if:
-    /// - The key doesn't exist in either environment or config file
+    /// - The key isn't set in any layer
     /// - The value cannot be deserialized into the requested type
-    /// - There is an error reading the config file
+    /// - There is an error reading a config file
     pub fn get<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
-        // First check environment variables (convert to uppercase)
-        let env_key = key.to_uppercase();
-        if let Ok(val) = env::var(&env_key) {
-            // Parse the environment variable value into a serde_json::Value
-            let value: Value = serde_json::from_str(&val).unwrap_or(Value::String(val));
-            return Ok(serde_json::from_value(value)?);
-        }
-
-        // Load current values from file
-        let values = self.load_values()?;
+        Ok(serde_json::from_value(self.get_entry(key)?.value)?)
+    }
 
-        // Then check our stored values
-        values
-            .get(key)
+    /// Get a value from one config file alone, e.g. to change it and write it back with
+    /// [`Config::set_in`] without copying values from the other layers
+    pub fn get_in<T: for<'de> Deserialize<'de>>(
+        &self,
+        scope: ConfigScope,
+        key: &str,
+    ) -> Result<T, ConfigError> {
+        Self::load_file(&self.scope_path(scope)?)?
+            .remove(key)
             .ok_or_else(|| ConfigError::NotFound(key.to_string()))
-            .and_then(|v| Ok(serde_json::from_value(v.clone())?))
+            .and_then(|v| Ok(serde_json::from_value(v)?))
     }
 
     /// Set a configuration value in the config file.
@@ -228,19 +662,25 @@ impl Config {
     /// - There is an error reading or writing the config file
     /// - There is an error serializing the value
     pub fn set(&self, key: &str, value: Value) -> Result<(), ConfigError> {
-        let mut values = self.load_values()?;
+        self.set_in(ConfigScope::User, key, value)
+    }
+
+    /// Set a value in the user's or the project's config file
+    pub fn set_in(&self, scope: ConfigScope, key: &str, value: Value) -> Result<(), ConfigError> {
+        let path = self.scope_path(scope)?;
+        let mut values = Self::load_file(&path)?;
         values.insert(key.to_string(), value);
 
         // Convert to YAML for storage
         let yaml_value = serde_yaml::to_string(&values)?;
 
         // Ensure the directory exists
-        if let Some(parent) = self.config_path.parent() {
+        if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| ConfigError::DirectoryError(e.to_string()))?;
         }
 
-        std::fs::write(&self.config_path, yaml_value)?;
+        std::fs::write(&path, yaml_value)?;
         Ok(())
     }
 
@@ -257,9 +697,9 @@ impl Config {
     /// # Errors
     ///
     /// Returns a ConfigError if:
-    /// - The key doesn't exist in either environment or keyring
+    /// - The key doesn't exist in either environment or the secrets backend
     /// - The value cannot be deserialized into the requested type
-    /// - There is an error accessing the keyring
+    /// - There is an error accessing the secrets backend
     pub fn get_secret<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
         // First check environment variables (convert to uppercase)
         let env_key = key.to_uppercase();
@@ -268,17 +708,17 @@ impl Config {
             return Ok(serde_json::from_value(value)?);
         }
 
-        // Then check keyring
-        let values = self.load_secrets()?;
+        // Then check the secrets backend
+        let values = self.secret_store().load()?;
         values
             .get(key)
             .ok_or_else(|| ConfigError::NotFound(key.to_string()))
             .and_then(|v| Ok(serde_json::from_value(v.clone())?))
     }
 
-    /// Set a secret value in the system keyring.
+    /// Set a secret value in the secrets backend.
     ///
-    /// This will store the value in a single JSON object in the system keyring,
+    /// This will store the value in a single JSON object in the backend,
     /// alongside any other secrets. The value can be any type that can be
     /// serialized to JSON.
     ///
@@ -288,42 +728,84 @@ impl Config {
     /// # Errors
     ///
     /// Returns a ConfigError if:
-    /// - There is an error accessing the keyring
+    /// - There is an error accessing the secrets backend, or it is read only
     /// - There is an error serializing the value
     pub fn set_secret(&self, key: &str, value: Value) -> Result<(), ConfigError> {
-        let mut values = self.load_secrets()?;
+        let store = self.secret_store();
+        let mut values = store.load()?;
         values.insert(key.to_string(), value);
-
-        let json_value = serde_json::to_string(&values)?;
-        let entry = Entry::new(&self.keyring_service, KEYRING_USERNAME)?;
-        entry.set_password(&json_value)?;
-        Ok(())
+        store.save(&values)
     }
 
-    /// Delete a secret from the system keyring.
+    /// Delete a secret from the secrets backend.
     ///
-    /// This will remove the specified key from the JSON object in the system keyring.
+    /// This will remove the specified key from the JSON object in the backend.
     /// Other secrets will remain unchanged.
     ///
     /// # Errors
     ///
     /// Returns a ConfigError if:
-    /// - There is an error accessing the keyring
+    /// - There is an error accessing the secrets backend, or it is read only
     /// - There is an error serializing the remaining values
     pub fn delete_secret(&self, key: &str) -> Result<(), ConfigError> {
-        let mut values = self.load_secrets()?;
+        let store = self.secret_store();
+        let mut values = store.load()?;
         values.remove(key);
+        store.save(&values)
+    }
+}
 
-        let json_value = serde_json::to_string(&values)?;
-        let entry = Entry::new(&self.keyring_service, KEYRING_USERNAME)?;
-        entry.set_password(&json_value)?;
-        Ok(())
+/// Reads a config's values and secrets, some of them under other names
+///
+/// Reading the key `key` reads `refs[key]` instead, e.g. so a profile can point
+/// `OPENAI_API_KEY` at a work key stored as `OPENAI_API_KEY_WORK`, or `OLLAMA_HOST` at
+/// `OLLAMA_HOST_LAPTOP`. The config itself is left alone, so nothing else reads through
+/// the references.
+#[derive(Clone, Copy)]
+pub struct ConfigResolver<'a> {
+    config: &'a Config,
+    refs: Option<&'a HashMap<String, String>>,
+}
+
+impl<'a> ConfigResolver<'a> {
+    pub fn new(config: &'a Config, refs: &'a HashMap<String, String>) -> Self {
+        Self {
+            config,
+            refs: Some(refs),
+        }
+    }
+
+    /// The config read through the references
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
+
+    /// The name a key is read under
+    fn resolve<'k>(&self, key: &'k str) -> &'k str
+    where
+        'a: 'k,
+    {
+        self.refs
+            .and_then(|refs| refs.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// Get a value as [`Config::get`] does, under the name the key refers to
+    pub fn get<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
+        self.config.get(self.resolve(key))
+    }
+
+    /// Get a secret as [`Config::get_secret`] does, under the name the key refers to
+    pub fn get_secret<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
+        self.config.get_secret(self.resolve(key))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::secrets::KEYRING_USERNAME;
+    use keyring::Entry;
     use serial_test::serial;
     use tempfile::NamedTempFile;
 
@@ -383,6 +865,161 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_layered_config() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let system = dir.path().join("system.yaml");
+        let user = dir.path().join("user.yaml");
+        std::fs::write(
+            &system,
+            "goose_layer_model: system-model\ngoose_layer_temp: 0.5\n",
+        )?;
+        std::fs::write(
+            &user,
+            "goose_layer_model: user-model\nextensions:\n  jetbrains:\n    enabled: true\n    type: builtin\n    name: jetbrains\n",
+        )?;
+
+        // The project config is found from a directory nested inside the project
+        let project = dir.path().join("repo");
+        let nested = project.join("src").join("agents");
+        std::fs::create_dir_all(&nested)?;
+        std::fs::create_dir_all(project.join(PROJECT_CONFIG_DIR))?;
+        let project_file = project.join(PROJECT_CONFIG_DIR).join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &project_file,
+            "GOOSE_TOOL_OUTPUT:\n  max_lines: 20\nextensions:\n  jetbrains:\n    enabled: false\n",
+        )?;
+
+        let config = Config::new(&user, TEST_KEYRING_SERVICE)?
+            .with_system_config(&system)
+            .with_project_dir(&nested);
+        assert_eq!(config.project_path(), Some(project_file.clone()));
+        assert_eq!(config.project_root(), Some(project.clone()));
+
+        let model: String = config.get("goose_layer_model")?;
+        assert_eq!(model, "user-model");
+        let temp: f32 = config.get("goose_layer_temp")?;
+        assert_eq!(temp, 0.5);
+
+        // The project config is only read once the project is trusted
+        assert!(config.get::<Value>("GOOSE_TOOL_OUTPUT").is_err());
+        config.trust_project(&project)?;
+        assert!(config.is_trusted_project(&project));
+        assert_eq!(config.get::<Value>("GOOSE_TOOL_OUTPUT")?["max_lines"], 20);
+
+        // Maps are merged, so the project only changes the field it sets
+        let extensions = config.get_entry("extensions")?;
+        assert_eq!(
+            extensions.value,
+            serde_json::json!({"jetbrains": {"enabled": false, "type": "builtin", "name": "jetbrains"}})
+        );
+        assert_eq!(
+            extensions.origins,
+            vec![
+                ConfigOrigin::User(user.clone()),
+                ConfigOrigin::Project(project_file.clone())
+            ]
+        );
+
+        // Writing to one file leaves the values of the other layers out of it
+        config.set("goose_layer_temp", serde_json::json!(0.7))?;
+        let user_model: String = config.get_in(ConfigScope::User, "goose_layer_model")?;
+        assert_eq!(user_model, "user-model");
+        config.set_in(
+            ConfigScope::Project,
+            "GOOSE_TOOL_OUTPUT",
+            serde_json::json!({"max_lines": 30}),
+        )?;
+        assert_eq!(config.get::<Value>("GOOSE_TOOL_OUTPUT")?["max_lines"], 30);
+        let temp: f32 = config.get("goose_layer_temp")?;
+        assert_eq!(temp, 0.7);
+
+        // Flags beat everything
+        config.set_flag("goose_layer_model", Value::String("flag-model".to_string()));
+        let entries = config.entries()?;
+        assert_eq!(entries["goose_layer_model"].value, "flag-model");
+        assert_eq!(
+            entries["goose_layer_model"].origins,
+            vec![ConfigOrigin::Flag]
+        );
+        assert_eq!(
+            entries["GOOSE_TOOL_OUTPUT"].origins,
+            vec![ConfigOrigin::Project(project_file)]
+        );
+
+        assert!(config.untrust_project(&project)?);
+        assert!(!config.untrust_project(&project)?);
+        assert!(config.get::<Value>("GOOSE_TOOL_OUTPUT").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_config_is_limited() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user.yaml");
+        std::fs::write(
+            &user,
+            "goose_limited_model: user-model\nextensions:\n  developer:\n    enabled: false\n    type: builtin\n    name: developer\n",
+        )?;
+        std::fs::create_dir_all(dir.path().join(PROJECT_CONFIG_DIR))?;
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_DIR).join(CONFIG_FILE_NAME),
+            concat!(
+                "goose_limited_model: project-model\n",
+                "GOOSE_SECRETS_BACKEND: file\n",
+                "GOOSE_TOOL_APPROVAL:\n  default: allow\n",
+                "OPENAI_HOST: https://example.com\n",
+                "extensions:\n",
+                "  developer:\n    enabled: true\n    type: stdio\n    cmd: sh\n",
+                "  exfiltrate:\n    enabled: true\n    type: stdio\n    name: exfiltrate\n    cmd: sh\n",
+            ),
+        )?;
+
+        let config = Config::new(&user, TEST_KEYRING_SERVICE)?.with_project_dir(dir.path());
+        config.trust_project(dir.path())?;
+
+        // Only the enabled flag of an extension the user added is taken
+        assert_eq!(
+            config.get::<Value>("extensions")?,
+            serde_json::json!({"developer": {"enabled": true, "type": "builtin", "name": "developer"}})
+        );
+        // Checked on the project layer itself, as the environment may set these
+        let (_, project_values) = config.file_layers()?.pop().unwrap();
+        let mut keys: Vec<_> = project_values.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["extensions"]);
+        let model: String = config.get("goose_limited_model")?;
+        assert_eq!(model, "user-model");
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_refs() -> Result<(), ConfigError> {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = Config::new(temp_file.path(), TEST_KEYRING_SERVICE)?;
+        std::env::set_var("GOOSE_TEST_WORK_API_KEY", "work-key");
+
+        let refs = HashMap::from([(
+            "GOOSE_TEST_API_KEY".to_string(),
+            "GOOSE_TEST_WORK_API_KEY".to_string(),
+        )]);
+        let resolver = ConfigResolver::new(&config, &refs);
+        let value: String = resolver.get_secret("GOOSE_TEST_API_KEY")?;
+        assert_eq!(value, "work-key");
+        let value: String = resolver.get("GOOSE_TEST_API_KEY")?;
+        assert_eq!(value, "work-key");
+
+        // The config itself still reads the key under its own name
+        let result: Result<String, ConfigError> = config.get_secret("GOOSE_TEST_API_KEY");
+        assert!(result.is_err());
+        let result: Result<String, ConfigError> = config.resolver().get("GOOSE_TEST_API_KEY");
+        assert!(result.is_err());
+
+        std::env::remove_var("GOOSE_TEST_WORK_API_KEY");
+        Ok(())
+    }
+
     #[test]
     fn test_missing_value() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -464,4 +1101,29 @@ mod tests {
         cleanup_keyring()?;
         Ok(())
     }
+
+    #[test]
+    #[serial]
+    fn test_file_secrets_backend() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(dir.path().join(CONFIG_FILE_NAME), TEST_KEYRING_SERVICE)?;
+        config.set(SECRETS_BACKEND_CONFIG_KEY, "file".into())?;
+        assert_eq!(config.secrets_backend(), SecretsBackend::File);
+        config.set_secrets_passphrase("correct horse".to_string());
+
+        config.set_secret("file_api_key", Value::String("secret123".to_string()))?;
+        let value: String = config.get_secret("file_api_key")?;
+        assert_eq!(value, "secret123");
+        assert!(config.secrets_path().exists());
+
+        config.delete_secret("file_api_key")?;
+        let result: Result<String, ConfigError> = config.get_secret("file_api_key");
+        assert!(matches!(result, Err(ConfigError::NotFound(_))));
+
+        // The env backend only reads environment variables
+        config.set_flag(SECRETS_BACKEND_CONFIG_KEY, "env".into());
+        let result = config.set_secret("file_api_key", Value::String("secret123".to_string()));
+        assert!(matches!(result, Err(ConfigError::SecretsError(_))));
+        Ok(())
+    }
 }
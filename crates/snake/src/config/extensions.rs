@@ -1,10 +1,12 @@
-use super::base::Config;
-use crate::agents::ExtensionConfig;
+use super::base::{Config, ConfigScope};
+use crate::agents::{ExtensionConfig, ToolFilter};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 const DEFAULT_EXTENSION: &str = "developer";
+pub(super) const EXTENSIONS_CONFIG_KEY: &str = "extensions";
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ExtensionEntry {
@@ -14,16 +16,50 @@ pub struct ExtensionEntry {
 }
 
 /// Extension configuration management
+///
+/// Extensions are added in the user's config. A trusted project's `.goose/config.yaml` can
+/// turn them on or off for that project with the same `extensions` map:
+///
+/// ```yaml
+/// extensions:
+///   jetbrains:
+///     enabled: false
+/// ```
 pub struct ExtensionManager;
 
+/// The effective extensions, merged from every config layer
+///
+/// An entry that isn't complete after merging is skipped. Projects can't add extensions, as
+/// [`Config`] drops anything but the `enabled` flags of those the user added.
+fn effective_extensions(config: &Config) -> HashMap<String, ExtensionEntry> {
+    let values: HashMap<String, Value> = config.get(EXTENSIONS_CONFIG_KEY).unwrap_or_default();
+    values
+        .into_iter()
+        .filter_map(|(name, value)| match serde_json::from_value(value) {
+            Ok(entry) => Some((name, entry)),
+            Err(e) => {
+                tracing::warn!("Skipping extension {}: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The extensions in one config file, kept as written so partial entries survive a rewrite
+fn scoped_extensions(config: &Config, scope: ConfigScope) -> HashMap<String, Value> {
+    config
+        .get_in(scope, EXTENSIONS_CONFIG_KEY)
+        .unwrap_or_default()
+}
+
 impl ExtensionManager {
     /// Get the extension configuration if enabled
     pub fn get_config(name: &str) -> Result<Option<ExtensionConfig>> {
         let config = Config::global();
 
         // Try to get the extension entry
-        let extensions: HashMap<String, ExtensionEntry> = match config.get("extensions") {
-            Ok(exts) => exts,
+        let extensions = match config.get_in::<Value>(ConfigScope::User, EXTENSIONS_CONFIG_KEY) {
+            Ok(_) => effective_extensions(config),
             Err(super::ConfigError::NotFound(_)) => {
                 // Initialize with default developer extension
                 let defaults = HashMap::from([(
@@ -32,11 +68,12 @@ impl ExtensionManager {
                         enabled: true,
                         config: ExtensionConfig::Builtin {
                             name: DEFAULT_EXTENSION.to_string(),
+                            tools: ToolFilter::default(),
                         },
                     },
                 )]);
-                config.set("extensions", serde_json::to_value(&defaults)?)?;
-                defaults
+                config.set(EXTENSIONS_CONFIG_KEY, serde_json::to_value(&defaults)?)?;
+                effective_extensions(config)
             }
             Err(e) => return Err(e.into()),
         };
@@ -54,11 +91,10 @@ impl ExtensionManager {
     pub fn set(entry: ExtensionEntry) -> Result<()> {
         let config = Config::global();
 
-        let mut extensions: HashMap<String, ExtensionEntry> =
-            config.get("extensions").unwrap_or_else(|_| HashMap::new());
+        let mut extensions = scoped_extensions(config, ConfigScope::User);
 
-        extensions.insert(entry.config.name().parse()?, entry);
-        config.set("extensions", serde_json::to_value(extensions)?)?;
+        extensions.insert(entry.config.name().parse()?, serde_json::to_value(entry)?);
+        config.set(EXTENSIONS_CONFIG_KEY, serde_json::to_value(extensions)?)?;
         Ok(())
     }
 
@@ -66,49 +102,62 @@ impl ExtensionManager {
     pub fn remove(name: &str) -> Result<()> {
         let config = Config::global();
 
-        let mut extensions: HashMap<String, ExtensionEntry> =
-            config.get("extensions").unwrap_or_else(|_| HashMap::new());
+        let mut extensions = scoped_extensions(config, ConfigScope::User);
 
         extensions.remove(name);
-        config.set("extensions", serde_json::to_value(extensions)?)?;
+        config.set(EXTENSIONS_CONFIG_KEY, serde_json::to_value(extensions)?)?;
         Ok(())
     }
 
     /// Enable or disable an extension
     pub fn set_enabled(name: &str, enabled: bool) -> Result<()> {
-        let config = Config::global();
+        Self::set_enabled_in(ConfigScope::User, name, enabled)
+    }
 
-        let mut extensions: HashMap<String, ExtensionEntry> =
-            config.get("extensions").unwrap_or_else(|_| HashMap::new());
+    /// Enable or disable an extension for everyone using the user's config, or only in the
+    /// current project
+    ///
+    /// A project can only enable extensions that are configured somewhere, so this does
+    /// nothing for unknown names.
+    pub fn set_enabled_in(scope: ConfigScope, name: &str, enabled: bool) -> Result<()> {
+        let config = Config::global();
+        let configured: HashMap<String, Value> =
+            config.get(EXTENSIONS_CONFIG_KEY).unwrap_or_default();
+        if !configured.contains_key(name) {
+            return Ok(());
+        }
 
-        if let Some(entry) = extensions.get_mut(name) {
-            entry.enabled = enabled;
-            config.set("extensions", serde_json::to_value(extensions)?)?;
+        let mut extensions = scoped_extensions(config, scope);
+        let entry = extensions
+            .entry(name.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(entry) = entry {
+            entry.insert("enabled".to_string(), Value::Bool(enabled));
         }
+        config.set_in(
+            scope,
+            EXTENSIONS_CONFIG_KEY,
+            serde_json::to_value(extensions)?,
+        )?;
         Ok(())
     }
 
     /// Get all extensions and their configurations
     pub fn get_all() -> Result<Vec<ExtensionEntry>> {
         let config = Config::global();
-        let extensions: HashMap<String, ExtensionEntry> =
-            config.get("extensions").unwrap_or_default();
-        Ok(Vec::from_iter(extensions.values().cloned()))
+        Ok(effective_extensions(config).into_values().collect())
     }
 
     /// Get all extension names
     pub fn get_all_names() -> Result<Vec<String>> {
         let config = Config::global();
-        Ok(config
-            .get("extensions")
-            .unwrap_or_else(|_| get_keys(Default::default())))
+        Ok(get_keys(effective_extensions(config)))
     }
 
     /// Check if an extension is enabled
     pub fn is_enabled(name: &str) -> Result<bool> {
         let config = Config::global();
-        let extensions: HashMap<String, ExtensionEntry> =
-            config.get("extensions").unwrap_or_else(|_| HashMap::new());
+        let extensions = effective_extensions(config);
 
         Ok(extensions.get(name).map(|e| e.enabled).unwrap_or(false))
     }
@@ -1,6 +1,16 @@
 mod base;
 mod extensions;
+mod profiles;
+mod secrets;
 
 pub use crate::agents::ExtensionConfig;
-pub use base::{Config, ConfigError};
+pub use base::{
+    find_project_config, Config, ConfigEntry, ConfigError, ConfigOrigin, ConfigResolver,
+    ConfigScope, PROJECT_CONFIG_DIR, PROJECT_CONFIG_KEYS, TRUSTED_PROJECTS_CONFIG_KEY,
+};
 pub use extensions::{ExtensionEntry, ExtensionManager};
+pub use profiles::{Profile, ProfileManager, PROFILES_CONFIG_KEY, PROFILE_CONFIG_KEY};
+pub use secrets::{
+    EnvSecretStore, FileSecretStore, KeyringSecretStore, SecretStore, SecretsBackend,
+    SECRETS_BACKEND_CONFIG_KEY, SECRETS_FILE_NAME, SECRETS_PASSPHRASE_ENV,
+};
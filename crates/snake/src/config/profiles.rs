@@ -0,0 +1,216 @@
+use super::base::{Config, ConfigError, ConfigResolver, ConfigScope};
+use super::extensions::ExtensionManager;
+use crate::agents::{Agent, AgentFactory, ExtensionConfig};
+use crate::model::ModelConfig;
+use crate::providers::{self, base::Provider};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// The config key holding the named profiles
+pub const PROFILES_CONFIG_KEY: &str = "profiles";
+/// The config key naming the profile to use when none is chosen on the command line
+pub const PROFILE_CONFIG_KEY: &str = "GOOSE_PROFILE";
+
+/// A named set of provider, model and agent settings to switch between
+///
+/// ```yaml
+/// profiles:
+///   work:
+///     provider: openai
+///     model: gpt-4o
+///     temperature: 0.2
+///     agent_version: truncate
+///     extensions: [developer, jetbrains]
+///     secrets:
+///       OPENAI_API_KEY: OPENAI_API_KEY_WORK
+///   laptop:
+///     provider: ollama
+///     model: qwen2.5
+///     secrets:
+///       OLLAMA_HOST: OLLAMA_HOST_LAPTOP
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub provider: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// The agent version to use, or the default version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_version: Option<String>,
+    /// The names of the extensions to enable, in place of those enabled in the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+    /// Secrets and settings the provider reads, mapped to the names to read instead
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, String>,
+}
+
+impl Profile {
+    /// The profile with a name, or the profile chosen in `GOOSE_PROFILE`
+    ///
+    /// Without either, the profile is made from the provider, model and agent version set
+    /// with `goose configure`.
+    pub fn from_config(config: &Config, name: Option<&str>) -> Result<Self> {
+        let name = match name {
+            Some(name) => Some(name.to_string()),
+            None => config.get(PROFILE_CONFIG_KEY).ok(),
+        };
+        if let Some(name) = name {
+            let mut profiles = profiles(config)?;
+            return profiles
+                .remove(&name)
+                .ok_or_else(|| anyhow!("Profile '{}' not found", name));
+        }
+
+        Ok(Profile {
+            provider: config
+                .get("GOOSE_PROVIDER")
+                .map_err(|_| anyhow!("No provider configured. Run 'goose configure' first"))?,
+            model: config
+                .get("GOOSE_MODEL")
+                .map_err(|_| anyhow!("No model configured. Run 'goose configure' first"))?,
+            agent_version: config.get("GOOSE_AGENT").ok(),
+            ..Default::default()
+        })
+    }
+
+    pub fn model_config(&self) -> ModelConfig {
+        ModelConfig::new(self.model.clone()).with_temperature(self.temperature)
+    }
+
+    /// Create the profile's provider, reading its secrets and settings through the
+    /// profile's references
+    pub fn create_provider(&self) -> Result<Box<dyn Provider + Send + Sync>> {
+        let config = ConfigResolver::new(Config::global(), &self.secrets);
+        providers::create(&self.provider, self.model_config(), &config)
+    }
+
+    /// Create an agent of the profile's version with its provider, without extensions
+    pub fn create_agent(&self) -> Result<Box<dyn Agent>> {
+        let version = self
+            .agent_version
+            .as_deref()
+            .unwrap_or(AgentFactory::default_version());
+        AgentFactory::create(version, self.create_provider()?)
+            .ok_or_else(|| anyhow!("Unknown agent version '{}'", version))
+    }
+
+    /// The configs of the extensions to start with the profile
+    pub fn extensions(&self) -> Result<Vec<ExtensionConfig>> {
+        let entries = ExtensionManager::get_all()?;
+        let Some(names) = &self.extensions else {
+            return Ok(entries
+                .into_iter()
+                .filter(|entry| entry.enabled)
+                .map(|entry| entry.config)
+                .collect());
+        };
+
+        let mut configs = Vec::new();
+        for name in names {
+            match entries.iter().find(|entry| entry.config.name() == name) {
+                Some(entry) => configs.push(entry.config.clone()),
+                None => tracing::warn!("Profile extension {} is not configured", name),
+            }
+        }
+        Ok(configs)
+    }
+}
+
+fn profiles(config: &Config) -> Result<BTreeMap<String, Profile>, ConfigError> {
+    match config.get(PROFILES_CONFIG_KEY) {
+        Err(ConfigError::NotFound(_)) => Ok(BTreeMap::new()),
+        result => result,
+    }
+}
+
+/// The profiles in the user's config alone, so rewriting them doesn't copy in those from
+/// the other layers
+fn user_profiles(config: &Config) -> Result<BTreeMap<String, Profile>, ConfigError> {
+    match config.get_in(ConfigScope::User, PROFILES_CONFIG_KEY) {
+        Err(ConfigError::NotFound(_)) => Ok(BTreeMap::new()),
+        result => result,
+    }
+}
+
+/// Profile configuration management
+pub struct ProfileManager;
+
+impl ProfileManager {
+    /// All profiles by name
+    pub fn get_all() -> Result<BTreeMap<String, Profile>> {
+        Ok(profiles(Config::global())?)
+    }
+
+    pub fn get(name: &str) -> Result<Option<Profile>> {
+        Ok(Self::get_all()?.remove(name))
+    }
+
+    /// Add a profile or replace the one with the same name
+    pub fn set(name: &str, profile: Profile) -> Result<()> {
+        let config = Config::global();
+        let mut profiles = user_profiles(config)?;
+        profiles.insert(name.to_string(), profile);
+        config.set(PROFILES_CONFIG_KEY, serde_json::to_value(profiles)?)?;
+        Ok(())
+    }
+
+    pub fn remove(name: &str) -> Result<()> {
+        let config = Config::global();
+        let mut profiles = user_profiles(config)?;
+        profiles.remove(name);
+        config.set(PROFILES_CONFIG_KEY, serde_json::to_value(profiles)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_profile_from_config() -> Result<()> {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(
+            temp_file.path(),
+            "GOOSE_PROVIDER: openai\n\
+             GOOSE_MODEL: gpt-4o\n\
+             OLLAMA_HOST_LAPTOP: http://laptop:11434\n\
+             profiles:\n  \
+               local:\n    \
+                 provider: ollama\n    \
+                 model: qwen2.5\n    \
+                 temperature: 0.2\n    \
+                 extensions: [developer]\n    \
+                 secrets:\n      \
+                   OLLAMA_HOST: OLLAMA_HOST_LAPTOP\n",
+        )?;
+        let config = Config::new(temp_file.path(), "goose-test")?;
+
+        let local = Profile::from_config(&config, Some("local"))?;
+        assert_eq!(local.provider, "ollama");
+        assert_eq!(local.model_config().temperature, Some(0.2));
+        assert_eq!(local.extensions, Some(vec!["developer".to_string()]));
+        assert_eq!(local.secrets["OLLAMA_HOST"], "OLLAMA_HOST_LAPTOP");
+        // The references apply to settings as well as secrets
+        let host: String = ConfigResolver::new(&config, &local.secrets).get("OLLAMA_HOST")?;
+        assert_eq!(host, "http://laptop:11434");
+
+        // Without a profile, the configured provider and model are used
+        let default = Profile::from_config(&config, None)?;
+        assert_eq!(default.provider, "openai");
+        assert_eq!(default.model, "gpt-4o");
+        assert_eq!(default.extensions, None);
+
+        // The default profile can be chosen in the config too
+        config.set(PROFILE_CONFIG_KEY, "local".into())?;
+        assert_eq!(Profile::from_config(&config, None)?, local);
+
+        let missing = Profile::from_config(&config, Some("missing")).unwrap_err();
+        assert_eq!(missing.to_string(), "Profile 'missing' not found");
+        Ok(())
+    }
+}
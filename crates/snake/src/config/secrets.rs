@@ -0,0 +1,387 @@
+//! Where secrets are stored
+//!
+//! Secrets are kept together as one JSON object of values, which is loaded and saved as a
+//! whole by a [`SecretStore`]. Three backends are provided:
+//! - [`KeyringSecretStore`]: one entry in the system keyring, the default.
+//! - [`FileSecretStore`]: a file under `~/.config/goose/` encrypted with a passphrase, for
+//!   machines without a keyring such as headless servers and containers.
+//! - [`EnvSecretStore`]: nothing is stored, secrets are only read from environment variables.
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use keyring::Entry;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::base::ConfigError;
+
+/// The config key choosing the backend secrets are stored in
+pub const SECRETS_BACKEND_CONFIG_KEY: &str = "GOOSE_SECRETS_BACKEND";
+/// The environment variable holding the passphrase of the encrypted secrets file
+pub const SECRETS_PASSPHRASE_ENV: &str = "GOOSE_SECRETS_PASSPHRASE";
+/// The name of the encrypted secrets file, beside the config file
+pub const SECRETS_FILE_NAME: &str = "secrets.enc";
+
+pub(super) const KEYRING_USERNAME: &str = "secrets";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// The most iterations a secrets file can ask for, so a tampered file can't hang goose
+const MAX_PBKDF2_ITERATIONS: u32 = 10 * PBKDF2_ITERATIONS;
+const SALT_LEN: usize = 16;
+
+/// The backends secrets can be stored in
+///
+/// ```yaml
+/// GOOSE_SECRETS_BACKEND: file
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsBackend {
+    #[default]
+    Keyring,
+    /// A file encrypted with the passphrase in `GOOSE_SECRETS_PASSPHRASE`
+    File,
+    /// Environment variables only
+    Env,
+}
+
+impl fmt::Display for SecretsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretsBackend::Keyring => write!(f, "keyring"),
+            SecretsBackend::File => write!(f, "file"),
+            SecretsBackend::Env => write!(f, "env"),
+        }
+    }
+}
+
+impl FromStr for SecretsBackend {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_value(Value::String(s.to_lowercase()))?)
+    }
+}
+
+/// Storage for the JSON object of every secret
+pub trait SecretStore: Send + Sync {
+    fn load(&self) -> Result<HashMap<String, Value>, ConfigError>;
+
+    /// Replace every stored secret
+    fn save(&self, values: &HashMap<String, Value>) -> Result<(), ConfigError>;
+}
+
+/// Stores the secrets as one JSON entry in the system keyring
+pub struct KeyringSecretStore {
+    service: String,
+}
+
+impl KeyringSecretStore {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+}
+
+impl SecretStore for KeyringSecretStore {
+    fn load(&self) -> Result<HashMap<String, Value>, ConfigError> {
+        let entry = Entry::new(&self.service, KEYRING_USERNAME)?;
+
+        match entry.get_password() {
+            Ok(content) => {
+                let values: HashMap<String, Value> = serde_json::from_str(&content)?;
+                Ok(values)
+            }
+            Err(keyring::Error::NoEntry) => Ok(HashMap::new()),
+            Err(e) => Err(ConfigError::KeyringError(e.to_string())),
+        }
+    }
+
+    fn save(&self, values: &HashMap<String, Value>) -> Result<(), ConfigError> {
+        let json_value = serde_json::to_string(values)?;
+        let entry = Entry::new(&self.service, KEYRING_USERNAME)?;
+        entry.set_password(&json_value)?;
+        Ok(())
+    }
+}
+
+/// Stores nothing, for when secrets are only given through environment variables
+pub struct EnvSecretStore;
+
+impl SecretStore for EnvSecretStore {
+    fn load(&self) -> Result<HashMap<String, Value>, ConfigError> {
+        Ok(HashMap::new())
+    }
+
+    fn save(&self, _values: &HashMap<String, Value>) -> Result<(), ConfigError> {
+        Err(ConfigError::SecretsError(
+            "Secrets are read from environment variables only, so they can't be saved. Set the environment variable instead".to_string(),
+        ))
+    }
+}
+
+/// The contents of the encrypted secrets file
+#[derive(Serialize, Deserialize)]
+struct EncryptedSecrets {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    /// The JSON object of secrets, encrypted with AES-256-GCM under a key derived from
+    /// the passphrase with PBKDF2-HMAC-SHA256
+    ciphertext: String,
+}
+
+/// Stores the secrets in a file encrypted with a passphrase
+///
+/// Deriving the key is slow on purpose, so the secrets are kept in memory until the file
+/// changes.
+pub struct FileSecretStore {
+    path: PathBuf,
+    passphrase: Option<String>,
+    cache: Mutex<Option<(SystemTime, HashMap<String, Value>)>>,
+}
+
+impl FileSecretStore {
+    /// A store at a path, locked unless there is a passphrase
+    pub fn new(path: impl AsRef<Path>, passphrase: Option<String>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            passphrase,
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, Option<(SystemTime, HashMap<String, Value>)>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn passphrase(&self) -> Result<&str, ConfigError> {
+        self.passphrase.as_deref().ok_or_else(|| {
+            ConfigError::SecretsError(format!(
+                "Set {} to unlock {}",
+                SECRETS_PASSPHRASE_ENV,
+                self.path.display()
+            ))
+        })
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, ConfigError> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| ConfigError::SecretsError("Invalid secrets file".to_string()))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| {
+        ConfigError::SecretsError("Failed to create the encryption key".to_string())
+    })?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, ConfigError> {
+    BASE64_STANDARD
+        .decode(value)
+        .map_err(|e| ConfigError::SecretsError(format!("Invalid secrets file: {}", e)))
+}
+
+impl SecretStore for FileSecretStore {
+    fn load(&self) -> Result<HashMap<String, Value>, ConfigError> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+        if let Some((cached, values)) = self.cache().as_ref() {
+            if *cached == modified {
+                return Ok(values.clone());
+            }
+        }
+
+        let file: EncryptedSecrets = serde_json::from_str(&std::fs::read_to_string(&self.path)?)?;
+        if file.iterations > MAX_PBKDF2_ITERATIONS {
+            return Err(ConfigError::SecretsError(format!(
+                "Invalid secrets file {}: too many iterations",
+                self.path.display()
+            )));
+        }
+        let key = derive_key(self.passphrase()?, &decode(&file.salt)?, file.iterations)?;
+        let nonce = Nonce::try_assume_unique_for_key(&decode(&file.nonce)?)
+            .map_err(|_| ConfigError::SecretsError("Invalid secrets file".to_string()))?;
+
+        let mut ciphertext = decode(&file.ciphertext)?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| {
+                ConfigError::SecretsError(format!(
+                    "Failed to decrypt {}, check {}",
+                    self.path.display(),
+                    SECRETS_PASSPHRASE_ENV
+                ))
+            })?;
+        let values: HashMap<String, Value> = serde_json::from_slice(plaintext)?;
+        *self.cache() = Some((modified, values.clone()));
+        Ok(values)
+    }
+
+    fn save(&self, values: &HashMap<String, Value>) -> Result<(), ConfigError> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| ConfigError::SecretsError("Failed to generate a nonce".to_string()))?;
+
+        let key = derive_key(self.passphrase()?, &salt, PBKDF2_ITERATIONS)?;
+        let mut ciphertext = serde_json::to_vec(values)?;
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut ciphertext,
+        )
+        .map_err(|_| ConfigError::SecretsError("Failed to encrypt secrets".to_string()))?;
+
+        let file = EncryptedSecrets {
+            version: 1,
+            iterations: PBKDF2_ITERATIONS,
+            salt: BASE64_STANDARD.encode(salt),
+            nonce: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        };
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ConfigError::DirectoryError(e.to_string()))?;
+        }
+        write_private(&self.path, serde_json::to_string_pretty(&file)?.as_bytes())?;
+        *self.cache() = Some((std::fs::metadata(&self.path)?.modified()?, values.clone()));
+        Ok(())
+    }
+}
+
+/// Replace a file with one only its owner can read
+///
+/// The contents are written to a temporary file beside it, which is synced and then renamed
+/// over the file, so an interrupted save leaves the old secrets in place.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&temp_path).and_then(|mut file| {
+        // The mode only applies to new files, so a leftover temporary file is restricted too
+        set_private(&file)?;
+        std::io::Write::write_all(&mut file, contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| std::fs::rename(&temp_path, path)) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_private(file: &std::fs::File) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn set_private(_file: &std::fs::File) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_file_store_round_trips() -> Result<(), ConfigError> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SECRETS_FILE_NAME);
+        let store = FileSecretStore::new(&path, Some("correct horse".to_string()));
+        assert!(store.load()?.is_empty());
+
+        let values = HashMap::from([("OPENAI_API_KEY".to_string(), json!("sk-123"))]);
+        store.save(&values)?;
+        assert_eq!(store.load()?, values);
+
+        // The file doesn't give the secrets away
+        let contents = std::fs::read_to_string(&path)?;
+        assert!(!contents.contains("sk-123"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let wrong = FileSecretStore::new(&path, Some("battery staple".to_string()));
+        assert!(matches!(wrong.load(), Err(ConfigError::SecretsError(_))));
+        let locked = FileSecretStore::new(&path, None);
+        assert!(matches!(locked.load(), Err(ConfigError::SecretsError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_store_save_replaces_file() -> Result<(), ConfigError> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SECRETS_FILE_NAME);
+        std::fs::write(&path, "{}")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+        }
+
+        let store = FileSecretStore::new(&path, Some("correct horse".to_string()));
+        store.save(&HashMap::from([("KEY".to_string(), json!("value"))]))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let files: Vec<_> = std::fs::read_dir(dir.path())?.collect();
+        assert_eq!(files.len(), 1);
+
+        // A file asking for an unreasonable number of iterations isn't decrypted
+        let mut file: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        file["iterations"] = json!(u32::MAX);
+        std::fs::write(&path, file.to_string())?;
+        let store = FileSecretStore::new(&path, Some("correct horse".to_string()));
+        assert!(matches!(store.load(), Err(ConfigError::SecretsError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_store_is_read_only() {
+        assert!(EnvSecretStore.load().unwrap().is_empty());
+        assert!(EnvSecretStore.save(&HashMap::new()).is_err());
+    }
+}
@@ -10,6 +10,7 @@ use std::collections::HashSet;
 use chrono::Utc;
 use mcp_core::content::{Content, ImageContent, TextContent};
 use mcp_core::handler::ToolResult;
+use mcp_core::prompt::{PromptMessage, PromptMessageContent, PromptMessageRole};
 use mcp_core::role::Role;
 use mcp_core::tool::ToolCall;
 
@@ -117,6 +118,18 @@ impl From<Content> for MessageContent {
     }
 }
 
+impl From<PromptMessageContent> for MessageContent {
+    fn from(content: PromptMessageContent) -> Self {
+        match content {
+            PromptMessageContent::Text { text } => MessageContent::text(text),
+            PromptMessageContent::Image { image } => MessageContent::Image(image),
+            PromptMessageContent::Resource { resource } => {
+                MessageContent::text(resource.get_text())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 /// A message to or from an LLM
 pub struct Message {
@@ -125,6 +138,16 @@ pub struct Message {
     pub content: Vec<MessageContent>,
 }
 
+impl From<PromptMessage> for Message {
+    fn from(message: PromptMessage) -> Self {
+        let base = match message.role {
+            PromptMessageRole::User => Message::user(),
+            PromptMessageRole::Assistant => Message::assistant(),
+        };
+        base.with_content(message.content.into())
+    }
+}
+
 impl Message {
     /// Create a new user message with the current timestamp
     pub fn user() -> Self {
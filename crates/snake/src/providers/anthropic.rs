@@ -1,13 +1,17 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
-use serde_json::Value;
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{json, Value};
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderStream, ProviderUsage};
 use super::errors::ProviderError;
-use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::formats::anthropic::{
+    create_request, get_usage, response_to_deltas, response_to_message,
+};
+use super::retry::RetryConfig;
+use super::utils::{emit_debug_trace, get_model, stream_response};
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -25,6 +29,8 @@ pub const ANTHROPIC_DOC_URL: &str = "https://docs.anthropic.com/en/docs/about-cl
 pub struct AnthropicProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     api_key: String,
     model: ModelConfig,
@@ -39,7 +45,11 @@ impl Default for AnthropicProvider {
 
 impl AnthropicProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let api_key: String = config.get_secret("ANTHROPIC_API_KEY")?;
         let host: String = config
             .get("ANTHROPIC_HOST")
@@ -51,24 +61,34 @@ impl AnthropicProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             api_key,
             model,
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, payload: &Value) -> Result<Response, ProviderError> {
         let url = format!("{}/v1/messages", self.host.trim_end_matches('/'));
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(payload);
+        let response = self.retry.send(request).await?;
 
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(&payload).await?;
+        Self::handle_response(response).await
+    }
+
+    /// Map the response from Anthropic to its JSON body or a ProviderError
+    async fn handle_response(response: Response) -> Result<Value, ProviderError> {
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
 
@@ -143,13 +163,14 @@ impl Provider for AnthropicProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
+        let payload = create_request(model_config, system, messages, tools)?;
 
         // Make request
         let response = self.post(payload.clone()).await?;
@@ -162,4 +183,40 @@ impl Provider for AnthropicProvider {
         emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stream".to_string(), json!(true));
+
+        let response = self.send(&payload).await?;
+        if response.status() != StatusCode::OK {
+            return Err(Self::handle_response(response)
+                .await
+                .expect_err("only successful responses are streamed"));
+        }
+
+        Ok(stream_response(
+            response,
+            &self.model.model_name,
+            response_to_deltas,
+        ))
+    }
 }
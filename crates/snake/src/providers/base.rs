@@ -1,4 +1,5 @@
 use anyhow::Result;
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 
 use super::errors::ProviderError;
@@ -83,11 +84,27 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// The provider that served the request, set when a fallback chain picks between several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// The cost of the request in USD, when the model's price is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            provider: None,
+            cost: None,
+        }
+    }
+
+    pub fn with_provider(mut self, provider: &str) -> Self {
+        self.provider = Some(provider.to_string());
+        self
     }
 }
 
@@ -112,6 +129,27 @@ impl Usage {
     }
 }
 
+/// An incremental piece of a streamed completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProviderDelta {
+    /// A fragment of the assistant's text
+    Text(String),
+    /// A fragment of a tool call's arguments, the id and name arrive with the first fragment
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// The final record of a stream, with the assembled message and the usage for the whole completion
+    Finish {
+        message: Message,
+        usage: ProviderUsage,
+    },
+}
+
+pub type ProviderStream = BoxStream<'static, Result<ProviderDelta, ProviderError>>;
+
 use async_trait::async_trait;
 
 /// Base trait for AI providers (OpenAI, Anthropic, etc)
@@ -142,6 +180,46 @@ pub trait Provider: Send + Sync {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError>;
 
+    /// Generate the next message like `complete`, with a model config in place of the
+    /// provider's own, e.g. to lower `max_tokens` for one request
+    ///
+    /// Providers which can't change their model per request ignore `model`.
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let _ = model;
+        self.complete(system, messages, tools).await
+    }
+
+    /// Generate the next message as a stream of deltas, ending with a `ProviderDelta::Finish`
+    ///
+    /// Providers which can't stream fall back to `complete` and send the text in a single delta
+    ///
+    /// # Errors
+    /// ProviderError
+    ///   - Errors from sending the request are returned before the stream starts, so
+    ///     ContextLengthExceeded is raised the same way as in `complete`
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let (message, usage) = self.complete(system, messages, tools).await?;
+
+        let mut deltas = Vec::new();
+        let text = message.as_concat_text();
+        if !text.is_empty() {
+            deltas.push(Ok(ProviderDelta::Text(text)));
+        }
+        deltas.push(Ok(ProviderDelta::Finish { message, usage }));
+        Ok(Box::pin(futures::stream::iter(deltas)))
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 }
@@ -9,8 +9,9 @@ use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
 use super::oauth;
+use super::retry::RetryConfig;
 use super::utils::{get_model, ImageFormat};
-use crate::config::ConfigError;
+use crate::config::{ConfigError, ConfigResolver};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -61,6 +62,8 @@ impl DatabricksAuth {
 pub struct DatabricksProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     auth: DatabricksAuth,
     model: ModelConfig,
@@ -76,8 +79,11 @@ impl Default for DatabricksProvider {
 
 impl DatabricksProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
 
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         // For compatibility for now we check both config and secret for databricks host
         // but it is not actually a secret value
         let mut host: Result<String, ConfigError> = config.get("DATABRICKS_HOST");
@@ -103,6 +109,7 @@ impl DatabricksProvider {
         if let Ok(api_key) = config.get_secret("DATABRICKS_TOKEN") {
             return Ok(Self {
                 client,
+                retry: RetryConfig::from_config(config.config()),
                 host,
                 auth: DatabricksAuth::token(api_key),
                 model,
@@ -113,6 +120,7 @@ impl DatabricksProvider {
         // Otherwise use Oauth flow
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             auth: DatabricksAuth::oauth(host.clone()),
             host,
             model,
@@ -144,13 +152,12 @@ impl DatabricksProvider {
         );
 
         let auth_header = self.ensure_auth_header().await?;
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Authorization", auth_header)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.retry.send(request).await?;
 
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
@@ -231,13 +238,15 @@ impl Provider for DatabricksProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let mut payload = create_request(&self.model, system, messages, tools, &self.image_format)?;
+        let mut payload =
+            create_request(model_config, system, messages, tools, &self.image_format)?;
         // Remove the model key which is part of the url with databricks
         payload
             .as_object_mut()
@@ -261,4 +270,14 @@ impl Provider for DatabricksProvider {
 
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
 }
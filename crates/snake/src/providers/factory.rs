@@ -2,12 +2,14 @@ use super::{
     anthropic::AnthropicProvider,
     base::{Provider, ProviderMetadata},
     databricks::DatabricksProvider,
+    fallback::{FallbackProvider, ProviderFallback, FALLBACKS_CONFIG_KEY},
     google::GoogleProvider,
     groq::GroqProvider,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
 };
+use crate::config::ConfigResolver;
 use crate::model::ModelConfig;
 use anyhow::Result;
 
@@ -23,15 +25,106 @@ pub fn providers() -> Vec<ProviderMetadata> {
     ]
 }
 
-pub fn create(name: &str, model: ModelConfig) -> Result<Box<dyn Provider + Send + Sync>> {
+/// Create the named provider, chained with the fallbacks configured in
+/// `GOOSE_PROVIDER_FALLBACKS` if there are any
+///
+/// The providers read their settings and secrets through `config`, e.g.
+/// `Config::global().resolver()`.
+pub fn create(
+    name: &str,
+    model: ModelConfig,
+    config: &ConfigResolver,
+) -> Result<Box<dyn Provider + Send + Sync>> {
+    let fallbacks: Vec<ProviderFallback> = config.get(FALLBACKS_CONFIG_KEY).unwrap_or_default();
+    create_with_fallbacks(name, model, &fallbacks, config)
+}
+
+/// Create the named provider followed by a chain of fallbacks
+///
+/// Fallbacks which cannot be created, e.g. because their keys are not configured, are
+/// skipped so they don't prevent using the preferred provider.
+pub fn create_with_fallbacks(
+    name: &str,
+    model: ModelConfig,
+    fallbacks: &[ProviderFallback],
+    config: &ConfigResolver,
+) -> Result<Box<dyn Provider + Send + Sync>> {
+    let provider = create_provider(name, model, config)?;
+    if fallbacks.is_empty() {
+        return Ok(provider);
+    }
+
+    let mut chain: Vec<(String, Box<dyn Provider>)> = vec![(name.to_string(), provider)];
+    for fallback in fallbacks {
+        match create_provider(&fallback.provider, fallback.model_config(), config) {
+            Ok(provider) => chain.push((fallback.provider.clone(), provider)),
+            Err(e) => tracing::warn!(
+                "Skipping fallback provider {} ({}): {}",
+                fallback.provider,
+                fallback.model,
+                e
+            ),
+        }
+    }
+    Ok(Box::new(FallbackProvider::new(chain)))
+}
+
+fn create_provider(
+    name: &str,
+    model: ModelConfig,
+    config: &ConfigResolver,
+) -> Result<Box<dyn Provider + Send + Sync>> {
     match name {
-        "openai" => Ok(Box::new(OpenAiProvider::from_env(model)?)),
-        "anthropic" => Ok(Box::new(AnthropicProvider::from_env(model)?)),
-        "databricks" => Ok(Box::new(DatabricksProvider::from_env(model)?)),
-        "groq" => Ok(Box::new(GroqProvider::from_env(model)?)),
-        "ollama" => Ok(Box::new(OllamaProvider::from_env(model)?)),
-        "openrouter" => Ok(Box::new(OpenRouterProvider::from_env(model)?)),
-        "google" => Ok(Box::new(GoogleProvider::from_env(model)?)),
+        "openai" => Ok(Box::new(OpenAiProvider::from_config(config, model)?)),
+        "anthropic" => Ok(Box::new(AnthropicProvider::from_config(config, model)?)),
+        "databricks" => Ok(Box::new(DatabricksProvider::from_config(config, model)?)),
+        "groq" => Ok(Box::new(GroqProvider::from_config(config, model)?)),
+        "ollama" => Ok(Box::new(OllamaProvider::from_config(config, model)?)),
+        "openrouter" => Ok(Box::new(OpenRouterProvider::from_config(config, model)?)),
+        "google" => Ok(Box::new(GoogleProvider::from_config(config, model)?)),
         _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_create_with_fallbacks() -> Result<()> {
+        let config = Config::global().resolver();
+        let fallbacks = vec![
+            ProviderFallback {
+                provider: "unknown".to_string(),
+                model: "model".to_string(),
+                context_limit: None,
+            },
+            ProviderFallback {
+                provider: "ollama".to_string(),
+                model: "qwen2.5".to_string(),
+                context_limit: Some(32_000),
+            },
+        ];
+
+        let provider = create_with_fallbacks(
+            "ollama",
+            ModelConfig::new("llama3.2".to_string()),
+            &fallbacks,
+            &config,
+        )?;
+        let model = provider.get_model_config();
+        assert_eq!(model.model_name, "llama3.2");
+        assert_eq!(model.context_limit(), 32_000);
+
+        // An unknown preferred provider is still an error
+        assert!(create_with_fallbacks(
+            "unknown",
+            ModelConfig::new("model".to_string()),
+            &fallbacks,
+            &config,
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use super::base::{Provider, ProviderDelta, ProviderMetadata, ProviderStream, ProviderUsage};
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// The config key holding the providers to fall back to, in order
+pub const FALLBACKS_CONFIG_KEY: &str = "GOOSE_PROVIDER_FALLBACKS";
+
+/// A provider and model to fall back to when the ones before it are unavailable
+///
+/// ```yaml
+/// GOOSE_PROVIDER_FALLBACKS:
+///   - provider: openai
+///     model: gpt-4o
+///   - provider: ollama
+///     model: llama3.2
+///     context_limit: 32000
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderFallback {
+    pub provider: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_limit: Option<usize>,
+}
+
+impl ProviderFallback {
+    pub fn model_config(&self) -> ModelConfig {
+        ModelConfig::new(self.model.clone()).with_context_limit(self.context_limit)
+    }
+}
+
+/// A provider that tries an ordered chain of providers, moving on to the next one when a
+/// provider is rate limited, failing on its side, or rejects the credentials
+///
+/// Errors caused by the request itself, like exceeding the context length, are returned
+/// right away since every provider would reject it.
+pub struct FallbackProvider {
+    providers: Vec<(String, Box<dyn Provider>)>,
+    model: ModelConfig,
+}
+
+impl FallbackProvider {
+    /// Create a chain from named providers, starting with the preferred one
+    pub fn new(providers: Vec<(String, Box<dyn Provider>)>) -> Self {
+        assert!(
+            !providers.is_empty(),
+            "A fallback chain needs at least one provider"
+        );
+
+        // Report the smallest context limit in the chain, so the conversation still fits
+        // whichever model ends up serving it
+        let context_limit = providers
+            .iter()
+            .map(|(_, provider)| provider.get_model_config().context_limit())
+            .min();
+        let model = providers[0]
+            .1
+            .get_model_config()
+            .with_context_limit(context_limit);
+
+        Self { providers, model }
+    }
+
+    /// Try each provider in turn, applying the limits of `model` to each provider's own model
+    async fn complete_each(
+        &self,
+        model: Option<&ModelConfig>,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut last_error = None;
+        for (name, provider) in &self.providers {
+            let result = match model {
+                Some(model) => {
+                    let mut config = provider.get_model_config();
+                    if model.max_tokens.is_some() {
+                        config = config.with_max_tokens(model.max_tokens);
+                    }
+                    if model.temperature.is_some() {
+                        config = config.with_temperature(model.temperature);
+                    }
+                    provider
+                        .complete_with_model(&config, system, messages, tools)
+                        .await
+                }
+                None => provider.complete(system, messages, tools).await,
+            };
+            match result {
+                Ok((message, usage)) => return Ok((message, usage.with_provider(name))),
+                Err(e) if Self::should_fall_back(&e) => {
+                    tracing::warn!("Provider {} failed, trying the next provider: {}", name, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("the chain is never empty"))
+    }
+
+    /// Whether the next provider in the chain might succeed where this one failed
+    fn should_fall_back(error: &ProviderError) -> bool {
+        matches!(
+            error,
+            ProviderError::RateLimitExceeded(_)
+                | ProviderError::ServerError(_)
+                | ProviderError::Authentication(_)
+        )
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_each(None, system, messages, tools).await
+    }
+
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_each(Some(model), system, messages, tools)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut last_error = None;
+        for (name, provider) in &self.providers {
+            // Once a provider starts streaming the turn is committed to it
+            match provider.stream(system, messages, tools).await {
+                Ok(deltas) => {
+                    let name = name.clone();
+                    return Ok(Box::pin(deltas.map(move |delta| match delta {
+                        Ok(ProviderDelta::Finish { message, usage }) => Ok(ProviderDelta::Finish {
+                            message,
+                            usage: usage.with_provider(&name),
+                        }),
+                        delta => delta,
+                    })));
+                }
+                Err(e) if Self::should_fall_back(&e) => {
+                    tracing::warn!("Provider {} failed, trying the next provider: {}", name, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("the chain is never empty"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Builds the error a mock provider fails with
+    type ErrorFn = fn(String) -> ProviderError;
+
+    /// Mock provider which fails with the given error, or replies with its model name
+    struct MockProvider {
+        model: ModelConfig,
+        error: Option<ErrorFn>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl MockProvider {
+        fn boxed(
+            model: &str,
+            context_limit: usize,
+            error: Option<ErrorFn>,
+            calls: &Arc<AtomicUsize>,
+        ) -> Box<dyn Provider> {
+            Box::new(Self {
+                model: ModelConfig::new(model.to_string()).with_context_limit(Some(context_limit)),
+                error,
+                calls: calls.clone(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.error {
+                Some(error) => Err(error("unavailable".to_string())),
+                None => Ok((
+                    Message::assistant().with_text(&self.model.model_name),
+                    ProviderUsage::new(self.model.model_name.clone(), Usage::default()),
+                )),
+            }
+        }
+    }
+
+    fn chain(errors: &[Option<ErrorFn>]) -> (FallbackProvider, Vec<Arc<AtomicUsize>>) {
+        let calls: Vec<_> = errors
+            .iter()
+            .map(|_| Arc::new(AtomicUsize::new(0)))
+            .collect();
+        let providers = errors
+            .iter()
+            .zip(&calls)
+            .enumerate()
+            .map(|(i, (error, calls))| {
+                (
+                    format!("provider-{}", i),
+                    MockProvider::boxed(&format!("model-{}", i), 100_000 - i * 1000, *error, calls),
+                )
+            })
+            .collect();
+        (FallbackProvider::new(providers), calls)
+    }
+
+    fn counts(calls: &[Arc<AtomicUsize>]) -> Vec<usize> {
+        calls.iter().map(|c| c.load(Ordering::SeqCst)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_uses_first_available_provider() -> anyhow::Result<()> {
+        let (provider, calls) = chain(&[
+            Some(ProviderError::RateLimitExceeded),
+            Some(ProviderError::Authentication),
+            None,
+            None,
+        ]);
+
+        let (message, usage) = provider.complete("system", &[], &[]).await?;
+        assert_eq!(message.as_concat_text(), "model-2");
+        assert_eq!(usage.model, "model-2");
+        assert_eq!(usage.provider.as_deref(), Some("provider-2"));
+        assert_eq!(counts(&calls), vec![1, 1, 1, 0]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_returns_request_errors_without_falling_back() {
+        let (provider, calls) = chain(&[Some(ProviderError::ContextLengthExceeded), None]);
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(
+            result,
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+        assert_eq!(counts(&calls), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_chain_is_exhausted() {
+        let (provider, _) = chain(&[
+            Some(ProviderError::RateLimitExceeded),
+            Some(ProviderError::ServerError),
+        ]);
+
+        let result = provider.complete("system", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::ServerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_and_records_provider() -> anyhow::Result<()> {
+        let (provider, calls) = chain(&[Some(ProviderError::ServerError), None]);
+
+        let mut deltas = provider.stream("system", &[], &[]).await?;
+        let mut finish = None;
+        while let Some(delta) = deltas.next().await {
+            if let ProviderDelta::Finish { usage, .. } = delta? {
+                finish = Some(usage);
+            }
+        }
+        let usage = finish.expect("the stream finishes");
+        assert_eq!(usage.provider.as_deref(), Some("provider-1"));
+        assert_eq!(counts(&calls), vec![1, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_config_uses_smallest_context_limit() {
+        let (provider, _) = chain(&[None, None, None]);
+        let model = provider.get_model_config();
+        assert_eq!(model.model_name, "model-0");
+        assert_eq!(model.context_limit(), 98_000);
+    }
+}
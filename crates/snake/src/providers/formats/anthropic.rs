@@ -1,7 +1,8 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{ProviderDelta, Usage};
 use crate::providers::errors::ProviderError;
+use crate::providers::utils::StreamAccumulator;
 use anyhow::{anyhow, Result};
 use mcp_core::content::Content;
 use mcp_core::role::Role;
@@ -212,6 +213,78 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
 }
 
 /// Create a complete request payload for Anthropic's API
+/// Convert an event of Anthropic's streamed response to deltas, recording usage on the accumulator
+/// https://docs.anthropic.com/en/api/messages-streaming
+pub fn response_to_deltas(
+    event: &Value,
+    accumulator: &mut StreamAccumulator,
+) -> Result<Vec<ProviderDelta>, ProviderError> {
+    let mut deltas = Vec::new();
+    let index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+
+    match event.get("type").and_then(|t| t.as_str()) {
+        Some("message_start") => {
+            let message = &event["message"];
+            if let Some(model) = message.get("model").and_then(|m| m.as_str()) {
+                accumulator.set_model(model);
+            }
+            accumulator.update_usage(get_usage(message)?);
+        }
+        Some("content_block_start") => {
+            let block = &event["content_block"];
+            if block.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
+                deltas.push(ProviderDelta::ToolCall {
+                    index,
+                    id: block["id"].as_str().map(String::from),
+                    name: block["name"].as_str().map(String::from),
+                    arguments: String::new(),
+                });
+            }
+        }
+        Some("content_block_delta") => {
+            let delta = &event["delta"];
+            match delta.get("type").and_then(|t| t.as_str()) {
+                Some("text_delta") => {
+                    let text = delta["text"].as_str().unwrap_or_default();
+                    deltas.push(ProviderDelta::Text(text.to_string()));
+                }
+                Some("input_json_delta") => {
+                    deltas.push(ProviderDelta::ToolCall {
+                        index,
+                        id: None,
+                        name: None,
+                        arguments: delta["partial_json"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Some("message_delta") => {
+            // Only the output tokens are reported at the end of the message
+            let output_tokens = event["usage"]["output_tokens"].as_u64().map(|v| v as i32);
+            accumulator.update_usage(Usage::new(None, output_tokens, None));
+        }
+        Some("error") => {
+            let error = &event["error"];
+            let message = error["message"]
+                .as_str()
+                .unwrap_or("Unknown error")
+                .to_string();
+            return Err(match error["type"].as_str() {
+                Some("overloaded_error") | Some("api_error") => ProviderError::ServerError(message),
+                Some("rate_limit_error") => ProviderError::RateLimitExceeded(message),
+                _ => ProviderError::RequestFailed(message),
+            });
+        }
+        _ => {}
+    }
+
+    Ok(deltas)
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
@@ -420,4 +493,52 @@ mod tests {
         assert_eq!(spec_array[0]["text"], system);
         assert!(spec_array[0].get("cache_control").is_some());
     }
+
+    #[test]
+    fn test_response_to_deltas() -> Result<()> {
+        let events = [
+            json!({"type": "message_start", "message": {"model": "claude-3-5-sonnet-latest", "usage": {"input_tokens": 10, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Let me "}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "check."}}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "tool_1", "name": "calculator", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"expression\": "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"2 + 2\"}"}}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 25}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let mut accumulator = StreamAccumulator::new("claude");
+        for event in &events {
+            for delta in response_to_deltas(event, &mut accumulator)? {
+                accumulator.push(&delta);
+            }
+        }
+
+        match accumulator.finish() {
+            ProviderDelta::Finish { message, usage } => {
+                assert_eq!(message.as_concat_text(), "Let me check.");
+                let request = message.content[1].as_tool_request().unwrap();
+                let tool_call = request.tool_call.as_ref().unwrap();
+                assert_eq!(request.id, "tool_1");
+                assert_eq!(tool_call.name, "calculator");
+                assert_eq!(tool_call.arguments, json!({"expression": "2 + 2"}));
+                assert_eq!(usage.model, "claude-3-5-sonnet-latest");
+                assert_eq!(usage.usage.input_tokens, Some(10));
+                assert_eq!(usage.usage.output_tokens, Some(25));
+                assert_eq!(usage.usage.total_tokens, Some(35));
+            }
+            _ => panic!("Expected Finish delta"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_deltas_error() {
+        let event = json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}});
+        let mut accumulator = StreamAccumulator::new("claude");
+        let result = response_to_deltas(&event, &mut accumulator);
+        assert!(matches!(result, Err(ProviderError::ServerError(_))));
+    }
 }
@@ -1,8 +1,8 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{ProviderDelta, Usage};
 use crate::providers::errors::ProviderError;
-use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
+use crate::providers::utils::{is_valid_function_name, sanitize_function_name, StreamAccumulator};
 use anyhow::Result;
 use mcp_core::content::Content;
 use mcp_core::role::Role;
@@ -265,6 +265,65 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
 }
 
 /// Create a complete request payload for Google's API
+/// Convert a chunk of Google's streamed response to deltas, recording usage on the accumulator
+///   function calls are not split across chunks, so each one is sent as a single delta
+pub fn response_to_deltas(
+    chunk: &Value,
+    accumulator: &mut StreamAccumulator,
+) -> Result<Vec<ProviderDelta>, ProviderError> {
+    if let Some(error) = chunk.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error");
+        return Err(ProviderError::RequestFailed(format!(
+            "Response stream failed: {}",
+            message
+        )));
+    }
+
+    if let Some(model) = chunk.get("modelVersion").and_then(|m| m.as_str()) {
+        accumulator.set_model(model);
+    }
+    if chunk.get("usageMetadata").is_some() {
+        accumulator.update_usage(get_usage(chunk)?);
+    }
+
+    let mut deltas = Vec::new();
+    let binding = vec![];
+    let parts = chunk["candidates"][0]["content"]["parts"]
+        .as_array()
+        .unwrap_or(&binding);
+
+    for part in parts {
+        if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
+            deltas.push(ProviderDelta::Text(text.to_string()));
+        } else if let Some(function_call) = part.get("functionCall") {
+            let id: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(8)
+                .map(char::from)
+                .collect();
+            let index = accumulator.tool_call_count()
+                + deltas
+                    .iter()
+                    .filter(|delta| matches!(delta, ProviderDelta::ToolCall { .. }))
+                    .count();
+            deltas.push(ProviderDelta::ToolCall {
+                index,
+                id: Some(id),
+                name: function_call["name"].as_str().map(String::from),
+                arguments: function_call
+                    .get("args")
+                    .map(|args| args.to_string())
+                    .unwrap_or_default(),
+            });
+        }
+    }
+
+    Ok(deltas)
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
@@ -336,6 +395,7 @@ mod tests {
             input_schema: json!({
                 "properties": params
             }),
+            annotations: None,
         }
     }
 
@@ -449,6 +509,7 @@ mod tests {
             input_schema: json!({
                 "properties": {}
             }),
+            annotations: None,
         }];
         let result = format_tools(&tools);
         assert_eq!(result.len(), 1);
@@ -536,4 +597,50 @@ mod tests {
             panic!("Expected valid tool request");
         }
     }
+
+    #[test]
+    fn test_response_to_deltas() -> anyhow::Result<()> {
+        let chunks = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Checking"}]}}], "modelVersion": "gemini-2.0-flash-exp"}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [
+                {"functionCall": {"name": "example_fn", "args": {"param": "value"}}},
+                {"functionCall": {"name": "other_fn"}}
+            ]}}], "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}}),
+        ];
+
+        let mut accumulator = StreamAccumulator::new("gemini");
+        for chunk in &chunks {
+            for delta in response_to_deltas(chunk, &mut accumulator)? {
+                accumulator.push(&delta);
+            }
+        }
+
+        match accumulator.finish() {
+            ProviderDelta::Finish { message, usage } => {
+                assert_eq!(message.as_concat_text(), "Checking");
+                assert_eq!(message.content.len(), 3);
+                let tool_call = message.content[1]
+                    .as_tool_request()
+                    .unwrap()
+                    .tool_call
+                    .as_ref()
+                    .unwrap();
+                assert_eq!(tool_call.name, "example_fn");
+                assert_eq!(tool_call.arguments, json!({"param": "value"}));
+                let tool_call = message.content[2]
+                    .as_tool_request()
+                    .unwrap()
+                    .tool_call
+                    .as_ref()
+                    .unwrap();
+                assert_eq!(tool_call.name, "other_fn");
+                assert_eq!(tool_call.arguments, json!({}));
+                assert_eq!(usage.model, "gemini-2.0-flash-exp");
+                assert_eq!(usage.usage.total_tokens, Some(15));
+            }
+            _ => panic!("Expected Finish delta"),
+        }
+
+        Ok(())
+    }
 }
@@ -1,9 +1,10 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{ProviderDelta, Usage};
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{
-    convert_image, is_valid_function_name, sanitize_function_name, ImageFormat,
+    convert_image, get_model, is_valid_function_name, sanitize_function_name, ImageFormat,
+    StreamAccumulator,
 };
 use anyhow::{anyhow, Error};
 use mcp_core::ToolError;
@@ -222,6 +223,61 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     })
 }
 
+/// Convert a chunk of OpenAI's streamed response to deltas, recording usage on the accumulator
+///   the usage arrives in a final chunk with no choices when `stream_options.include_usage` is set
+pub fn response_to_deltas(
+    chunk: &Value,
+    accumulator: &mut StreamAccumulator,
+) -> Result<Vec<ProviderDelta>, ProviderError> {
+    if let Some(error) = chunk.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error");
+        return Err(ProviderError::RequestFailed(format!(
+            "Response stream failed: {}",
+            message
+        )));
+    }
+
+    if chunk.get("model").is_some() {
+        accumulator.set_model(&get_model(chunk));
+    }
+    if chunk.get("usage").is_some_and(|usage| !usage.is_null()) {
+        accumulator.update_usage(get_usage(chunk)?);
+    }
+
+    let mut deltas = Vec::new();
+    let delta = &chunk["choices"][0]["delta"];
+
+    if let Some(text) = delta.get("content").and_then(|t| t.as_str()) {
+        if !text.is_empty() {
+            deltas.push(ProviderDelta::Text(text.to_string()));
+        }
+    }
+
+    if let Some(tool_calls) = delta.get("tool_calls").and_then(|t| t.as_array()) {
+        for (position, tool_call) in tool_calls.iter().enumerate() {
+            let index = tool_call
+                .get("index")
+                .and_then(|i| i.as_u64())
+                .map(|i| i as usize)
+                .unwrap_or(position);
+            deltas.push(ProviderDelta::ToolCall {
+                index,
+                id: tool_call["id"].as_str().map(String::from),
+                name: tool_call["function"]["name"].as_str().map(String::from),
+                arguments: tool_call["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+    }
+
+    Ok(deltas)
+}
+
 pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
     let usage = data
         .get("usage")
@@ -297,6 +353,14 @@ pub fn create_request(
     Ok(payload)
 }
 
+/// Ask for the response to be streamed, including the usage in the final chunk
+pub fn with_streaming(mut payload: Value) -> Value {
+    let object = payload.as_object_mut().unwrap();
+    object.insert("stream".to_string(), json!(true));
+    object.insert("stream_options".to_string(), json!({"include_usage": true}));
+    payload
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -562,4 +626,52 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_response_to_deltas() -> anyhow::Result<()> {
+        let chunks = [
+            json!({"model": "gpt-4o-2024", "choices": [{"delta": {"role": "assistant", "content": "Hel"}}]}),
+            json!({"choices": [{"delta": {"content": "lo"}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "1", "function": {"name": "example_fn", "arguments": ""}}]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"param\": "}}]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\"value\"}"}}]}}]}),
+            json!({"choices": [], "usage": {"prompt_tokens": 10, "completion_tokens": 25, "total_tokens": 35}}),
+        ];
+
+        let mut accumulator = StreamAccumulator::new("gpt-4o");
+        let mut text = String::new();
+        for chunk in &chunks {
+            for delta in response_to_deltas(chunk, &mut accumulator)? {
+                if let ProviderDelta::Text(fragment) = &delta {
+                    text.push_str(fragment);
+                }
+                accumulator.push(&delta);
+            }
+        }
+        assert_eq!(text, "Hello");
+
+        match accumulator.finish() {
+            ProviderDelta::Finish { message, usage } => {
+                assert_eq!(message.as_concat_text(), "Hello");
+                let request = message.content[1].as_tool_request().unwrap();
+                let tool_call = request.tool_call.as_ref().unwrap();
+                assert_eq!(request.id, "1");
+                assert_eq!(tool_call.name, "example_fn");
+                assert_eq!(tool_call.arguments, json!({"param": "value"}));
+                assert_eq!(usage.model, "gpt-4o-2024");
+                assert_eq!(usage.usage.total_tokens, Some(35));
+            }
+            _ => panic!("Expected Finish delta"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_deltas_error() {
+        let chunk = json!({"error": {"message": "Overloaded"}});
+        let mut accumulator = StreamAccumulator::new("gpt-4o");
+        let result = response_to_deltas(&chunk, &mut accumulator);
+        assert!(matches!(result, Err(ProviderError::RequestFailed(_))));
+    }
 }
@@ -1,13 +1,19 @@
 use super::errors::ProviderError;
+use super::retry::RetryConfig;
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
-use crate::providers::formats::google::{create_request, get_usage, response_to_message};
-use crate::providers::utils::{emit_debug_trace, unescape_json_values};
+use crate::providers::base::{
+    ConfigKey, Provider, ProviderMetadata, ProviderStream, ProviderUsage,
+};
+use crate::providers::formats::google::{
+    create_request, get_usage, response_to_deltas, response_to_message,
+};
+use crate::providers::utils::{emit_debug_trace, stream_response, unescape_json_values};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::tool::Tool;
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, Response, StatusCode};
 use serde_json::Value;
 use std::time::Duration;
 
@@ -28,6 +34,8 @@ pub const GOOGLE_DOC_URL: &str = "https://ai.google/get-started/our-models/";
 pub struct GoogleProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     api_key: String,
     model: ModelConfig,
@@ -42,7 +50,11 @@ impl Default for GoogleProvider {
 
 impl GoogleProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let api_key: String = config.get_secret("GOOGLE_API_KEY")?;
         let host: String = config
             .get("GOOGLE_HOST")
@@ -54,28 +66,39 @@ impl GoogleProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             api_key,
             model,
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, method: &str, payload: &Value) -> Result<Response, ProviderError> {
         let url = format!(
-            "{}/v1beta/models/{}:generateContent?key={}",
+            "{}/v1beta/models/{}:{}",
             self.host.trim_end_matches('/'),
             self.model.model_name,
-            self.api_key
+            method
         );
 
-        let response = self
+        let request = self
             .client
             .post(&url)
+            .query(&[("key", &self.api_key)])
             .header("CONTENT_TYPE", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(payload);
+        let response = self.retry.send(request).await?;
 
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send("generateContent", &payload).await?;
+        Self::handle_response(response).await
+    }
+
+    /// Map the response from Google to its JSON body or a ProviderError
+    async fn handle_response(response: Response) -> Result<Value, ProviderError> {
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
 
@@ -142,13 +165,14 @@ impl Provider for GoogleProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
+        let payload = create_request(model_config, system, messages, tools)?;
 
         // Make request
         let response = self.post(payload.clone()).await?;
@@ -158,10 +182,42 @@ impl Provider for GoogleProvider {
         let usage = get_usage(&response)?;
         let model = match response.get("modelVersion") {
             Some(model_version) => model_version.as_str().unwrap_or_default().to_string(),
-            None => self.model.model_name.clone(),
+            None => model_config.model_name.clone(),
         };
         emit_debug_trace(self, &payload, &response, &usage);
         let provider_usage = ProviderUsage::new(model, usage);
         Ok((message, provider_usage))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools)?;
+
+        let response = self.send("streamGenerateContent?alt=sse", &payload).await?;
+        if response.status() != StatusCode::OK {
+            return Err(Self::handle_response(response)
+                .await
+                .expect_err("only successful responses are streamed"));
+        }
+
+        Ok(stream_response(
+            response,
+            &self.model.model_name,
+            |chunk, accumulator| response_to_deltas(&unescape_json_values(chunk), accumulator),
+        ))
+    }
 }
@@ -1,4 +1,6 @@
 use super::errors::ProviderError;
+use super::retry::RetryConfig;
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
@@ -21,6 +23,8 @@ pub const GROQ_DOC_URL: &str = "https://console.groq.com/docs/models";
 pub struct GroqProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     api_key: String,
     model: ModelConfig,
@@ -35,7 +39,11 @@ impl Default for GroqProvider {
 
 impl GroqProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let api_key: String = config.get_secret("GROQ_API_KEY")?;
         let host: String = config
             .get("GROQ_HOST")
@@ -47,6 +55,7 @@ impl GroqProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             api_key,
             model,
@@ -59,13 +68,12 @@ impl GroqProvider {
             self.host.trim_end_matches('/')
         );
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.retry.send(request).await?;
 
         let status = response.status();
         let payload: Option<Value> = response.json().await.ok();
@@ -120,14 +128,15 @@ impl Provider for GroqProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
         let payload = create_request(
-            &self.model,
+            model_config,
             system,
             messages,
             tools,
@@ -149,4 +158,14 @@ impl Provider for GroqProvider {
         super::utils::emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
 }
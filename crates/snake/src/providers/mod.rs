@@ -3,6 +3,7 @@ pub mod base;
 pub mod databricks;
 pub mod errors;
 mod factory;
+pub mod fallback;
 pub mod formats;
 pub mod google;
 pub mod groq;
@@ -10,6 +11,8 @@ pub mod oauth;
 pub mod ollama;
 pub mod openai;
 pub mod openrouter;
+pub mod pricing;
+pub mod retry;
 pub mod utils;
 
-pub use factory::{create, providers};
+pub use factory::{create, create_with_fallbacks, providers};
@@ -1,13 +1,17 @@
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderStream, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::utils::{get_model, handle_response_openai_compat};
+use super::retry::RetryConfig;
+use super::utils::{get_model, handle_response_openai_compat, stream_response};
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::formats::openai::{
+    create_request, get_usage, response_to_deltas, response_to_message, with_streaming,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::tool::Tool;
-use reqwest::Client;
+use reqwest::{Client, Response, StatusCode};
 use serde_json::Value;
 use std::time::Duration;
 
@@ -21,6 +25,8 @@ pub const OLLAMA_DOC_URL: &str = "https://ollama.com/library";
 pub struct OllamaProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     model: ModelConfig,
 }
@@ -34,7 +40,11 @@ impl Default for OllamaProvider {
 
 impl OllamaProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let host: String = config
             .get("OLLAMA_HOST")
             .unwrap_or_else(|_| OLLAMA_HOST.to_string());
@@ -45,16 +55,25 @@ impl OllamaProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             model,
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, payload: &Value) -> Result<Response, ProviderError> {
         let url = format!("{}/v1/chat/completions", self.host.trim_end_matches('/'));
 
-        let response = self.client.post(&url).json(&payload).send().await?;
+        let response = self
+            .retry
+            .send(self.client.post(&url).json(payload))
+            .await?;
 
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(&payload).await?;
         handle_response_openai_compat(response).await
     }
 }
@@ -86,14 +105,15 @@ impl Provider for OllamaProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let payload = create_request(
-            &self.model,
+            model_config,
             system,
             messages,
             tools,
@@ -116,4 +136,43 @@ impl Provider for OllamaProvider {
         super::utils::emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let payload = create_request(
+            &self.model,
+            system,
+            messages,
+            tools,
+            &super::utils::ImageFormat::OpenAi,
+        )?;
+
+        let response = self.send(&with_streaming(payload)).await?;
+        if response.status() != StatusCode::OK {
+            // Non-streamed error bodies are mapped the same way as in `complete`
+            return Err(handle_response_openai_compat(response)
+                .await
+                .expect_err("only successful responses are streamed"));
+        }
+
+        Ok(stream_response(
+            response,
+            &self.model.model_name,
+            response_to_deltas,
+        ))
+    }
 }
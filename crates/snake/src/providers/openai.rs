@@ -1,13 +1,19 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Response, StatusCode};
 use serde_json::Value;
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderStream, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::formats::openai::{
+    create_request, get_usage, response_to_deltas, response_to_message, with_streaming,
+};
+use super::retry::RetryConfig;
+use super::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, stream_response, ImageFormat,
+};
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -28,6 +34,8 @@ pub const OPEN_AI_DOC_URL: &str = "https://platform.openai.com/docs/models";
 pub struct OpenAiProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     api_key: String,
     model: ModelConfig,
@@ -42,7 +50,11 @@ impl Default for OpenAiProvider {
 
 impl OpenAiProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let api_key: String = config.get_secret("OPENAI_API_KEY")?;
         let host: String = config
             .get("OPENAI_HOST")
@@ -53,23 +65,28 @@ impl OpenAiProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             api_key,
             model,
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, payload: &Value) -> Result<Response, ProviderError> {
         let url = format!("{}/v1/chat/completions", self.host.trim_end_matches('/'));
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&payload)
-            .send()
-            .await?;
+            .json(payload);
+        let response = self.retry.send(request).await?;
 
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(&payload).await?;
         handle_response_openai_compat(response).await
     }
 }
@@ -102,13 +119,14 @@ impl Provider for OpenAiProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        let payload = create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
 
         // Make request
         let response = self.post(payload.clone()).await?;
@@ -127,4 +145,37 @@ impl Provider for OpenAiProvider {
         emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = self.send(&with_streaming(payload)).await?;
+        if response.status() != StatusCode::OK {
+            // Non-streamed error bodies are mapped the same way as in `complete`
+            return Err(handle_response_openai_compat(response)
+                .await
+                .expect_err("only successful responses are streamed"));
+        }
+
+        Ok(stream_response(
+            response,
+            &self.model.model_name,
+            response_to_deltas,
+        ))
+    }
 }
@@ -6,7 +6,9 @@ use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::retry::RetryConfig;
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat};
+use crate::config::ConfigResolver;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
@@ -23,6 +25,8 @@ pub const OPENROUTER_DOC_URL: &str = "https://openrouter.ai/models";
 pub struct OpenRouterProvider {
     #[serde(skip)]
     client: Client,
+    #[serde(skip)]
+    retry: RetryConfig,
     host: String,
     api_key: String,
     model: ModelConfig,
@@ -37,7 +41,11 @@ impl Default for OpenRouterProvider {
 
 impl OpenRouterProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let config = crate::config::Config::global();
+        Self::from_config(&crate::config::Config::global().resolver(), model)
+    }
+
+    /// Create the provider with the settings and secrets read through a resolver
+    pub fn from_config(config: &ConfigResolver, model: ModelConfig) -> Result<Self> {
         let api_key: String = config.get_secret("OPENROUTER_API_KEY")?;
         let host: String = config
             .get("OPENROUTER_HOST")
@@ -49,6 +57,7 @@ impl OpenRouterProvider {
 
         Ok(Self {
             client,
+            retry: RetryConfig::from_config(config.config()),
             host,
             api_key,
             model,
@@ -61,16 +70,15 @@ impl OpenRouterProvider {
             self.host.trim_end_matches('/')
         );
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("HTTP-Referer", "https://github.com/block/goose")
             .header("X-Title", "Goose")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.retry.send(request).await?;
 
         handle_response_openai_compat(response).await
     }
@@ -189,14 +197,15 @@ impl Provider for OpenRouterProvider {
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_model(
         &self,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         // Create the base payload
-        let payload = create_request_based_on_model(&self.model, system, messages, tools)?;
+        let payload = create_request_based_on_model(model_config, system, messages, tools)?;
 
         // Make request
         let response = self.post(payload.clone()).await?;
@@ -215,4 +224,14 @@ impl Provider for OpenRouterProvider {
         emit_debug_trace(self, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
 }
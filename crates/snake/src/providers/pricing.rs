@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::base::Usage;
+use crate::config::Config;
+
+/// The config key holding prices that override or extend the built in table
+pub const PRICING_CONFIG_KEY: &str = "GOOSE_MODEL_PRICING";
+
+/// The price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl ModelPricing {
+    pub const fn new(input: f64, output: f64) -> Self {
+        Self { input, output }
+    }
+
+    /// The cost of the tokens in the usage, or None if the provider reported no token counts
+    pub fn cost(&self, usage: &Usage) -> Option<f64> {
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+        let input = usage.input_tokens.unwrap_or(0) as f64 * self.input;
+        let output = usage.output_tokens.unwrap_or(0) as f64 * self.output;
+        Some((input + output) / 1_000_000.0)
+    }
+}
+
+/// Published list prices of the models the built in providers know about
+const BUILTIN_PRICING: &[(&str, ModelPricing)] = &[
+    // https://openai.com/api/pricing
+    ("gpt-4o", ModelPricing::new(2.5, 10.0)),
+    ("gpt-4o-mini", ModelPricing::new(0.15, 0.6)),
+    ("gpt-4-turbo", ModelPricing::new(10.0, 30.0)),
+    ("gpt-3.5-turbo", ModelPricing::new(0.5, 1.5)),
+    ("o1", ModelPricing::new(15.0, 60.0)),
+    ("o1-mini", ModelPricing::new(3.0, 12.0)),
+    // https://www.anthropic.com/pricing#anthropic-api
+    ("claude-3-5-sonnet", ModelPricing::new(3.0, 15.0)),
+    ("claude-3-5-haiku", ModelPricing::new(0.8, 4.0)),
+    ("claude-3-opus", ModelPricing::new(15.0, 75.0)),
+    ("claude-3-sonnet", ModelPricing::new(3.0, 15.0)),
+    ("claude-3-haiku", ModelPricing::new(0.25, 1.25)),
+    // https://ai.google.dev/pricing
+    ("gemini-1.5-pro", ModelPricing::new(1.25, 5.0)),
+    ("gemini-1.5-flash", ModelPricing::new(0.075, 0.3)),
+];
+
+/// Looks up model prices, preferring those configured in `GOOSE_MODEL_PRICING`
+///
+/// A price applies to every model whose name starts with its key, so `gpt-4o` also prices
+/// `gpt-4o-2024-08-06`, and the longest matching key wins. Provider prefixes like
+/// `anthropic/` are ignored, and `.` matches `-` so `claude-3.5-sonnet` is priced as
+/// `claude-3-5-sonnet`.
+///
+/// ```yaml
+/// GOOSE_MODEL_PRICING:
+///   gpt-4o:
+///     input: 2.5
+///     output: 10
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Pricing {
+    overrides: HashMap<String, ModelPricing>,
+}
+
+impl Pricing {
+    pub fn new(overrides: HashMap<String, ModelPricing>) -> Self {
+        Self { overrides }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.get(PRICING_CONFIG_KEY).unwrap_or_default())
+    }
+
+    /// Find the price of a model
+    pub fn get(&self, model: &str) -> Option<ModelPricing> {
+        let model = normalize(model);
+        let matching = |key: &str| {
+            let key = normalize(key);
+            model.starts_with(&key).then_some(key.len())
+        };
+
+        // Overrides win over built in prices with a key of the same length
+        let overrides = self
+            .overrides
+            .iter()
+            .filter_map(|(key, pricing)| matching(key).map(|len| ((len, 1), *pricing)));
+        let builtin = BUILTIN_PRICING
+            .iter()
+            .filter_map(|(key, pricing)| matching(key).map(|len| ((len, 0), *pricing)));
+
+        overrides
+            .chain(builtin)
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, pricing)| pricing)
+    }
+
+    /// The cost of a request to the model, or None if the model has no known price
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.get(model)?.cost(usage)
+    }
+}
+
+fn normalize(model: &str) -> String {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    name.to_lowercase().replace('.', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_pricing_matches_longest_prefix() {
+        let pricing = Pricing::default();
+        assert_eq!(pricing.get("gpt-4o"), Some(ModelPricing::new(2.5, 10.0)));
+        assert_eq!(
+            pricing.get("gpt-4o-2024-08-06"),
+            Some(ModelPricing::new(2.5, 10.0))
+        );
+        assert_eq!(
+            pricing.get("gpt-4o-mini"),
+            Some(ModelPricing::new(0.15, 0.6))
+        );
+        assert_eq!(pricing.get("o1-mini"), Some(ModelPricing::new(3.0, 12.0)));
+        assert_eq!(
+            pricing.get("anthropic/claude-3.5-sonnet"),
+            Some(ModelPricing::new(3.0, 15.0))
+        );
+        assert_eq!(pricing.get("llama3.2"), None);
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let pricing = Pricing::new(HashMap::from([
+            ("gpt-4o".to_string(), ModelPricing::new(1.0, 2.0)),
+            ("llama".to_string(), ModelPricing::new(0.0, 0.0)),
+        ]));
+        assert_eq!(pricing.get("gpt-4o"), Some(ModelPricing::new(1.0, 2.0)));
+        // A more specific built in price still applies
+        assert_eq!(
+            pricing.get("gpt-4o-mini"),
+            Some(ModelPricing::new(0.15, 0.6))
+        );
+        assert_eq!(pricing.get("llama3.2"), Some(ModelPricing::new(0.0, 0.0)));
+        assert_eq!(
+            pricing.get("claude-3-haiku"),
+            Some(ModelPricing::new(0.25, 1.25))
+        );
+    }
+
+    #[test]
+    fn test_cost() {
+        let pricing = Pricing::default();
+        let usage = Usage::new(Some(1_000_000), Some(100_000), Some(1_100_000));
+        let cost = pricing.cost("claude-3-5-sonnet-latest", &usage).unwrap();
+        assert!((cost - 4.5).abs() < 1e-9);
+
+        assert_eq!(pricing.cost("unknown-model", &usage), None);
+        assert_eq!(pricing.cost("gpt-4o", &Usage::default()), None);
+    }
+}
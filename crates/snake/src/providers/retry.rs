@@ -0,0 +1,398 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use super::errors::ProviderError;
+use crate::config::Config;
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(120);
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Retries provider requests that fail with rate limits, server errors or connection
+/// problems, backing off exponentially with jitter between attempts
+///
+/// Delays requested by the server through `Retry-After` or `x-ratelimit-reset-*` headers
+/// are honored in place of the backoff. No attempt is started after the deadline.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    max_attempts: u32,
+    deadline: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ATTEMPTS, DEFAULT_DEADLINE)
+    }
+}
+
+impl RetryConfig {
+    pub fn new(max_attempts: u32, deadline: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            deadline,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Load the retry settings from `GOOSE_PROVIDER_MAX_ATTEMPTS` and
+    /// `GOOSE_PROVIDER_RETRY_DEADLINE` (in seconds), using the defaults for any that are unset
+    pub fn from_config(config: &Config) -> Self {
+        let max_attempts = config
+            .get("GOOSE_PROVIDER_MAX_ATTEMPTS")
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        let deadline = config
+            .get("GOOSE_PROVIDER_RETRY_DEADLINE")
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_DEADLINE);
+        Self::new(max_attempts, deadline)
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// The jittered delay before the given retry, counting from 1
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        let capped = exponential.min(self.max_backoff);
+        // Wait at least half of the backoff, so concurrent clients spread out without
+        // retrying immediately
+        capped.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    /// Send the request, retrying it while the failure is transient
+    ///
+    /// Once the attempts or the deadline run out, the last response is returned as is so
+    /// the provider can map its status to a ProviderError.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, ProviderError> {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let this_attempt = request.try_clone().ok_or_else(|| {
+                ProviderError::ExecutionError("Request body cannot be retried".to_string())
+            })?;
+
+            let (delay, failure) = match this_attempt.send().await {
+                Ok(response) if is_retryable(response.status()) => {
+                    let delay = retry_after(response.headers(), Utc::now())
+                        .unwrap_or_else(|| self.backoff(attempt));
+                    (delay, Failure::Status(response))
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    (self.backoff(attempt), Failure::Transport(e))
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if attempt >= self.max_attempts || start.elapsed() + delay > self.deadline {
+                return match failure {
+                    Failure::Status(response) => Ok(response),
+                    Failure::Transport(e) => Err(e.into()),
+                };
+            }
+
+            tracing::warn!(
+                "Provider request failed with {}, retrying in {:.1}s (attempt {}/{})",
+                failure,
+                delay.as_secs_f64(),
+                attempt + 1,
+                self.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// A failed attempt that may succeed when retried
+enum Failure {
+    Status(Response),
+    Transport(reqwest::Error),
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Status(response) => write!(f, "status {}", response.status()),
+            Failure::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Rate limits, overloaded servers and gateway errors are worth retrying
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    ) || status.as_u16() == 529 // Anthropic's overloaded error
+}
+
+/// The delay the server asked for through `Retry-After` or, failing that, the longest of
+/// the `x-ratelimit-reset-*` headers
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let parse = |value: &reqwest::header::HeaderValue| {
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| parse_reset(value.trim(), now))
+    };
+
+    if let Some(delay) = headers.get(reqwest::header::RETRY_AFTER).and_then(parse) {
+        return Some(delay);
+    }
+
+    headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-ratelimit-reset"))
+        .filter_map(|(_, value)| parse(value))
+        .max()
+}
+
+/// Parse a reset header, which is either a number of seconds, a duration such as `6m0s` or
+/// `20ms`, or a timestamp in RFC 3339 or HTTP date format
+fn parse_reset(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    if let Some(duration) = parse_duration(value) {
+        return Some(duration);
+    }
+
+    let timestamp = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .ok()?;
+    // A timestamp in the past means we may retry right away
+    Some(
+        (timestamp.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Parse a duration made of `h`, `m`, `s` and `ms` components, e.g. `1h2m3.5s`
+fn parse_duration(value: &str) -> Option<Duration> {
+    if value.is_empty() {
+        return None;
+    }
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|&len| len > 0)?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        total += number
+            * match &rest[..unit_len] {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                "ms" => 0.001,
+                _ => return None,
+            };
+        rest = &rest[unit_len..];
+    }
+    Duration::try_from_secs_f64(total).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fast_retries(max_attempts: u32, deadline: Duration) -> RetryConfig {
+        RetryConfig::new(max_attempts, deadline)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+    }
+
+    async fn post(retry: &RetryConfig, server: &MockServer) -> Result<Response, ProviderError> {
+        let request = reqwest::Client::new()
+            .post(server.uri())
+            .json(&serde_json::json!({"model": "test"}));
+        retry.send(request).await
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_with_jitter() {
+        let retry =
+            RetryConfig::default().with_backoff(Duration::from_secs(1), Duration::from_secs(30));
+        for _ in 0..20 {
+            let first = retry.backoff(1);
+            assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+            let third = retry.backoff(3);
+            assert!(third >= Duration::from_secs(2) && third <= Duration::from_secs(4));
+            // Capped at the maximum backoff
+            let tenth = retry.backoff(10);
+            assert!(tenth >= Duration::from_secs(15) && tenth <= Duration::from_secs(30));
+        }
+    }
+
+    #[test]
+    fn test_parse_reset() {
+        let now = Utc::now();
+        assert_eq!(parse_reset("2", now), Some(Duration::from_secs(2)));
+        assert_eq!(parse_reset("0.5", now), Some(Duration::from_millis(500)));
+        assert_eq!(parse_reset("6m0s", now), Some(Duration::from_secs(360)));
+        assert_eq!(parse_reset("1h2m3s", now), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_reset("20ms", now), Some(Duration::from_millis(20)));
+        assert_eq!(parse_reset("1.5s", now), Some(Duration::from_millis(1500)));
+
+        let later = now + chrono::Duration::seconds(30);
+        assert_eq!(
+            parse_reset(&later.to_rfc3339(), now),
+            Some(Duration::from_secs(30))
+        );
+        let later = now.with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+            + chrono::Duration::seconds(30);
+        let delay = parse_reset(&later.to_rfc2822(), now).unwrap();
+        // HTTP dates only have second precision
+        assert!(delay <= Duration::from_secs(30) && delay > Duration::from_secs(28));
+        let earlier = now - chrono::Duration::seconds(30);
+        assert_eq!(
+            parse_reset(&earlier.to_rfc3339(), now),
+            Some(Duration::ZERO)
+        );
+
+        assert_eq!(parse_reset("soon", now), None);
+        assert_eq!(parse_reset("5x", now), None);
+        assert_eq!(parse_reset("", now), None);
+    }
+
+    #[test]
+    fn test_retry_after_prefers_retry_after_header() {
+        let now = Utc::now();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-reset-requests", "1s".parse().unwrap());
+        headers.insert("x-ratelimit-reset-tokens", "6m0s".parse().unwrap());
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(360)));
+
+        headers.insert("retry-after", "3".parse().unwrap());
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(3)));
+
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_until_success() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = post(&fast_retries(5, Duration::from_secs(10)), &server).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_honors_retry_after() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let start = Instant::now();
+        let response = post(&fast_retries(5, Duration::from_secs(10)), &server).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        // The backoff alone would have retried after at most 10ms
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        // The last response is returned so the provider can report the rate limit
+        let response = post(&fast_retries(3, Duration::from_secs(10)), &server).await?;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_when_delay_exceeds_deadline() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "60"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let start = Instant::now();
+        let response = post(&fast_retries(5, Duration::from_secs(5)), &server).await?;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = post(&fast_retries(5, Duration::from_secs(10)), &server).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_returns_connection_error_after_retries() {
+        // Nothing listens on a port once its listener is dropped
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let request = reqwest::Client::new()
+            .post(format!("http://{}", address))
+            .json(&serde_json::json!({}));
+
+        let result = fast_retries(2, Duration::from_secs(10)).send(request).await;
+        assert!(matches!(result, Err(ProviderError::ExecutionError(_))));
+    }
+}
@@ -1,12 +1,16 @@
-use super::base::Usage;
+use super::base::{ProviderDelta, ProviderStream, ProviderUsage, Usage};
 use anyhow::Result;
+use futures::StreamExt;
 use regex::Regex;
 use reqwest::{Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
+use crate::message::Message;
 use crate::providers::errors::ProviderError;
 use mcp_core::content::ImageContent;
+use mcp_core::tool::ToolCall;
+use mcp_core::ToolError;
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum ImageFormat {
@@ -159,10 +163,182 @@ pub fn emit_debug_trace<T: serde::Serialize>(
     );
 }
 
+/// Collects the deltas of a streamed completion into the final message and usage
+pub struct StreamAccumulator {
+    text: String,
+    // (index, id, name, arguments) in the order the tool calls started
+    tool_calls: Vec<(usize, String, String, String)>,
+    usage: Usage,
+    model: String,
+}
+
+impl StreamAccumulator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            text: String::new(),
+            tool_calls: Vec::new(),
+            usage: Usage::default(),
+            model: model.to_string(),
+        }
+    }
+
+    /// The number of tool calls started so far
+    pub fn tool_call_count(&self) -> usize {
+        self.tool_calls.len()
+    }
+
+    /// Record the model reported by the stream, which may be more specific than the requested one
+    pub fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+    }
+
+    /// Merge usage reported by the stream, later values replace earlier ones
+    pub fn update_usage(&mut self, usage: Usage) {
+        self.usage.input_tokens = usage.input_tokens.or(self.usage.input_tokens);
+        self.usage.output_tokens = usage.output_tokens.or(self.usage.output_tokens);
+        // A partial update makes the earlier total stale, it is recomputed in `finish` if missing
+        self.usage.total_tokens = usage.total_tokens;
+    }
+
+    pub fn push(&mut self, delta: &ProviderDelta) {
+        match delta {
+            ProviderDelta::Text(text) => self.text.push_str(text),
+            ProviderDelta::ToolCall {
+                index,
+                id,
+                name,
+                arguments,
+            } => {
+                let position = match self.tool_calls.iter().position(|call| call.0 == *index) {
+                    Some(position) => position,
+                    None => {
+                        self.tool_calls
+                            .push((*index, String::new(), String::new(), String::new()));
+                        self.tool_calls.len() - 1
+                    }
+                };
+                let call = &mut self.tool_calls[position];
+                if let Some(id) = id {
+                    call.1 = id.clone();
+                }
+                if let Some(name) = name {
+                    call.2 = name.clone();
+                }
+                call.3.push_str(arguments);
+            }
+            ProviderDelta::Finish { .. } => {}
+        }
+    }
+
+    /// Build the final record of the stream
+    pub fn finish(self) -> ProviderDelta {
+        let mut message = Message::assistant();
+        if !self.text.is_empty() {
+            message = message.with_text(self.text);
+        }
+
+        for (_, id, name, arguments) in self.tool_calls {
+            if !is_valid_function_name(&name) {
+                let error = ToolError::NotFound(format!(
+                    "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+                    name
+                ));
+                message = message.with_tool_request(id, Err(error));
+                continue;
+            }
+
+            // Tools without parameters may not stream any argument fragments
+            let arguments = if arguments.trim().is_empty() {
+                Ok(json!({}))
+            } else {
+                serde_json::from_str::<Value>(&arguments)
+            };
+            match arguments {
+                Ok(params) => {
+                    message = message.with_tool_request(id, Ok(ToolCall::new(&name, params)));
+                }
+                Err(e) => {
+                    let error = ToolError::InvalidParameters(format!(
+                        "Could not interpret tool use parameters for id {}: {}",
+                        id, e
+                    ));
+                    message = message.with_tool_request(id, Err(error));
+                }
+            }
+        }
+
+        let mut usage = self.usage;
+        if usage.total_tokens.is_none() {
+            if let (Some(input), Some(output)) = (usage.input_tokens, usage.output_tokens) {
+                usage.total_tokens = Some(input + output);
+            }
+        }
+
+        ProviderDelta::Finish {
+            message,
+            usage: ProviderUsage::new(self.model, usage),
+        }
+    }
+}
+
+/// Stream a completion from a server-sent events response
+///
+/// Each event's JSON data is passed to `handle_event`, which converts it into deltas and records
+/// usage on the accumulator. The deltas are forwarded as they arrive and the stream ends with the
+/// assembled `ProviderDelta::Finish`.
+pub fn stream_response<F>(response: Response, model: &str, mut handle_event: F) -> ProviderStream
+where
+    F: FnMut(&Value, &mut StreamAccumulator) -> Result<Vec<ProviderDelta>, ProviderError>
+        + Send
+        + 'static,
+{
+    let mut accumulator = StreamAccumulator::new(model);
+    let mut bytes = response.bytes_stream();
+
+    Box::pin(async_stream::try_stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut done = false;
+        while !done {
+            match bytes.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => {
+                    // Flush a final event which isn't terminated by a newline
+                    buffer.push(b'\n');
+                    done = true;
+                }
+            }
+
+            while let Some(position) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=position).collect();
+                let line = String::from_utf8_lossy(&line);
+                let data = match line.trim().strip_prefix("data:") {
+                    Some(data) => data.trim(),
+                    None => continue,
+                };
+                if data.is_empty() || data == "[DONE]" {
+                    continue;
+                }
+
+                let event: Value = serde_json::from_str(data).map_err(|e| {
+                    ProviderError::RequestFailed(format!("Invalid event in response stream: {}", e))
+                })?;
+                for delta in handle_event(&event, &mut accumulator)? {
+                    accumulator.push(&delta);
+                    yield delta;
+                }
+            }
+        }
+
+        yield accumulator.finish();
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
     fn test_sanitize_function_name() {
@@ -228,4 +404,76 @@ mod tests {
         let unescaped_value = unescape_json_values(&value);
         assert_eq!(unescaped_value, json!({"text": "Hello World"}));
     }
+
+    #[tokio::test]
+    async fn test_stream_response() -> anyhow::Result<()> {
+        // Events are split across lines and the last one has no trailing newline
+        let body = "event: delta\ndata: {\"text\": \"Hel\"}\n\ndata: {\"text\": \"lo\"}\r\n\ndata: [DONE]\n\ndata: {\"tokens\": 7}";
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let response = reqwest::Client::new().post(server.uri()).send().await?;
+        let stream = stream_response(response, "test-model", |event, accumulator| {
+            if let Some(tokens) = event["tokens"].as_i64() {
+                accumulator.update_usage(Usage::new(None, Some(tokens as i32), None));
+            }
+            Ok(event["text"]
+                .as_str()
+                .map(|text| ProviderDelta::Text(text.to_string()))
+                .into_iter()
+                .collect())
+        });
+        let deltas: Vec<ProviderDelta> = stream.map(|delta| delta.unwrap()).collect().await;
+
+        assert_eq!(deltas.len(), 3);
+        assert!(matches!(&deltas[0], ProviderDelta::Text(text) if text == "Hel"));
+        assert!(matches!(&deltas[1], ProviderDelta::Text(text) if text == "lo"));
+        match &deltas[2] {
+            ProviderDelta::Finish { message, usage } => {
+                assert_eq!(message.as_concat_text(), "Hello");
+                assert_eq!(usage.model, "test-model");
+                assert_eq!(usage.usage.output_tokens, Some(7));
+            }
+            _ => panic!("Expected Finish delta"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_accumulator_invalid_tool_call() {
+        let mut accumulator = StreamAccumulator::new("test-model");
+        for delta in [
+            ProviderDelta::ToolCall {
+                index: 0,
+                id: Some("1".to_string()),
+                name: Some("invalid fn".to_string()),
+                arguments: String::new(),
+            },
+            ProviderDelta::ToolCall {
+                index: 1,
+                id: Some("2".to_string()),
+                name: Some("valid_fn".to_string()),
+                arguments: "{\"param\": ".to_string(),
+            },
+        ] {
+            accumulator.push(&delta);
+        }
+
+        match accumulator.finish() {
+            ProviderDelta::Finish { message, .. } => {
+                let first = message.content[0].as_tool_request().unwrap();
+                assert!(matches!(first.tool_call, Err(ToolError::NotFound(_))));
+                let second = message.content[1].as_tool_request().unwrap();
+                assert!(matches!(
+                    second.tool_call,
+                    Err(ToolError::InvalidParameters(_))
+                ));
+            }
+            _ => panic!("Expected Finish delta"),
+        }
+    }
 }
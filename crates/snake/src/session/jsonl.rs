@@ -0,0 +1,254 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use super::{validate_name, SessionError, SessionMetadata, SessionResult, SessionStore};
+use crate::message::Message;
+
+/// Stores each session as a `<name>.jsonl` file of messages, one per line
+///
+/// The metadata is kept in a `<name>.json` file beside the messages. Sessions written
+/// before there was metadata get what can be told from their file.
+pub struct JsonlSessionStore {
+    dir: PathBuf,
+}
+
+impl JsonlSessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The file holding a session's messages
+    pub fn session_file(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", name))
+    }
+
+    fn metadata_file(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// The path of an existing session's messages
+    fn existing_session_file(&self, name: &str) -> SessionResult<PathBuf> {
+        validate_name(name)?;
+        let path = self.session_file(name);
+        if !path.exists() {
+            return Err(SessionError::NotFound(name.to_string()));
+        }
+        Ok(path)
+    }
+
+    fn write_metadata(&self, metadata: &SessionMetadata) -> SessionResult<()> {
+        write_replacing(&self.metadata_file(&metadata.name), |writer| {
+            serde_json::to_writer_pretty(writer, metadata)?;
+            Ok(())
+        })
+    }
+}
+
+/// Replace a file with what `write` writes, so a failed or interrupted write leaves the old
+/// content in place
+///
+/// The content goes to a hidden temporary file beside it, which is renamed over the file
+/// once written.
+fn write_replacing(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> SessionResult<()>,
+) -> SessionResult<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let result = File::create(&temp_path)
+        .map_err(SessionError::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .and_then(|_| Ok(fs::rename(&temp_path, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// The metadata of a session without a metadata file, from its messages file
+fn legacy_metadata(name: &str, path: &Path) -> SessionResult<SessionMetadata> {
+    let file_metadata = fs::metadata(path)?;
+    let updated: DateTime<Utc> = file_metadata.modified()?.into();
+    let created = file_metadata
+        .created()
+        .map(DateTime::<Utc>::from)
+        .unwrap_or(updated);
+    let message_count = BufReader::new(File::open(path)?)
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .count();
+
+    Ok(SessionMetadata {
+        name: name.to_string(),
+        working_dir: None,
+        provider: None,
+        model: None,
+        created,
+        updated,
+        message_count,
+        usage: Vec::new(),
+        forked_from: None,
+    })
+}
+
+impl SessionStore for JsonlSessionStore {
+    fn create(&self, metadata: &SessionMetadata) -> SessionResult<()> {
+        validate_name(&metadata.name)?;
+        let path = self.session_file(&metadata.name);
+        if path.exists() {
+            return Err(SessionError::AlreadyExists(metadata.name.clone()));
+        }
+        File::create(path)?;
+        self.write_metadata(metadata)
+    }
+
+    fn metadata(&self, name: &str) -> SessionResult<SessionMetadata> {
+        let path = self.existing_session_file(name)?;
+        match fs::read_to_string(self.metadata_file(name)) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => legacy_metadata(name, &path),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn update(&self, metadata: &SessionMetadata) -> SessionResult<()> {
+        self.existing_session_file(&metadata.name)?;
+        self.write_metadata(metadata)
+    }
+
+    fn messages(&self, name: &str) -> SessionResult<Vec<Message>> {
+        let path = self.existing_session_file(name)?;
+        let mut messages = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                messages.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(messages)
+    }
+
+    fn save_messages(&self, name: &str, messages: &[Message]) -> SessionResult<()> {
+        let mut metadata = self.metadata(name)?;
+
+        write_replacing(&self.session_file(name), |writer| {
+            for message in messages {
+                serde_json::to_writer(&mut *writer, message)?;
+                writeln!(writer)?;
+            }
+            Ok(())
+        })?;
+
+        metadata.message_count = messages.len();
+        metadata.updated = Utc::now();
+        self.write_metadata(&metadata)
+    }
+
+    fn list(&self) -> SessionResult<Vec<SessionMetadata>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            // One unreadable session shouldn't hide the rest
+            match self.metadata(name) {
+                Ok(metadata) => sessions.push(metadata),
+                Err(e) => tracing::warn!("Skipping session {}: {}", path.display(), e),
+            }
+        }
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated));
+        Ok(sessions)
+    }
+
+    fn delete(&self, name: &str) -> SessionResult<()> {
+        let path = self.existing_session_file(name)?;
+        fs::remove_file(path)?;
+        match fs::remove_file(self.metadata_file(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> SessionResult<()> {
+        let mut metadata = self.metadata(name)?;
+        validate_name(new_name)?;
+        let new_path = self.session_file(new_name);
+        if new_path.exists() {
+            return Err(SessionError::AlreadyExists(new_name.to_string()));
+        }
+
+        fs::rename(self.session_file(name), new_path)?;
+        metadata.name = new_name.to_string();
+        self.write_metadata(&metadata)?;
+        match fs::remove_file(self.metadata_file(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reads_sessions_without_metadata() {
+        let dir = tempdir().unwrap();
+        let messages = [
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi"),
+        ];
+        let content: String = messages
+            .iter()
+            .map(|m| format!("{}\n", serde_json::to_string(m).unwrap()))
+            .collect();
+        fs::write(dir.path().join("old.jsonl"), content).unwrap();
+
+        let store = JsonlSessionStore::new(dir.path().to_path_buf());
+        let metadata = store.metadata("old").unwrap();
+        assert_eq!(metadata.message_count, 2);
+        assert_eq!(metadata.model, None);
+        assert_eq!(store.messages("old").unwrap(), messages);
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_leaves_no_temporary_files() {
+        let dir = tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf());
+        store.create(&SessionMetadata::new("chat")).unwrap();
+        store
+            .save_messages("chat", &[Message::user().with_text("Hello")])
+            .unwrap();
+        store
+            .save_messages("chat", &[Message::user().with_text("Hi")])
+            .unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["chat.json", "chat.jsonl"]);
+        assert_eq!(store.messages("chat").unwrap().len(), 1);
+    }
+}
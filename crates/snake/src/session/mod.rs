@@ -0,0 +1,360 @@
+//! Storage for the conversations of sessions, shared by the CLI and the server
+//!
+//! A session is a named list of messages with metadata about where and how it ran. Stores
+//! can fork a session at any message into a new one, and list or search the sessions they
+//! hold. Two backends are provided:
+//! - [`JsonlSessionStore`]: one `<name>.jsonl` file of messages per session, the format the
+//!   CLI has always written, with the metadata in a `<name>.json` file beside it.
+//! - [`SqliteSessionStore`]: every session in one SQLite database.
+
+mod jsonl;
+mod sqlite;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::Config;
+use crate::message::Message;
+use crate::providers::base::ProviderUsage;
+
+pub use jsonl::JsonlSessionStore;
+pub use sqlite::SqliteSessionStore;
+
+/// The config key choosing the backend sessions are stored in
+pub const SESSION_STORE_CONFIG_KEY: &str = "GOOSE_SESSION_STORE";
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Session '{0}' not found")]
+    NotFound(String),
+    #[error("Session '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("Invalid session name '{0}'")]
+    InvalidName(String),
+    #[error("Session '{session}' has {count} messages, so it can't be forked at message {index}")]
+    MessageOutOfRange {
+        session: String,
+        index: usize,
+        count: usize,
+    },
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse session data: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+pub type SessionResult<T> = Result<T, SessionError>;
+
+/// Where a forked session came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionFork {
+    /// The name of the session it was forked from
+    pub session: String,
+    /// How many of that session's messages it started with
+    pub message_count: usize,
+}
+
+/// What a store knows about a session besides its messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    pub name: String,
+    /// The directory the session was started in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub created: DateTime<Utc>,
+    /// When the messages were last saved
+    pub updated: DateTime<Utc>,
+    #[serde(default)]
+    pub message_count: usize,
+    /// The usage of every request made for the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage: Vec<ProviderUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<SessionFork>,
+}
+
+impl SessionMetadata {
+    /// Metadata for a new session started in the current directory
+    pub fn new(name: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            name: name.into(),
+            working_dir: std::env::current_dir().ok(),
+            provider: None,
+            model: None,
+            created: now,
+            updated: now,
+            message_count: 0,
+            usage: Vec::new(),
+            forked_from: None,
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl Into<String>, model: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self.model = Some(model.into());
+        self
+    }
+
+    /// The total tokens used by the session, counting requests that reported them
+    pub fn total_tokens(&self) -> i64 {
+        self.usage
+            .iter()
+            .filter_map(|usage| usage.usage.total_tokens)
+            .map(i64::from)
+            .sum()
+    }
+}
+
+/// Storage for sessions, keyed by name
+///
+/// Stores are shared between threads, and each call sees the effects of the calls that
+/// finished before it.
+pub trait SessionStore: Send + Sync {
+    /// Add a new session with no messages
+    fn create(&self, metadata: &SessionMetadata) -> SessionResult<()>;
+
+    fn metadata(&self, name: &str) -> SessionResult<SessionMetadata>;
+
+    /// Replace the metadata of an existing session
+    fn update(&self, metadata: &SessionMetadata) -> SessionResult<()>;
+
+    fn messages(&self, name: &str) -> SessionResult<Vec<Message>>;
+
+    /// Replace the messages of an existing session, updating its message count and time
+    fn save_messages(&self, name: &str, messages: &[Message]) -> SessionResult<()>;
+
+    /// Every session, most recently updated first
+    fn list(&self) -> SessionResult<Vec<SessionMetadata>>;
+
+    fn delete(&self, name: &str) -> SessionResult<()>;
+
+    /// Give a session a new name, which must not be in use
+    fn rename(&self, name: &str, new_name: &str) -> SessionResult<()>;
+
+    fn exists(&self, name: &str) -> SessionResult<bool> {
+        match self.metadata(name) {
+            Ok(_) => Ok(true),
+            Err(SessionError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sessions whose name or messages contain the query, ignoring case, most recent first
+    fn search(&self, query: &str) -> SessionResult<Vec<SessionMetadata>> {
+        let query = query.to_lowercase();
+        let mut found = Vec::new();
+        for metadata in self.list()? {
+            let matches = metadata.name.to_lowercase().contains(&query)
+                || self
+                    .messages(&metadata.name)?
+                    .iter()
+                    .any(|message| message.as_concat_text().to_lowercase().contains(&query));
+            if matches {
+                found.push(metadata);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Start a new session from the first `message_count` messages of another
+    ///
+    /// The fork keeps the working directory, provider and model of the original, but not its
+    /// usage.
+    fn fork(
+        &self,
+        name: &str,
+        message_count: usize,
+        new_name: &str,
+    ) -> SessionResult<SessionMetadata> {
+        let original = self.metadata(name)?;
+        let mut messages = self.messages(name)?;
+        if message_count > messages.len() {
+            return Err(SessionError::MessageOutOfRange {
+                session: name.to_string(),
+                index: message_count,
+                count: messages.len(),
+            });
+        }
+        messages.truncate(message_count);
+
+        let mut metadata = SessionMetadata::new(new_name);
+        metadata.working_dir = original.working_dir;
+        metadata.provider = original.provider;
+        metadata.model = original.model;
+        metadata.forked_from = Some(SessionFork {
+            session: name.to_string(),
+            message_count,
+        });
+        self.create(&metadata)?;
+        self.save_messages(new_name, &messages)?;
+        self.metadata(new_name)
+    }
+}
+
+/// The backends sessions can be stored in
+///
+/// ```yaml
+/// GOOSE_SESSION_STORE: sqlite
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    /// A `.jsonl` file per session, readable by older versions of goose
+    #[default]
+    Jsonl,
+    /// A single `sessions.db` database
+    Sqlite,
+}
+
+/// The directory sessions are stored in, ~/.config/goose/sessions
+pub fn sessions_dir() -> SessionResult<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not determine home directory",
+        )
+    })?;
+    Ok(home_dir.join(".config").join("goose").join("sessions"))
+}
+
+/// Open the session store chosen in the config
+pub fn from_config(config: &Config) -> SessionResult<Arc<dyn SessionStore>> {
+    let kind: SessionStoreKind = config.get(SESSION_STORE_CONFIG_KEY).unwrap_or_default();
+    open(kind, sessions_dir()?)
+}
+
+/// Open a session store of a kind in a directory, creating the directory if needed
+pub fn open(kind: SessionStoreKind, dir: PathBuf) -> SessionResult<Arc<dyn SessionStore>> {
+    std::fs::create_dir_all(&dir)?;
+    Ok(match kind {
+        SessionStoreKind::Jsonl => Arc::new(JsonlSessionStore::new(dir)),
+        SessionStoreKind::Sqlite => Arc::new(SqliteSessionStore::open(dir.join("sessions.db"))?),
+    })
+}
+
+/// Session names become file names, so they can't contain path separators
+fn validate_name(name: &str) -> SessionResult<()> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_control);
+    if invalid {
+        return Err(SessionError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn check_store(store: &dyn SessionStore) {
+        let metadata = SessionMetadata::new("first").with_provider("openai", "gpt-4o");
+        store.create(&metadata).unwrap();
+        assert!(matches!(
+            store.create(&metadata),
+            Err(SessionError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            store.create(&SessionMetadata::new("../escape")),
+            Err(SessionError::InvalidName(_))
+        ));
+
+        let messages = vec![
+            Message::user().with_text("Where is the config loaded?"),
+            Message::assistant().with_text("In config/base.rs"),
+            Message::user().with_text("Thanks"),
+        ];
+        store.save_messages("first", &messages).unwrap();
+        assert_eq!(store.messages("first").unwrap(), messages);
+        let saved = store.metadata("first").unwrap();
+        assert_eq!(saved.message_count, 3);
+        assert_eq!(saved.model.as_deref(), Some("gpt-4o"));
+        assert!(saved.updated >= saved.created);
+
+        // Forking keeps the messages up to the fork, leaving the original alone
+        let fork = store.fork("first", 2, "second").unwrap();
+        assert_eq!(fork.message_count, 2);
+        assert_eq!(fork.provider.as_deref(), Some("openai"));
+        assert_eq!(
+            fork.forked_from,
+            Some(SessionFork {
+                session: "first".to_string(),
+                message_count: 2
+            })
+        );
+        assert_eq!(store.messages("second").unwrap(), messages[..2]);
+        assert_eq!(store.messages("first").unwrap().len(), 3);
+        assert!(matches!(
+            store.fork("first", 4, "third"),
+            Err(SessionError::MessageOutOfRange { .. })
+        ));
+
+        store
+            .save_messages("second", &[Message::user().with_text("Something else")])
+            .unwrap();
+        let names: Vec<_> = store.list().unwrap().into_iter().map(|m| m.name).collect();
+        assert_eq!(names, vec!["second", "first"]);
+
+        let found: Vec<_> = store
+            .search("CONFIG")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(found, vec!["first"]);
+        assert_eq!(store.search("sec").unwrap()[0].name, "second");
+
+        assert!(matches!(
+            store.rename("first", "second"),
+            Err(SessionError::AlreadyExists(_))
+        ));
+        store.rename("second", "renamed").unwrap();
+        assert!(!store.exists("second").unwrap());
+        assert_eq!(store.metadata("renamed").unwrap().name, "renamed");
+        assert_eq!(store.messages("renamed").unwrap().len(), 1);
+
+        store.delete("first").unwrap();
+        assert!(!store.exists("first").unwrap());
+        assert!(matches!(
+            store.messages("first"),
+            Err(SessionError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.delete("first"),
+            Err(SessionError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_jsonl_store() {
+        let dir = tempdir().unwrap();
+        check_store(&JsonlSessionStore::new(dir.path().to_path_buf()));
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        check_store(&SqliteSessionStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_open_creates_dir() {
+        let dir = tempdir().unwrap();
+        let store = open(SessionStoreKind::Sqlite, dir.path().join("sessions")).unwrap();
+        store.create(&SessionMetadata::new("session")).unwrap();
+        assert!(dir.path().join("sessions").join("sessions.db").exists());
+    }
+}
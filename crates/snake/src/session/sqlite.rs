@@ -0,0 +1,231 @@
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{validate_name, SessionError, SessionMetadata, SessionResult, SessionStore};
+use crate::message::Message;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        name TEXT PRIMARY KEY,
+        metadata TEXT NOT NULL,
+        updated INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        session TEXT NOT NULL REFERENCES sessions(name) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        text TEXT NOT NULL,
+        PRIMARY KEY (session, position)
+    );
+";
+
+/// Stores every session in one SQLite database
+///
+/// The metadata is kept as JSON, as is each message, alongside the lowercased text of the
+/// message for searching.
+pub struct SqliteSessionStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteSessionStore {
+    /// Open the database at a path, creating it if needed
+    pub fn open(path: impl AsRef<Path>) -> SessionResult<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Open a database that only lasts as long as the store
+    pub fn open_in_memory() -> SessionResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> SessionResult<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave a transaction half applied
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn write_metadata(conn: &Connection, metadata: &SessionMetadata) -> SessionResult<usize> {
+    Ok(conn.execute(
+        "UPDATE sessions SET metadata = ?2, updated = ?3 WHERE name = ?1",
+        params![
+            metadata.name,
+            serde_json::to_string(metadata)?,
+            metadata.updated.timestamp_micros()
+        ],
+    )?)
+}
+
+fn read_metadata(conn: &Connection, name: &str) -> SessionResult<SessionMetadata> {
+    let metadata: Option<String> = conn
+        .query_row(
+            "SELECT metadata FROM sessions WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match metadata {
+        Some(metadata) => Ok(serde_json::from_str(&metadata)?),
+        None => Err(SessionError::NotFound(name.to_string())),
+    }
+}
+
+/// Read the metadata column of each row a query returns
+fn query_metadata(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> SessionResult<Vec<SessionMetadata>> {
+    let mut statement = conn.prepare(sql)?;
+    let rows = statement.query_map(params, |row| row.get::<_, String>(0))?;
+    let mut sessions = Vec::new();
+    for row in rows {
+        sessions.push(serde_json::from_str(&row?)?);
+    }
+    Ok(sessions)
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn create(&self, metadata: &SessionMetadata) -> SessionResult<()> {
+        validate_name(&metadata.name)?;
+        let inserted = self.conn().execute(
+            "INSERT OR IGNORE INTO sessions (name, metadata, updated) VALUES (?1, ?2, ?3)",
+            params![
+                metadata.name,
+                serde_json::to_string(metadata)?,
+                metadata.updated.timestamp_micros()
+            ],
+        )?;
+        if inserted == 0 {
+            return Err(SessionError::AlreadyExists(metadata.name.clone()));
+        }
+        Ok(())
+    }
+
+    fn metadata(&self, name: &str) -> SessionResult<SessionMetadata> {
+        read_metadata(&self.conn(), name)
+    }
+
+    fn update(&self, metadata: &SessionMetadata) -> SessionResult<()> {
+        if write_metadata(&self.conn(), metadata)? == 0 {
+            return Err(SessionError::NotFound(metadata.name.clone()));
+        }
+        Ok(())
+    }
+
+    fn messages(&self, name: &str) -> SessionResult<Vec<Message>> {
+        let conn = self.conn();
+        read_metadata(&conn, name)?;
+        let mut statement =
+            conn.prepare("SELECT message FROM messages WHERE session = ?1 ORDER BY position")?;
+        let rows = statement.query_map([name], |row| row.get::<_, String>(0))?;
+        let mut messages = Vec::new();
+        for row in rows {
+            messages.push(serde_json::from_str(&row?)?);
+        }
+        Ok(messages)
+    }
+
+    fn save_messages(&self, name: &str, messages: &[Message]) -> SessionResult<()> {
+        let mut conn = self.conn();
+        let transaction = conn.transaction()?;
+        let mut metadata = read_metadata(&transaction, name)?;
+
+        transaction.execute("DELETE FROM messages WHERE session = ?1", [name])?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO messages (session, position, message, text) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (position, message) in messages.iter().enumerate() {
+                insert.execute(params![
+                    name,
+                    position as i64,
+                    serde_json::to_string(message)?,
+                    message.as_concat_text().to_lowercase()
+                ])?;
+            }
+        }
+
+        metadata.message_count = messages.len();
+        metadata.updated = Utc::now();
+        write_metadata(&transaction, &metadata)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn list(&self) -> SessionResult<Vec<SessionMetadata>> {
+        query_metadata(
+            &self.conn(),
+            "SELECT metadata FROM sessions ORDER BY updated DESC",
+            [],
+        )
+    }
+
+    fn delete(&self, name: &str) -> SessionResult<()> {
+        // The session's messages are deleted with it
+        if self
+            .conn()
+            .execute("DELETE FROM sessions WHERE name = ?1", [name])?
+            == 0
+        {
+            return Err(SessionError::NotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> SessionResult<()> {
+        validate_name(new_name)?;
+        let mut conn = self.conn();
+        let transaction = conn.transaction()?;
+        let mut metadata = read_metadata(&transaction, name)?;
+        metadata.name = new_name.to_string();
+
+        let inserted = transaction.execute(
+            "INSERT OR IGNORE INTO sessions (name, metadata, updated) VALUES (?1, ?2, ?3)",
+            params![
+                metadata.name,
+                serde_json::to_string(&metadata)?,
+                metadata.updated.timestamp_micros()
+            ],
+        )?;
+        if inserted == 0 {
+            return Err(SessionError::AlreadyExists(new_name.to_string()));
+        }
+        transaction.execute(
+            "UPDATE messages SET session = ?2 WHERE session = ?1",
+            [name, new_name],
+        )?;
+        transaction.execute("DELETE FROM sessions WHERE name = ?1", [name])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> SessionResult<Vec<SessionMetadata>> {
+        let escaped = query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query_metadata(
+            &self.conn(),
+            "SELECT metadata FROM sessions s
+             WHERE lower(s.name) LIKE ?1 ESCAPE '\\'
+                OR EXISTS (
+                    SELECT 1 FROM messages m
+                    WHERE m.session = s.name AND m.text LIKE ?1 ESCAPE '\\'
+                )
+             ORDER BY s.updated DESC",
+            [format!("%{}%", escaped)],
+        )
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use futures::StreamExt;
+use goose::agents::{AgentEvent, AgentFactory};
+use goose::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use goose::providers::errors::ProviderError;
+use mcp_core::content::Content;
+use mcp_core::role::Role;
+use mcp_core::tool::{Tool, ToolCall};
+use serde_json::json;
+
+/// System prompt and messages of every completion request, in order
+type Requests = Arc<Mutex<Vec<(String, Vec<Message>)>>>;
+
+const SUMMARY: &str = "The user asked for two listings which were both completed.";
+
+/// Mock provider that rejects long conversations and answers summarization requests
+#[derive(Clone)]
+struct MockProvider {
+    model_config: ModelConfig,
+    max_messages: usize,
+    requests: Requests,
+}
+
+#[async_trait::async_trait]
+impl Provider for MockProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model_config.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((system.to_string(), messages.to_vec()));
+
+        let usage = ProviderUsage::new("mock".to_string(), Usage::default());
+        if system.starts_with("You are compressing") {
+            return Ok((Message::assistant().with_text(SUMMARY), usage));
+        }
+        if messages.len() > self.max_messages {
+            return Err(ProviderError::ContextLengthExceeded(
+                "too many messages".to_string(),
+            ));
+        }
+        Ok((Message::assistant().with_text("done"), usage))
+    }
+}
+
+fn filler() -> String {
+    "hello ".repeat(100)
+}
+
+fn history() -> Vec<Message> {
+    vec![
+        Message::user().with_text("first request"),
+        Message::assistant().with_tool_request(
+            "1",
+            Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+        ),
+        Message::user().with_tool_response("1", Ok(vec![Content::text(filler())])),
+        Message::assistant().with_text(filler()),
+        Message::user().with_text(filler()),
+        Message::assistant().with_tool_request(
+            "2",
+            Ok(ToolCall::new(
+                "developer__shell",
+                json!({"command": "ls -a"}),
+            )),
+        ),
+        Message::user().with_tool_response("2", Ok(vec![Content::text(filler())])),
+        Message::assistant().with_text(filler()),
+        Message::user().with_text("final request"),
+    ]
+}
+
+#[tokio::test]
+async fn test_summarize_agent_compresses_history() -> Result<()> {
+    let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+    let provider = MockProvider {
+        model_config: ModelConfig::new("gpt-4o".to_string()).with_context_limit(Some(1000)),
+        max_messages: 7,
+        requests: requests.clone(),
+    };
+
+    let agent = AgentFactory::create("summarize", Box::new(provider)).unwrap();
+    let messages = history();
+
+    let mut reply = agent.reply(&messages).await?;
+    let mut responses = Vec::new();
+    while let Some(event) = reply.next().await {
+        if let AgentEvent::Message(message) = event? {
+            responses.push(message);
+        }
+    }
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].as_concat_text(), "done");
+
+    let requests = requests.lock().unwrap();
+    // Rejected request, one summarization request, then the accepted request
+    assert_eq!(requests.len(), 3);
+
+    let (_, summarize_request) = &requests[1];
+    let transcript = summarize_request[0].as_concat_text();
+    assert!(transcript.contains("first request"));
+    assert!(transcript.contains("developer__shell"));
+
+    let (_, sent) = &requests[2];
+    assert_eq!(sent[0].role, Role::User);
+    assert!(sent[0].as_concat_text().contains(SUMMARY));
+
+    // The most recent turns are kept verbatim
+    let kept = &sent[1..];
+    assert_eq!(kept, &messages[messages.len() - kept.len()..]);
+    assert_eq!(kept[0].role, Role::Assistant);
+
+    // Every tool response still follows its request
+    let mut requested = Vec::new();
+    for message in sent {
+        requested.extend(message.get_tool_request_ids());
+        for id in message.get_tool_response_ids() {
+            assert!(requested.contains(&id), "orphaned tool response {}", id);
+        }
+    }
+
+    Ok(())
+}
@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use futures::StreamExt;
-use goose::agents::AgentFactory;
+use goose::agents::{AgentEvent, AgentFactory};
 use goose::message::Message;
 use goose::model::ModelConfig;
 use goose::providers::base::Provider;
@@ -115,7 +115,12 @@ async fn run_truncate_test(
     let mut responses = Vec::new();
     while let Some(response_result) = reply_stream.next().await {
         match response_result {
-            Ok(response) => responses.push(response),
+            Ok(AgentEvent::Message(response)) => responses.push(response),
+            Ok(
+                AgentEvent::Delta(_)
+                | AgentEvent::ConfirmationRequired(_)
+                | AgentEvent::Progress(_),
+            ) => {}
             Err(e) => {
                 println!("Error: {:?}", e);
                 return Err(e);
@@ -6,6 +6,7 @@ import { useNavigate } from 'react-router-dom';
 import { More } from './icons';
 import { Settings, Grid, MessageSquare } from 'lucide-react';
 import { Button } from './ui/button';
+import { getApiUrl, getSecretKey } from '../config';
 
 interface VersionInfo {
   current_version: string;
@@ -35,8 +36,11 @@ export default function MoreMenu() {
     // Fetch available versions when the menu opens
     const fetchVersions = async () => {
       try {
-        const port = window.appConfig.get('GOOSE_PORT');
-        const response = await fetch(`http://127.0.0.1:${port}/agent/versions`);
+        const response = await fetch(getApiUrl('/agent/versions'), {
+          headers: {
+            'X-Secret-Key': getSecretKey(),
+          },
+        });
         if (!response.ok) {
           throw new Error(`HTTP error! status: ${response.status}`);
         }
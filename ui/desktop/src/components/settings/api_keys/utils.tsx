@@ -72,6 +72,9 @@ export async function getSecretsSettings(): Promise<Record<string, ProviderRespo
 export async function getProvidersList(): Promise<Provider[]> {
   const response = await fetch(getApiUrl('/agent/providers'), {
     method: 'GET',
+    headers: {
+      'X-Secret-Key': getSecretKey(),
+    },
   });
 
   if (!response.ok) {
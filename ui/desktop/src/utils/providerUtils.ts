@@ -37,6 +37,9 @@ export interface Provider {
 export async function getProvidersList(): Promise<Provider[]> {
   const response = await fetch(getApiUrl('/agent/providers'), {
     method: 'GET',
+    headers: {
+      'X-Secret-Key': getSecretKey(),
+    },
   });
 
   if (!response.ok) {
@@ -56,6 +59,23 @@ export async function getProvidersList(): Promise<Provider[]> {
   }));
 }
 
+// The agent session started last. Requests that don't name a session only reach it while
+// it is the server's only one, so the previous session is ended when a new one starts.
+let currentSessionId: string | null = null;
+
+const endAgent = async (sessionId: string) => {
+  const response = await fetch(getApiUrl(`/agent/${sessionId}`), {
+    method: 'DELETE',
+    headers: {
+      'X-Secret-Key': getSecretKey(),
+    },
+  });
+
+  if (!response.ok && response.status !== 404) {
+    console.error(`Failed to end agent session: ${response.statusText}`);
+  }
+};
+
 const addAgent = async (provider: string, model: string) => {
   const response = await fetch(getApiUrl('/agent'), {
     method: 'POST',
@@ -70,6 +90,12 @@ const addAgent = async (provider: string, model: string) => {
     throw new Error(`Failed to add agent: ${response.statusText}`);
   }
 
+  const { session_id: sessionId } = await response.json();
+  if (currentSessionId && currentSessionId !== sessionId) {
+    await endAgent(currentSessionId);
+  }
+  currentSessionId = sessionId;
+
   return response;
 };
 